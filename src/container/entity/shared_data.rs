@@ -8,7 +8,9 @@ use std::collections::VecDeque;
 use crate::{Direction, GfxType, VITALS_MAX};
 
 pub enum MovementType {
-    MovementBuffer,
+    /// Plays the next buffered move once the playback time in server
+    /// milliseconds reaches it. None plays it right away.
+    MovementBuffer(Option<u64>),
     Manual(u8, Option<Position>),
 }
 
@@ -188,6 +190,8 @@ pub struct EndMovement(pub Position);
 pub struct MovementData {
     pub end_pos: Position,
     pub dir: u8,
+    /// Server time in milliseconds the move was stamped with.
+    pub time: u64,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -1,4 +1,7 @@
-use crate::{Config, Result, SystemHolder, World, data_types::*, database::*};
+use crate::{
+    Config, NetStatsOverlay, Result, SystemHolder, World, data_types::*,
+    database::*, ui_scale_factor,
+};
 
 pub mod game_content;
 pub mod inputs;
@@ -22,6 +25,7 @@ pub struct Content {
     pub game_content: GameContent,
     pub content_type: ContentType,
     pub ping_start: MyInstant,
+    pub net_stats: NetStatsOverlay,
 }
 
 impl Content {
@@ -31,6 +35,7 @@ impl Content {
             menu_content: MenuContent::new(systems),
             game_content: GameContent::new(systems),
             ping_start: MyInstant::now(),
+            net_stats: NetStatsOverlay::new(systems),
         };
         content.menu_content.show(systems);
         content.game_content.hide(world, systems)?;
@@ -38,6 +43,53 @@ impl Content {
         Ok(content)
     }
 
+    /// Content with no graphics behind it, for running packet handlers
+    /// against SystemHolder::headless.
+    #[cfg(test)]
+    pub fn headless() -> Self {
+        Content {
+            content_type: ContentType::Menu,
+            menu_content: MenuContent::default(),
+            game_content: GameContent::headless(),
+            ping_start: MyInstant::now(),
+            net_stats: NetStatsOverlay::default(),
+        }
+    }
+
+    /// Lays the menu and the game interface out again once the UI scale
+    /// changed. Waits while a storage, shop or trade window is open.
+    pub fn update_layout(
+        &mut self,
+        world: &mut World,
+        systems: &mut SystemHolder,
+    ) -> Result<()> {
+        if !systems.settings.layout_changed()
+            || self.game_content.interface.has_server_window()
+        {
+            return Ok(());
+        }
+
+        systems.settings.layout_rebuilt();
+        systems.scale =
+            ui_scale_factor(&systems.renderer, systems.config.ui_scale);
+        info!("Laying out the UI for scale {}", systems.scale);
+
+        let realm_picked = self.menu_content.realm_picked;
+        self.menu_content.unload(systems);
+        self.menu_content = MenuContent::new(systems);
+        self.menu_content.realm_picked = realm_picked;
+
+        match self.content_type {
+            ContentType::Menu => self.menu_content.show(systems),
+            ContentType::Game => {
+                self.menu_content.hide(systems);
+                self.game_content.rebuild_interface(world, systems)?;
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn switch_content(
         &mut self,
         world: &mut World,
@@ -1,4 +1,4 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
 
 use graphics::*;
 
@@ -17,17 +17,23 @@ use crate::{
 };
 
 pub mod floating_text;
+pub mod interpolation;
 pub mod map;
 pub mod npc;
+pub mod pathfind;
 pub mod player;
 pub mod player_data;
+pub mod prediction;
 pub mod target;
 
 pub use floating_text::*;
+pub use interpolation::*;
 pub use map::*;
 pub use npc::*;
+pub use pathfind::*;
 pub use player::*;
 pub use player_data::*;
+pub use prediction::*;
 pub use target::*;
 
 const KEY_ATTACK: usize = 0;
@@ -66,8 +72,10 @@ pub struct GameContent {
     pub float_text: FloatingText,
     pub refresh_map: bool,
     pub can_move: bool,
-    pub reconnect_count: usize,
     pub move_keypressed: Vec<ControlKey>,
+    pub move_path: Option<MovePath>,
+    pub prediction: MovePrediction,
+    pub server_clock: ServerClock,
 }
 
 impl GameContent {
@@ -79,6 +87,28 @@ impl GameContent {
         let game_lights =
             systems.gfx.add_light(lights, 1, "Game Lights", false);
 
+        GameContent::with_widgets(
+            game_lights,
+            Interface::new(systems),
+            Target::new(systems),
+        )
+    }
+
+    /// Game content with no graphics behind it, for headless systems.
+    #[cfg(test)]
+    pub fn headless() -> Self {
+        GameContent::with_widgets(
+            GfxType::None,
+            Interface::headless(),
+            Target::default(),
+        )
+    }
+
+    fn with_widgets(
+        game_lights: GfxType,
+        interface: Interface,
+        target: Target,
+    ) -> Self {
         GameContent {
             players: Rc::new(RefCell::new(IndexSet::default())),
             npcs: Rc::new(RefCell::new(IndexSet::default())),
@@ -86,20 +116,22 @@ impl GameContent {
             game_lights,
             map: MapContent::new(),
             camera: Camera::new(Vec2::new(0.0, 0.0)),
-            interface: Interface::new(systems),
+            interface,
             keyinput: [false; MAX_KEY],
             finalized: false,
             myentity: None,
             in_game: false,
             player_data: PlayerData::new(),
-            target: Target::new(systems),
+            target,
             pick_up_timer: 0.0,
             current_music: String::new(),
             float_text: FloatingText::new(),
             refresh_map: false,
             can_move: true,
-            reconnect_count: 0,
             move_keypressed: Vec::with_capacity(4),
+            move_path: None,
+            prediction: MovePrediction::default(),
+            server_clock: ServerClock::default(),
         }
     }
 
@@ -140,6 +172,8 @@ impl GameContent {
         self.player_data.unload();
         self.float_text.unload(systems);
         self.camera.0 = Vec2::new(0.0, 0.0);
+        self.prediction.clear();
+        self.server_clock.reset();
         systems.caret.index = None;
         Ok(())
     }
@@ -179,7 +213,58 @@ impl GameContent {
         socket: &mut Poller,
     ) -> Result<()> {
         self.finalize_entity(world, systems, socket)?;
+        self.fill_interface(world, systems)?;
+
+        if let Some(myindex) = self.myentity {
+            if let Some(Entity::Player(p_data)) = world.entities.get(myindex) {
+                let mut size = systems.gfx.get_size(&p_data.hp_bar.bar_index);
+                size.x = get_percent(
+                    p_data.vitals.vital[0],
+                    p_data.vitals.vitalmax[0],
+                    18,
+                ) as f32;
+                systems.gfx.set_size(&p_data.hp_bar.bar_index, size);
+                systems.gfx.set_visible(
+                    &p_data.hp_bar.bar_index,
+                    p_data.hp_bar.visible,
+                );
+                systems.gfx.set_visible(
+                    &p_data.hp_bar.bg_index,
+                    p_data.hp_bar.visible,
+                );
+            }
+        }
+
+        systems.gfx.set_visible(&self.game_lights, true);
+
+        if let Some(music) = &get_map_music(systems, self.map.mapindex[0]) {
+            if self.current_music != *music {
+                self.current_music.clone_from(music);
+                systems.audio.set_music(format!("./audio/{}", music))?;
+            }
+        }
+
+        self.finalized = true;
+        Ok(())
+    }
 
+    /// Builds the interface again after the UI scale changed.
+    pub fn rebuild_interface(
+        &mut self,
+        world: &mut World,
+        systems: &mut SystemHolder,
+    ) -> Result<()> {
+        self.interface.rebuild(systems);
+        self.fill_interface(world, systems)
+    }
+
+    /// Shows the player data in the inventory, storage, profile and vital
+    /// bars.
+    fn fill_interface(
+        &mut self,
+        world: &mut World,
+        systems: &mut SystemHolder,
+    ) -> Result<()> {
         self.player_data.inventory.iter().enumerate().for_each(
             |(index, item)| {
                 self.interface
@@ -247,22 +332,6 @@ impl GameContent {
                     self.player_data.levelexp as i32,
                     nextexp as i32,
                 );
-
-                let mut size = systems.gfx.get_size(&p_data.hp_bar.bar_index);
-                size.x = get_percent(
-                    p_data.vitals.vital[0],
-                    p_data.vitals.vitalmax[0],
-                    18,
-                ) as f32;
-                systems.gfx.set_size(&p_data.hp_bar.bar_index, size);
-                systems.gfx.set_visible(
-                    &p_data.hp_bar.bar_index,
-                    p_data.hp_bar.visible,
-                );
-                systems.gfx.set_visible(
-                    &p_data.hp_bar.bg_index,
-                    p_data.hp_bar.visible,
-                );
             }
         }
 
@@ -274,16 +343,6 @@ impl GameContent {
             );
         }
 
-        systems.gfx.set_visible(&self.game_lights, true);
-
-        if let Some(music) = &get_map_music(systems, self.map.mapindex[0]) {
-            if self.current_music != *music {
-                self.current_music.clone_from(music);
-                systems.audio.set_music(format!("./audio/{}", music))?;
-            }
-        }
-
-        self.finalized = true;
         Ok(())
     }
 
@@ -368,12 +427,23 @@ impl GameContent {
         _world: &mut World,
         socket: &mut Poller,
         dir: Option<Direction>,
+    ) -> Result<()> {
+        let seq = self.prediction.next_seq();
+
+        self.send_player_move(socket, dir, seq)
+    }
+
+    fn send_player_move(
+        &mut self,
+        socket: &mut Poller,
+        dir: Option<Direction>,
+        seq: u32,
     ) -> Result<()> {
         if let Some(_myentity) = self.myentity {
             let dir = if let Some(dir) = dir {
                 enum_to_dir(dir)
             } else {
-                send_move(socket, None)?;
+                send_move(socket, None, seq)?;
                 return Ok(());
             };
 
@@ -383,11 +453,54 @@ impl GameContent {
                 _ => {}
             }
 
-            send_move(socket, Some(dir))?;
+            send_move(socket, Some(dir), seq)?;
         }
         Ok(())
     }
 
+    /// Sends a step and, when nothing is in the way, starts walking it at
+    /// once instead of waiting for the server. Steps onto another map wait
+    /// for the server so the maps load as before.
+    pub fn step_player(
+        &mut self,
+        world: &mut World,
+        systems: &mut SystemHolder,
+        socket: &mut Poller,
+        dir: Direction,
+    ) -> Result<()> {
+        let seq = self.prediction.next_seq();
+
+        if let Some(myentity) = self.myentity {
+            let idle = match world.entities.get(myentity) {
+                Some(Entity::Player(p_data)) => {
+                    !p_data.movement.is_moving
+                        && !p_data.attacking.0
+                        && p_data.movement_buffer.is_empty()
+                }
+                _ => false,
+            };
+
+            if idle
+                && can_move(world, systems, myentity, self, &dir)?
+                && let Some(from) = self.my_position(world)
+            {
+                let to = self.map.get_next_pos(from, &dir);
+
+                if to.map == from.map {
+                    move_player(
+                        world,
+                        systems,
+                        myentity,
+                        MovementType::Manual(enum_to_dir(dir), Some(to)),
+                    )?;
+                    self.prediction.push(seq, from, to, dir);
+                }
+            }
+        }
+
+        self.send_player_move(socket, Some(dir), seq)
+    }
+
     pub fn player_attack(
         &mut self,
         world: &mut World,
@@ -512,14 +625,179 @@ impl GameContent {
         Ok(())
     }
 
+    /// Keeps the most recently pressed direction first, so releasing it
+    /// falls back to one that is still held.
+    pub fn set_move_key(&mut self, key: ControlKey, pressed: bool) {
+        self.move_keypressed.retain(|held| *held != key);
+
+        if pressed {
+            self.move_keypressed.insert(0, key);
+        }
+    }
+
     pub fn reset_key_input(
         &mut self,
         world: &mut World,
         socket: &mut Poller,
     ) -> Result<()> {
         self.move_keypressed.clear();
+        self.move_path = None;
         self.move_player(world, socket, None)
     }
+
+    /// Starts walking to the tile under `screen_pos`. Returns false when
+    /// it can not be reached from the loaded maps.
+    pub fn start_path(
+        &mut self,
+        world: &mut World,
+        systems: &mut SystemHolder,
+        socket: &mut Poller,
+        screen_pos: Vec2,
+    ) -> Result<bool> {
+        let Some(pos) = self.my_position(world) else {
+            return Ok(false);
+        };
+
+        let goal = screen_to_tile(systems, self, screen_pos);
+        let Some(steps) = self.plan_path(world, systems, pos, goal) else {
+            return Ok(false);
+        };
+
+        self.move_path = Some(MovePath {
+            goal,
+            steps,
+            sent_from: None,
+        });
+        self.update_path(world, systems, socket)?;
+        Ok(true)
+    }
+
+    /// Stops a click-to-move walk, if one is going.
+    pub fn cancel_path(
+        &mut self,
+        world: &mut World,
+        socket: &mut Poller,
+    ) -> Result<()> {
+        if self.move_path.take().is_some() {
+            self.move_player(world, socket, None)?;
+        }
+        Ok(())
+    }
+
+    /// Sends the next step of the walk once the last one finished. The
+    /// stop goes out with the last step and before each turn, so the
+    /// server does not walk on while the step is drawn. A player that
+    /// ended up off the path, such as after a warp, gets a new path to the
+    /// same goal.
+    pub fn update_path(
+        &mut self,
+        world: &mut World,
+        systems: &mut SystemHolder,
+        socket: &mut Poller,
+    ) -> Result<()> {
+        let Some(mut path) = self.move_path.take() else {
+            return Ok(());
+        };
+
+        let pos = match self.myentity.and_then(|key| world.entities.get(key)) {
+            Some(Entity::Player(p_data)) => {
+                if p_data.movement.is_moving || p_data.attacking.0 {
+                    self.move_path = Some(path);
+                    return Ok(());
+                }
+
+                p_data.pos
+            }
+            _ => return Ok(()),
+        };
+
+        if pos == path.goal {
+            return self.move_player(world, socket, None);
+        }
+
+        if let Some(index) =
+            path.steps.iter().position(|(from, _)| *from == pos)
+        {
+            path.steps.drain(..index);
+        } else if let Some(steps) =
+            self.plan_path(world, systems, pos, path.goal)
+        {
+            path.steps = steps;
+        } else {
+            return self.move_player(world, socket, None);
+        }
+
+        let dir = path.steps.front().map(|(_, dir)| *dir);
+        let next = path.steps.get(1).map(|(_, dir)| *dir);
+        let send = path.sent_from != Some(pos);
+
+        path.sent_from = Some(pos);
+        self.move_path = Some(path);
+
+        if let Some(dir) = dir.filter(|_| send) {
+            self.step_player(world, systems, socket, dir)?;
+
+            if next != Some(dir) {
+                self.move_player(world, socket, None)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Where the local player stands, None before it is loaded.
+    pub fn my_position(&self, world: &World) -> Option<Position> {
+        match world.entities.get(self.myentity?) {
+            Some(Entity::Player(p_data)) => Some(p_data.pos),
+            _ => None,
+        }
+    }
+
+    /// A* over the loaded maps, with other players and npcs in the way.
+    fn plan_path(
+        &self,
+        world: &World,
+        systems: &SystemHolder,
+        from: Position,
+        goal: Position,
+    ) -> Option<VecDeque<(Position, Direction)>> {
+        let center = self.map.map_pos;
+        let start = to_grid_pos(from, center)?;
+        let target = to_grid_pos(goal, center)?;
+
+        let mut maps = PathMaps::default();
+
+        for (index, key) in self.map.mapindex.iter().enumerate() {
+            maps.maps[index] = systems
+                .base
+                .mapdata
+                .get(*key)
+                .map(|data| (&data.attributes, &data.dir_block));
+        }
+
+        maps.occupied = world
+            .entities
+            .iter()
+            .filter_map(|(key, entity_data)| match entity_data {
+                Entity::Player(p_data) if Some(key) != self.myentity => {
+                    to_grid_pos(p_data.pos, center)
+                }
+                Entity::Npc(n_data) => to_grid_pos(n_data.pos, center),
+                _ => None,
+            })
+            .collect();
+
+        let mut pos = start;
+        let steps = find_path(&maps, start, target)?
+            .into_iter()
+            .map(|dir| {
+                let step = (from_grid_pos(pos, center), dir);
+                pos = step_pos(pos, dir);
+                step
+            })
+            .collect();
+
+        Some(steps)
+    }
 }
 
 pub fn update_player(
@@ -530,11 +808,27 @@ pub fn update_player(
     buffer: &mut BufferTask,
     seconds: f32,
 ) -> Result<()> {
+    let playback_ms = content.server_clock.playback_time(seconds);
     let players = content.players.clone();
     for entity in players.borrow().iter() {
-        move_player(world, systems, *entity, MovementType::MovementBuffer)?;
+        // Our own moves are already late by a round trip, so play them now.
+        let playback_ms =
+            (Some(*entity) != content.myentity).then_some(playback_ms);
+
+        move_player(
+            world,
+            systems,
+            *entity,
+            MovementType::MovementBuffer(playback_ms),
+        )?;
         process_player_movement(
-            world, systems, socket, *entity, content, buffer,
+            world,
+            systems,
+            socket,
+            *entity,
+            content,
+            buffer,
+            playback_ms,
         )?;
         process_player_attack(world, systems, *entity, seconds)?
     }
@@ -548,10 +842,23 @@ pub fn update_npc(
     content: &mut GameContent,
     seconds: f32,
 ) -> Result<()> {
+    let playback_ms = content.server_clock.playback_time(seconds);
     let npcs = content.npcs.clone();
     for entity in npcs.borrow().iter() {
-        move_npc(world, systems, *entity, MovementType::MovementBuffer)?;
-        process_npc_movement(world, systems, *entity, socket, content)?;
+        move_npc(
+            world,
+            systems,
+            *entity,
+            MovementType::MovementBuffer(Some(playback_ms)),
+        )?;
+        process_npc_movement(
+            world,
+            systems,
+            *entity,
+            socket,
+            content,
+            playback_ms,
+        )?;
         process_npc_attack(world, systems, *entity, seconds)?;
     }
     Ok(())
@@ -4,7 +4,7 @@ use input::Key;
 use winit::{event_loop::ActiveEventLoop, keyboard::NamedKey};
 
 use crate::{
-    Alert, COLOR_RED, ContentType, Entity, EntityKind, MouseInputType,
+    Action, Alert, COLOR_RED, ContentType, Entity, EntityKind, MouseInputType,
     SystemHolder, Tooltip,
     content::*,
     data_types::*,
@@ -13,7 +13,7 @@ use crate::{
 
 use super::{
     KEY_ATTACK, KEY_MOVEDOWN, KEY_MOVELEFT, KEY_MOVERIGHT, KEY_MOVEUP,
-    KEY_PICKUP,
+    KEY_PICKUP, SelectedTextbox, Window, toggle_interface,
 };
 
 impl GameContent {
@@ -116,6 +116,10 @@ impl GameContent {
                     }
                     _ => {}
                 }
+            } else {
+                content
+                    .game_content
+                    .start_path(world, systems, socket, screen_pos)?;
             }
         }
 
@@ -136,15 +140,44 @@ impl GameContent {
             return Ok(());
         }
 
-        Interface::key_input(
+        if Interface::key_input(
             &mut content.game_content,
             world,
             systems,
             socket,
             key,
             pressed,
-        )?;
+        )? {
+            return Ok(());
+        }
 
+        // Held keys are still released while typing so movement can not get
+        // stuck, but nothing new starts from a key typed into a textbox.
+        let typing = content.game_content.interface.selected_textbox
+            != SelectedTextbox::None;
+        let action = systems
+            .keybinds
+            .action_for(key)
+            .filter(|_| !(typing && pressed));
+
+        if pressed && !typing {
+            content.game_content.cancel_path(world, socket)?;
+        }
+
+        GameContent::action_input(
+            content, world, systems, socket, action, pressed,
+        )
+    }
+
+    /// Runs a gameplay action for the keyboard or the gamepad.
+    pub fn action_input(
+        content: &mut Content,
+        world: &mut World,
+        systems: &mut SystemHolder,
+        socket: &mut Poller,
+        action: Option<Action>,
+        pressed: bool,
+    ) -> Result<()> {
         if content.game_content.interface.inventory.hold_slot.is_some()
             || content.game_content.interface.storage.hold_slot.is_some()
         {
@@ -155,81 +188,40 @@ impl GameContent {
             return Ok(());
         }
 
-        match key {
-            Key::Character('w') | Key::Character('W') => {
-                if let Some(index) = content
-                    .game_content
-                    .move_keypressed
-                    .iter()
-                    .position(|key| *key == ControlKey::MoveUp)
-                {
-                    let _ = content.game_content.move_keypressed.remove(index);
-                }
-
-                if pressed {
-                    content
-                        .game_content
-                        .move_keypressed
-                        .insert(0, ControlKey::MoveUp);
-                }
-            }
-            Key::Character('s') | Key::Character('S') => {
-                if let Some(index) = content
-                    .game_content
-                    .move_keypressed
-                    .iter()
-                    .position(|key| *key == ControlKey::MoveDown)
-                {
-                    let _ = content.game_content.move_keypressed.remove(index);
-                }
-
-                if pressed {
-                    content
-                        .game_content
-                        .move_keypressed
-                        .insert(0, ControlKey::MoveDown);
-                }
-            }
-            Key::Character('a') | Key::Character('A') => {
-                if let Some(index) = content
-                    .game_content
-                    .move_keypressed
-                    .iter()
-                    .position(|key| *key == ControlKey::MoveLeft)
-                {
-                    let _ = content.game_content.move_keypressed.remove(index);
-                }
-
-                if pressed {
-                    content
-                        .game_content
-                        .move_keypressed
-                        .insert(0, ControlKey::MoveLeft);
-                }
-            }
-            Key::Character('d') | Key::Character('D') => {
-                if let Some(index) = content
-                    .game_content
-                    .move_keypressed
-                    .iter()
-                    .position(|key| *key == ControlKey::MoveRight)
-                {
-                    let _ = content.game_content.move_keypressed.remove(index);
-                }
-
-                if pressed {
-                    content
-                        .game_content
-                        .move_keypressed
-                        .insert(0, ControlKey::MoveRight);
-                }
-            }
-            Key::Named(NamedKey::Control) => {
+        match action {
+            Some(Action::MoveUp) => content
+                .game_content
+                .set_move_key(ControlKey::MoveUp, pressed),
+            Some(Action::MoveDown) => content
+                .game_content
+                .set_move_key(ControlKey::MoveDown, pressed),
+            Some(Action::MoveLeft) => content
+                .game_content
+                .set_move_key(ControlKey::MoveLeft, pressed),
+            Some(Action::MoveRight) => content
+                .game_content
+                .set_move_key(ControlKey::MoveRight, pressed),
+            Some(Action::Attack) => {
                 content.game_content.keyinput[KEY_ATTACK] = pressed;
             }
-            Key::Named(NamedKey::Space) => {
+            Some(Action::PickUp) => {
                 content.game_content.keyinput[KEY_PICKUP] = pressed;
             }
+            Some(Action::OpenInventory) if pressed => toggle_interface(
+                &mut content.game_content.interface,
+                systems,
+                Window::Inventory,
+            ),
+            Some(Action::OpenProfile) if pressed => toggle_interface(
+                &mut content.game_content.interface,
+                systems,
+                Window::Profile,
+            ),
+            Some(Action::OpenSetting) if pressed => toggle_interface(
+                &mut content.game_content.interface,
+                systems,
+                Window::Setting,
+            ),
             _ => {}
         }
 
@@ -245,7 +237,9 @@ impl GameContent {
             };
 
             if let Some(dir) = move_dir {
-                content.game_content.move_player(world, socket, Some(dir))?;
+                content
+                    .game_content
+                    .step_player(world, systems, socket, dir)?;
             }
         } else {
             content.game_content.move_player(world, socket, None)?;
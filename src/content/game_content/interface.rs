@@ -7,15 +7,17 @@ use input::Key;
 use winit::keyboard::NamedKey;
 
 use crate::{
-    Alert, AlertIndex, AlertType, COLOR_WHITE, GameContent, GfxType,
-    MouseInputType, Result, SystemHolder, TradeStatus, World,
-    interface::chatbox::*, is_within_area, send_buyitem, send_closeshop,
-    send_closestorage, send_closetrade, send_command, send_message,
-    send_removetradeitem, send_submittrade, send_unequip,
-    send_updatetrademoney, send_useitem, socket, systems::Poller, widget::*,
+    Action, Alert, AlertIndex, AlertType, COLOR_WHITE, GameContent, GfxType,
+    MouseInputType, Result, SettingChange, SystemHolder, TradeStatus, World,
+    apply_setting, interface::chatbox::*, is_within_area, reset_settings,
+    send_buyitem, send_closeshop, send_closestorage, send_closetrade,
+    send_command, send_message, send_removetradeitem, send_submittrade,
+    send_unequip, send_updatetrademoney, send_useitem, socket, systems::Poller,
+    widget::*,
 };
 
 pub mod chatbox;
+mod focus_nav;
 mod inventory;
 mod item_description;
 mod profile;
@@ -26,6 +28,7 @@ mod storage;
 mod trade;
 
 pub use chatbox::*;
+use focus_nav::*;
 use inventory::*;
 use item_description::*;
 pub use profile::*;
@@ -46,13 +49,15 @@ pub enum Window {
     Trade,
 }
 
-#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
 pub enum SelectedTextbox {
+    #[default]
     None,
     Chatbox,
     Trade,
 }
 
+#[derive(Default)]
 pub struct Interface {
     pub ping_text: GfxType,
     pub average_ping: GfxType,
@@ -73,6 +78,7 @@ pub struct Interface {
     window_order: Vec<(Window, usize)>,
     drag_window: Option<Window>,
     pub selected_textbox: SelectedTextbox,
+    pub focus: FocusNav,
 }
 
 impl Interface {
@@ -177,12 +183,22 @@ impl Interface {
             window_order: Vec::with_capacity(7),
             drag_window: None,
             selected_textbox: SelectedTextbox::None,
+            focus: FocusNav::new(systems),
         };
 
         interface.add_window_order();
         interface
     }
 
+    /// An interface with no graphics behind it, for headless systems.
+    #[cfg(test)]
+    pub fn headless() -> Self {
+        let mut interface = Interface::default();
+
+        interface.add_window_order();
+        interface
+    }
+
     pub fn add_window_order(&mut self) {
         self.window_order.push((Window::Chatbox, 0));
         self.window_order.push((Window::Inventory, 1));
@@ -194,6 +210,38 @@ impl Interface {
         self.window_order.sort_by(|a, b| a.1.cmp(&b.1));
     }
 
+    /// True while a storage, shop or trade window is open. Their contents
+    /// come from the server, so the layout is not rebuilt under them.
+    pub fn has_server_window(&self) -> bool {
+        self.storage.visible || self.shop.visible || self.trade.visible
+    }
+
+    /// Builds every window again with the current `systems.scale` and opens
+    /// the inventory, profile and setting windows that were open. The chat
+    /// starts empty, and the caller fills the windows from the player data.
+    pub fn rebuild(&mut self, systems: &mut SystemHolder) {
+        let open: Vec<Window> = [
+            (Window::Inventory, self.inventory.visible),
+            (Window::Profile, self.profile.visible),
+            (Window::Setting, self.setting.visible),
+        ]
+        .into_iter()
+        .filter_map(|(window, visible)| visible.then_some(window))
+        .collect();
+
+        self.unload(systems);
+
+        for label in [self.ping_text, self.average_ping, self.frame_loop] {
+            systems.gfx.remove_gfx(&mut systems.renderer, &label);
+        }
+
+        *self = Interface::new(systems);
+
+        for window in open {
+            open_interface(self, systems, window);
+        }
+    }
+
     pub fn recreate(&mut self, systems: &mut SystemHolder) {
         self.menu_button = create_menu_button(systems);
         self.vitalbar = VitalBar::new(systems);
@@ -209,6 +257,7 @@ impl Interface {
         self.did_button_click = false;
         self.drag_window = None;
         self.selected_textbox = SelectedTextbox::None;
+        self.focus = FocusNav::new(systems);
         systems
             .gfx
             .set_visible(&self.ping_text, systems.config.show_ping);
@@ -220,6 +269,42 @@ impl Interface {
             .set_visible(&self.frame_loop, systems.config.show_frame_loop);
     }
 
+    /// Applies a change from the Setting window, including the overlay
+    /// labels the interface owns.
+    pub fn change_setting(
+        &mut self,
+        systems: &mut SystemHolder,
+        change: SettingChange,
+    ) {
+        if apply_setting(systems, change) {
+            self.update_overlay(systems, change);
+        }
+    }
+
+    /// Puts the Setting window options back to their defaults.
+    pub fn reset_settings(&mut self, systems: &mut SystemHolder) {
+        for change in reset_settings(systems) {
+            self.update_overlay(systems, change);
+        }
+
+        self.setting.refresh(systems);
+    }
+
+    fn update_overlay(
+        &self,
+        systems: &mut SystemHolder,
+        change: SettingChange,
+    ) {
+        let (label, show) = match change {
+            SettingChange::ShowPing(show) => (&self.ping_text, show),
+            SettingChange::ShowAveragePing(show) => (&self.average_ping, show),
+            SettingChange::ShowFrameLoop(show) => (&self.frame_loop, show),
+            _ => return,
+        };
+
+        systems.gfx.set_visible(label, show);
+    }
+
     pub fn unload(&mut self, systems: &mut SystemHolder) {
         self.menu_button.iter_mut().for_each(|button| {
             button.unload(systems);
@@ -234,6 +319,7 @@ impl Interface {
         self.trade.unload(systems);
         self.window_order.clear();
         self.item_desc.unload(systems);
+        self.focus.unload(systems);
         systems.gfx.set_visible(&self.ping_text, false);
         systems.gfx.set_visible(&self.average_ping, false);
         systems.gfx.set_visible(&self.frame_loop, false);
@@ -499,25 +585,20 @@ impl Interface {
                             .set_move_scroll(systems, screen_pos);
 
                         if interface.setting.bgm_scroll.in_hold {
-                            interface.setting.update_bgm_value(
+                            let volume = interface.setting.bgm_scroll.value;
+                            interface.setting.update_bgm_value(systems, volume);
+                            interface.change_setting(
                                 systems,
-                                interface.setting.bgm_scroll.value,
+                                SettingChange::BgmVolume(volume as u8),
                             );
-                            let volume = interface.setting.bgm_scroll.value
-                                as f32
-                                * 0.01;
-                            systems.audio.set_music_volume(volume);
                             result = true;
                         } else if interface.setting.sfx_scroll.in_hold {
-                            interface.setting.update_sfx_value(
+                            let volume = interface.setting.sfx_scroll.value;
+                            interface.setting.update_sfx_value(systems, volume);
+                            interface.change_setting(
                                 systems,
-                                interface.setting.sfx_scroll.value,
+                                SettingChange::SfxVolume(volume as u8),
                             );
-                            let volume = interface.setting.sfx_scroll.value
-                                as f32
-                                * 0.01;
-
-                            systems.audio.set_effect_volume(volume);
                             result = true;
                         }
                     }
@@ -585,13 +666,17 @@ impl Interface {
 
                 if interface.setting.visible {
                     if interface.setting.bgm_scroll.in_hold {
-                        systems.config.bgm_volume =
-                            interface.setting.bgm_scroll.value as u8;
-                        systems.config.save_config("settings.toml");
+                        let volume = interface.setting.bgm_scroll.value as u8;
+                        interface.change_setting(
+                            systems,
+                            SettingChange::BgmVolume(volume),
+                        );
                     } else if interface.setting.sfx_scroll.in_hold {
-                        systems.config.sfx_volume =
-                            interface.setting.sfx_scroll.value as u8;
-                        systems.config.save_config("settings.toml");
+                        let volume = interface.setting.sfx_scroll.value as u8;
+                        interface.change_setting(
+                            systems,
+                            SettingChange::SfxVolume(volume),
+                        );
                     }
 
                     interface
@@ -637,34 +722,43 @@ impl Interface {
         socket: &mut Poller,
         key: &Key,
         pressed: bool,
-    ) -> Result<()> {
+    ) -> Result<bool> {
+        if game_content.interface.setting.capture.is_some() {
+            if pressed {
+                game_content.interface.setting.capture_key(systems, key);
+            }
+
+            return Ok(true);
+        }
+
         if pressed && !game_content.interface.trade.visible {
-            if let Key::Named(NamedKey::Enter) = key {
-                if game_content.interface.selected_textbox
-                    == SelectedTextbox::Chatbox
-                {
-                    game_content.interface.selected_textbox =
-                        SelectedTextbox::None;
-                    game_content
-                        .interface
-                        .chatbox
-                        .textbox
-                        .set_select(systems, false);
-                    trigger_chatbox_button(
-                        &mut game_content.interface,
-                        systems,
-                        socket,
-                        2,
-                    )?;
-                } else {
-                    game_content.interface.selected_textbox =
-                        SelectedTextbox::Chatbox;
-                    game_content
-                        .interface
-                        .chatbox
-                        .textbox
-                        .set_select(systems, true);
-                }
+            let chat_selected = game_content.interface.selected_textbox
+                == SelectedTextbox::Chatbox;
+
+            if chat_selected && matches!(key, Key::Named(NamedKey::Enter)) {
+                game_content.interface.selected_textbox = SelectedTextbox::None;
+                game_content
+                    .interface
+                    .chatbox
+                    .textbox
+                    .set_select(systems, false);
+                trigger_chatbox_button(
+                    &mut game_content.interface,
+                    systems,
+                    socket,
+                    2,
+                )?;
+            } else if !chat_selected
+                && systems.keybinds.action_for(key) == Some(Action::FocusChat)
+            {
+                game_content.interface.selected_textbox =
+                    SelectedTextbox::Chatbox;
+                game_content
+                    .interface
+                    .chatbox
+                    .textbox
+                    .set_select(systems, true);
+                return Ok(true);
             }
         }
 
@@ -685,7 +779,7 @@ impl Interface {
             }
             _ => {}
         }
-        Ok(())
+        Ok(false)
     }
 
     pub fn hover_buttons(
@@ -821,25 +915,63 @@ impl Interface {
                             if let Some(index) =
                                 self.setting.click_buttons(systems, screen_pos)
                             {
-                                if index == 0 {
-                                    close_interface(
-                                        self,
-                                        systems,
-                                        Window::Setting,
-                                    );
-                                    return Ok(true);
+                                match index {
+                                    0 => {
+                                        close_interface(
+                                            self,
+                                            systems,
+                                            Window::Setting,
+                                        );
+                                        return Ok(true);
+                                    }
+                                    SETTING_BUTTON_UI_SCALE => {
+                                        let change = self
+                                            .setting
+                                            .trigger_ui_scale(systems);
+                                        self.change_setting(systems, change);
+                                    }
+                                    SETTING_BUTTON_KEYS => self
+                                        .setting
+                                        .set_page(systems, SettingPage::Keys),
+                                    SETTING_BUTTON_DEFAULT => {
+                                        self.reset_settings(systems)
+                                    }
+                                    SETTING_BUTTON_KEYS_BACK => {
+                                        self.setting.set_page(
+                                            systems,
+                                            SettingPage::General,
+                                        )
+                                    }
+                                    SETTING_BUTTON_KEYS_DEFAULT => {
+                                        self.setting.reset_keys(systems)
+                                    }
+                                    _ => {
+                                        if let Some(action) =
+                                            Setting::key_row_action(index)
+                                        {
+                                            self.setting
+                                                .start_capture(systems, action);
+                                        }
+                                    }
                                 }
 
                                 self.setting.did_button_click = true;
                             }
 
-                            if self.setting.sfx_scroll.in_scroll(screen_pos) {
+                            let general =
+                                self.setting.page == SettingPage::General;
+
+                            if general
+                                && self.setting.sfx_scroll.in_scroll(screen_pos)
+                            {
                                 self.setting
                                     .sfx_scroll
                                     .set_hold(systems, true, screen_pos);
                             }
 
-                            if self.setting.bgm_scroll.in_scroll(screen_pos) {
+                            if general
+                                && self.setting.bgm_scroll.in_scroll(screen_pos)
+                            {
                                 self.setting
                                     .bgm_scroll
                                     .set_hold(systems, true, screen_pos);
@@ -849,13 +981,12 @@ impl Interface {
                                 self.setting.click_checkbox(systems, screen_pos)
                             {
                                 self.setting.did_checkbox_click = true;
-                                self.setting.trigger_checkbox(
-                                    systems,
-                                    index,
-                                    &self.ping_text,
-                                    &self.average_ping,
-                                    &self.frame_loop,
-                                );
+
+                                if let Some(change) =
+                                    self.setting.trigger_checkbox(index)
+                                {
+                                    self.change_setting(systems, change);
+                                }
                             }
 
                             hold_interface(
@@ -1222,31 +1353,36 @@ fn trigger_button(
     index: usize,
 ) {
     match index {
-        0 => {
-            if interface.profile.visible {
-                close_interface(interface, systems, Window::Profile);
-            } else {
-                open_interface(interface, systems, Window::Profile);
-            }
-        }
-        1 => {
-            if interface.inventory.visible {
-                close_interface(interface, systems, Window::Inventory);
-            } else {
-                open_interface(interface, systems, Window::Inventory);
-            }
-        }
-        2 => {
-            if interface.setting.visible {
-                close_interface(interface, systems, Window::Setting);
-            } else {
-                open_interface(interface, systems, Window::Setting);
-            }
-        }
+        0 => toggle_interface(interface, systems, Window::Profile),
+        1 => toggle_interface(interface, systems, Window::Inventory),
+        2 => toggle_interface(interface, systems, Window::Setting),
         _ => {}
     }
 }
 
+/// Opens the window, or closes it when it is already open.
+pub fn toggle_interface(
+    interface: &mut Interface,
+    systems: &mut SystemHolder,
+    window: Window,
+) {
+    let visible = match window {
+        Window::Inventory => interface.inventory.visible,
+        Window::Profile => interface.profile.visible,
+        Window::Setting => interface.setting.visible,
+        Window::Chatbox => return,
+        Window::Storage => interface.storage.visible,
+        Window::Shop => interface.shop.visible,
+        Window::Trade => interface.trade.visible,
+    };
+
+    if visible {
+        close_interface(interface, systems, window);
+    } else {
+        open_interface(interface, systems, window);
+    }
+}
+
 fn trigger_chatbox_button(
     interface: &mut Interface,
     systems: &mut SystemHolder,
@@ -1,4 +1,5 @@
 use cosmic_text::{Attrs, Metrics};
+use educe::Educe;
 use graphics::*;
 
 use crate::{
@@ -20,7 +21,7 @@ pub struct Chat {
     channel: MessageChannel,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Default)]
 pub struct ChatTab {
     bg: GfxType,
     text: GfxType,
@@ -171,6 +172,8 @@ impl ChatTab {
     }
 }
 
+#[derive(Educe)]
+#[educe(Default)]
 pub struct Chatbox {
     window: GfxType,
     textbox_bg: GfxType,
@@ -187,6 +190,7 @@ pub struct Chatbox {
     chat: Vec<Chat>,
     chat_areasize: Vec2,
     chat_zorder: f32,
+    #[educe(Default = Bounds::new(0.0, 0.0, 0.0, 0.0))]
     chat_bounds: Bounds,
     chat_line_size: f32,
     chat_scroll_value: usize,
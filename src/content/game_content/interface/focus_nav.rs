@@ -0,0 +1,268 @@
+use graphics::*;
+
+use crate::{Direction, SystemHolder, data_types::*, widget::*};
+
+use super::{Interface, Window, interface_set_to_first, open_interface};
+
+/// Windows the gamepad focus can move through, in the order LB/RB cycle.
+const FOCUS_WINDOWS: [Window; 3] =
+    [Window::Inventory, Window::Shop, Window::Trade];
+
+/// A slot or button the gamepad focus can rest on, in screen position.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FocusArea {
+    pub pos: Vec2,
+    pub size: Vec2,
+}
+
+impl FocusArea {
+    pub fn new(pos: Vec2, size: Vec2) -> Self {
+        FocusArea { pos, size }
+    }
+
+    pub fn from_button(button: &Button, systems: &SystemHolder) -> Self {
+        FocusArea {
+            pos: button.base_pos
+                + (button.adjust_pos * systems.scale as f32).floor(),
+            size: (button.size * systems.scale as f32).floor(),
+        }
+    }
+
+    pub fn center(&self) -> Vec2 {
+        self.pos + self.size * 0.5
+    }
+}
+
+/// The area to move to from `current` when pushing `dir`. Picks the
+/// nearest area on that side, counting sideways distance double so the
+/// focus keeps to its row or column. Screen y grows upward.
+pub fn next_focus(
+    areas: &[FocusArea],
+    current: usize,
+    dir: Direction,
+) -> Option<usize> {
+    let from = areas.get(current)?.center();
+
+    areas
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| *index != current)
+        .filter_map(|(index, area)| {
+            let offset = area.center() - from;
+            let (ahead, side) = match dir {
+                Direction::Up => (offset.y, offset.x),
+                Direction::Down => (-offset.y, offset.x),
+                Direction::Left => (-offset.x, offset.y),
+                Direction::Right => (offset.x, offset.y),
+            };
+
+            (ahead > 1.0).then_some((index, ahead + side.abs() * 2.0))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(index, _)| index)
+}
+
+/// Gamepad focus over the inventory, shop and trade windows. The focused
+/// area is outlined, and pad buttons act on its center as mouse input.
+#[derive(Default)]
+pub struct FocusNav {
+    pub active: bool,
+    window: Option<Window>,
+    index: usize,
+    highlight: GfxType,
+}
+
+impl FocusNav {
+    pub fn new(systems: &mut SystemHolder) -> Self {
+        let mut rect = Rect::new(&mut systems.renderer, 0);
+        rect.set_position(Vec3::new(0.0, 0.0, ORDER_FOCUS_HIGHLIGHT))
+            .set_color(Color::rgba(0, 0, 0, 0))
+            .set_border_width(2.0)
+            .set_border_color(Color::rgba(230, 200, 60, 255));
+        let highlight = systems.gfx.add_rect(rect, 0, "Focus Highlight", false);
+
+        FocusNav {
+            active: false,
+            window: None,
+            index: 0,
+            highlight,
+        }
+    }
+
+    pub fn unload(&mut self, systems: &mut SystemHolder) {
+        systems
+            .gfx
+            .remove_gfx(&mut systems.renderer, &self.highlight);
+    }
+}
+
+impl Interface {
+    /// Turns gamepad focus mode on or off. Opens the inventory when none
+    /// of the focus windows are open.
+    pub fn set_focus_mode(&mut self, systems: &mut SystemHolder, active: bool) {
+        self.focus.active = active;
+
+        if active && self.focus_windows().is_empty() {
+            open_interface(self, systems, Window::Inventory);
+        }
+
+        if !active {
+            systems.gfx.set_visible(&self.focus.highlight, false);
+        }
+    }
+
+    /// Moves the focus within the focused window. Returns the screen
+    /// position it now points at.
+    pub fn move_focus(
+        &mut self,
+        systems: &mut SystemHolder,
+        dir: Direction,
+    ) -> Option<Vec2> {
+        let window = self.focused_window(systems)?;
+        let areas = self.focus_areas(systems, window);
+
+        if let Some(index) = next_focus(&areas, self.focus.index, dir) {
+            self.focus.index = index;
+        }
+
+        self.focus_pos(systems)
+    }
+
+    /// Moves the focus to the next or previous open focus window.
+    pub fn cycle_focus_window(
+        &mut self,
+        systems: &mut SystemHolder,
+        forward: bool,
+    ) -> Option<Vec2> {
+        let windows = self.focus_windows();
+        let current = self.focused_window(systems)?;
+        let position = windows.iter().position(|window| *window == current)?;
+        let next = if forward {
+            (position + 1) % windows.len()
+        } else {
+            (position + windows.len() - 1) % windows.len()
+        };
+
+        self.focus.window = Some(windows[next]);
+        self.focus.index = 0;
+        interface_set_to_first(self, systems, windows[next]);
+        self.focus_pos(systems)
+    }
+
+    /// The screen position of the focused area. Windows move and close
+    /// under the focus, so this also refreshes the outline.
+    pub fn focus_pos(&mut self, systems: &mut SystemHolder) -> Option<Vec2> {
+        let area = self.focused_window(systems).and_then(|window| {
+            let areas = self.focus_areas(systems, window);
+            self.focus.index =
+                self.focus.index.min(areas.len().saturating_sub(1));
+            areas.get(self.focus.index).copied()
+        });
+
+        let Some(area) = area else {
+            systems.gfx.set_visible(&self.focus.highlight, false);
+            return None;
+        };
+
+        let mut pos = systems.gfx.get_pos(&self.focus.highlight);
+        pos.x = area.pos.x - 2.0;
+        pos.y = area.pos.y - 2.0;
+        systems.gfx.set_pos(&self.focus.highlight, pos);
+        systems
+            .gfx
+            .set_size(&self.focus.highlight, area.size + Vec2::new(4.0, 4.0));
+        systems.gfx.set_visible(&self.focus.highlight, true);
+
+        Some(area.center())
+    }
+
+    /// True while an item is picked up, so confirming drops it.
+    pub fn is_holding_slot(&self) -> bool {
+        self.inventory.hold_slot.is_some() || self.storage.hold_slot.is_some()
+    }
+
+    fn focus_windows(&self) -> Vec<Window> {
+        FOCUS_WINDOWS
+            .into_iter()
+            .filter(|window| match window {
+                Window::Inventory => self.inventory.visible,
+                Window::Shop => self.shop.visible,
+                Window::Trade => self.trade.visible,
+                _ => false,
+            })
+            .collect()
+    }
+
+    fn focused_window(&mut self, systems: &mut SystemHolder) -> Option<Window> {
+        let windows = self.focus_windows();
+
+        if let Some(window) = self.focus.window
+            && windows.contains(&window)
+        {
+            return Some(window);
+        }
+
+        // Start on the open window nearest the front.
+        let window = self
+            .window_order
+            .iter()
+            .map(|(window, _)| *window)
+            .find(|window| windows.contains(window))?;
+
+        self.focus.window = Some(window);
+        self.focus.index = 0;
+        interface_set_to_first(self, systems, window);
+        Some(window)
+    }
+
+    fn focus_areas(
+        &self,
+        systems: &SystemHolder,
+        window: Window,
+    ) -> Vec<FocusArea> {
+        match window {
+            Window::Inventory => self.inventory.focus_areas(systems),
+            Window::Shop => self.shop.focus_areas(systems),
+            Window::Trade => self.trade.focus_areas(systems),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(columns: usize, rows: usize) -> Vec<FocusArea> {
+        (0..columns * rows)
+            .map(|i| {
+                let x = (i % columns) as f32;
+                let y = (i / columns) as f32;
+                FocusArea::new(
+                    Vec2::new(10.0 + 37.0 * x, 195.0 - 37.0 * y),
+                    Vec2::new(32.0, 32.0),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn focus_follows_rows_and_columns() {
+        let mut areas = grid(5, 3);
+
+        assert_eq!(next_focus(&areas, 0, Direction::Right), Some(1));
+        assert_eq!(next_focus(&areas, 1, Direction::Down), Some(6));
+        assert_eq!(next_focus(&areas, 6, Direction::Up), Some(1));
+        assert_eq!(next_focus(&areas, 0, Direction::Left), None);
+        assert_eq!(next_focus(&areas, 14, Direction::Down), None);
+
+        // A wide button under the grid is reached from any column.
+        areas.push(FocusArea::new(
+            Vec2::new(10.0, 80.0),
+            Vec2::new(180.0, 20.0),
+        ));
+        assert_eq!(next_focus(&areas, 14, Direction::Down), Some(15));
+        assert_eq!(next_focus(&areas, 15, Direction::Up), Some(12));
+        assert_eq!(next_focus(&areas, 3, Direction::Right), Some(4));
+    }
+}
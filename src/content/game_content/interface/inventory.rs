@@ -6,7 +6,7 @@ use crate::{
     widget::*,
 };
 
-use super::ItemDescription;
+use super::{FocusArea, ItemDescription};
 
 const MAX_INV_X: f32 = 5.0;
 
@@ -22,6 +22,7 @@ struct ItemSlot {
     count_data: u16,
 }
 
+#[derive(Default)]
 pub struct Inventory {
     pub visible: bool,
     bg: GfxType,
@@ -363,12 +364,7 @@ impl Inventory {
                     .floor(),
         );
 
-        let sprite =
-            if let Some(itemdata) = systems.base.item.get(data.num as usize) {
-                itemdata.sprite as usize
-            } else {
-                0
-            };
+        let sprite = systems.base.get_item(data.num as usize).sprite as usize;
 
         let mut image = Image::new(
             Some(systems.resource.items[sprite].allocation),
@@ -495,6 +491,32 @@ impl Inventory {
         None
     }
 
+    /// Every slot, empty ones included so held items can be dropped there.
+    pub fn focus_areas(&self, systems: &SystemHolder) -> Vec<FocusArea> {
+        (0..MAX_INV)
+            .map(|slot| {
+                let frame_pos = Vec2::new(
+                    slot as f32 % MAX_INV_X,
+                    (slot as f32 / MAX_INV_X).floor(),
+                );
+
+                FocusArea::new(
+                    Vec2::new(
+                        self.pos.x
+                            + ((10.0 + (37.0 * frame_pos.x))
+                                * systems.scale as f32)
+                                .floor(),
+                        self.pos.y
+                            + ((195.0 - (37.0 * frame_pos.y))
+                                * systems.scale as f32)
+                                .floor(),
+                    ),
+                    (Vec2::new(32.0, 32.0) * systems.scale as f32).floor(),
+                )
+            })
+            .collect()
+    }
+
     pub fn in_window(&mut self, screen_pos: Vec2) -> bool {
         if !self.visible {
             return false;
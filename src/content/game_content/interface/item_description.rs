@@ -19,6 +19,7 @@ struct ItemDescData {
     size: Vec2,
 }
 
+#[derive(Default)]
 pub struct ItemDescription {
     pub visible: bool,
     bg: GfxType,
@@ -83,7 +84,7 @@ impl ItemDescription {
             }
         }
 
-        let item_base = &systems.base.item[index];
+        let item_base = systems.base.get_item(index);
 
         let mut text_holder = Vec::with_capacity(4);
         text_holder.push(item_base.name.clone());
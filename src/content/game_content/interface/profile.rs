@@ -19,6 +19,7 @@ struct EqData {
     index: usize,
 }
 
+#[derive(Default)]
 pub struct Profile {
     pub visible: bool,
     bg: GfxType,
@@ -758,7 +759,7 @@ impl Profile {
             self.pos.y + (10.0 * systems.scale as f32).floor(),
         );
 
-        let item_sprite = systems.base.item[item.num as usize].sprite;
+        let item_sprite = systems.base.get_item(item.num as usize).sprite;
 
         let mut img = Image::new(
             Some(systems.resource.items[item_sprite as usize].allocation),
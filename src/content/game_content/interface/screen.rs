@@ -1,6 +1,7 @@
 use crate::{data_types::*, get_percent, widget::*, SystemHolder};
 use graphics::*;
 
+#[derive(Default)]
 pub struct VitalBar {
     bg: GfxType,
     bar_bg: [GfxType; 3],
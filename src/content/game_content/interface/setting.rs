@@ -1,9 +1,105 @@
 use graphics::*;
 
+use input::Key;
+use winit::keyboard::NamedKey;
+
 use crate::{
-    data_types::*, is_within_area, logic::*, widget::*, Interface, SystemHolder,
+    Action, ClientPresentMode, Interface, KEYBINDS_FILE, Keybinds,
+    SettingChange, SystemHolder, data_types::*, is_within_area, key_name,
+    logic::*, next_ui_scale, widget::*,
 };
 
+pub const SETTING_BUTTON_UI_SCALE: usize = 1;
+pub const SETTING_BUTTON_KEYS: usize = 2;
+pub const SETTING_BUTTON_DEFAULT: usize = 3;
+pub const SETTING_BUTTON_KEYS_BACK: usize = 4;
+pub const SETTING_BUTTON_KEYS_DEFAULT: usize = 5;
+/// First of the key binding rows, one per `Action::GAMEPLAY` entry.
+pub const SETTING_BUTTON_KEY_ROW: usize = 6;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SettingPage {
+    #[default]
+    General,
+    Keys,
+}
+
+impl SettingPage {
+    fn title(&self) -> &'static str {
+        match self {
+            SettingPage::General => "Setting",
+            SettingPage::Keys => "Key Bindings",
+        }
+    }
+}
+
+/// The page a button belongs to, None for the close button.
+fn button_page(index: usize) -> Option<SettingPage> {
+    match index {
+        0 => None,
+        SETTING_BUTTON_UI_SCALE..=SETTING_BUTTON_DEFAULT => {
+            Some(SettingPage::General)
+        }
+        _ => Some(SettingPage::Keys),
+    }
+}
+
+fn ui_scale_text(scale: f32) -> String {
+    format!("Scale {}%", (scale * 100.0).round())
+}
+
+fn key_row_text(keybinds: &Keybinds, action: Action) -> String {
+    let keys = keybinds.keys(action);
+
+    if keys.is_empty() {
+        format!("{}: None", action.label())
+    } else {
+        format!("{}: {}", action.label(), keys.join(" / "))
+    }
+}
+
+fn text_button(
+    systems: &mut SystemHolder,
+    w_pos: Vec3,
+    z_order: f32,
+    text: String,
+    pos: Vec2,
+    size: Vec2,
+) -> Button {
+    Button::new(
+        systems,
+        ButtonType::Rect(ButtonRect {
+            rect_color: Color::rgba(70, 70, 70, 255),
+            got_border: true,
+            border_color: Color::rgba(20, 20, 20, 255),
+            border_radius: 0.0,
+            hover_change: ButtonChangeType::ColorChange(Color::rgba(
+                50, 50, 50, 255,
+            )),
+            click_change: ButtonChangeType::ColorChange(Color::rgba(
+                150, 150, 150, 255,
+            )),
+        }),
+        ButtonContentType::Text(ButtonContentText {
+            text,
+            pos: Vec2::new(0.0, ((size.y - 20.0) * 0.5).max(0.0).floor()),
+            color: Color::rgba(200, 200, 200, 255),
+            render_layer: 1,
+            hover_change: ButtonChangeType::None,
+            click_change: ButtonChangeType::None,
+        }),
+        Vec2::new(w_pos.x, w_pos.y),
+        pos,
+        z_order,
+        (0.0001, 4),
+        size,
+        0,
+        false,
+        None,
+    )
+}
+
+#[derive(Default)]
 pub struct Setting {
     pub visible: bool,
     bg: GfxType,
@@ -27,6 +123,9 @@ pub struct Setting {
     header_size: Vec2,
     pub did_button_click: bool,
     pub did_checkbox_click: bool,
+    pub page: SettingPage,
+    /// Action waiting for a key on the key binding page.
+    pub capture: Option<Action>,
 
     min_bound: Vec2,
     max_bound: Vec2,
@@ -159,7 +258,8 @@ impl Setting {
         );
         bgm_scroll.set_value(systems, systems.config.bgm_volume as usize);
 
-        let mut button = Vec::with_capacity(1);
+        let mut button =
+            Vec::with_capacity(SETTING_BUTTON_KEY_ROW + Action::GAMEPLAY.len());
         let close_button = Button::new(
             systems,
             ButtonType::Rect(ButtonRect {
@@ -193,6 +293,50 @@ impl Setting {
         );
         button.push(close_button);
 
+        for (text, pos, size) in [
+            (
+                ui_scale_text(systems.config.ui_scale),
+                Vec2::new(10.0, 10.0),
+                Vec2::new(78.0, 24.0),
+            ),
+            (
+                "Keys".to_string(),
+                Vec2::new(92.0, 10.0),
+                Vec2::new(45.0, 24.0),
+            ),
+            (
+                "Default".to_string(),
+                Vec2::new(141.0, 10.0),
+                Vec2::new(49.0, 24.0),
+            ),
+            (
+                "Back".to_string(),
+                Vec2::new(10.0, 5.0),
+                Vec2::new(85.0, 24.0),
+            ),
+            (
+                "Default".to_string(),
+                Vec2::new(105.0, 5.0),
+                Vec2::new(85.0, 24.0),
+            ),
+        ] {
+            let page_button =
+                text_button(systems, w_pos, detail_2, text, pos, size);
+            button.push(page_button);
+        }
+
+        for (i, action) in Action::GAMEPLAY.into_iter().enumerate() {
+            let row = text_button(
+                systems,
+                w_pos,
+                detail_2,
+                key_row_text(&systems.keybinds, action),
+                Vec2::new(10.0, 212.0 - (i as f32 * 20.0)),
+                Vec2::new(180.0, 19.0),
+            );
+            button.push(row);
+        }
+
         let mut label = Vec::with_capacity(2);
         for i in 0..2 {
             let (msg, ypos) = match i {
@@ -441,11 +585,56 @@ impl Setting {
                 false,
                 None,
             ),
+            Checkbox::new(
+                systems,
+                CheckboxType::Rect(CheckboxRect {
+                    rect_color: Color::rgba(100, 100, 100, 255),
+                    got_border: true,
+                    border_color: Color::rgba(50, 50, 50, 255),
+                    border_radius: 2.0,
+                    hover_change: CheckboxChangeType::ColorChange(Color::rgba(
+                        140, 140, 140, 255,
+                    )),
+                    click_change: CheckboxChangeType::ColorChange(Color::rgba(
+                        70, 70, 70, 255,
+                    )),
+                }),
+                CheckType::SetRect(CheckRect {
+                    rect_color: Color::rgba(200, 200, 200, 255),
+                    got_border: false,
+                    border_color: Color::rgba(255, 255, 255, 255),
+                    border_radius: 2.0,
+                    pos: Vec2::new(5.0, 5.0),
+                    size: Vec2::new(14.0, 14.0),
+                }),
+                Vec2::new(w_pos.x, w_pos.y),
+                Vec2::new(10.0, orig_size.y - 226.0),
+                detail_1,
+                (0.0001, 4),
+                Vec2::new(24.0, 24.0),
+                0,
+                Some(CheckboxText {
+                    text: "Enable VSync?".to_string(),
+                    offset_pos: Vec2::new(3.0, 2.0),
+                    render_layer: 1,
+                    label_size: Vec2::new(180.0, 20.0),
+                    color: Color::rgba(200, 200, 200, 255),
+                    hover_change: CheckboxChangeType::ColorChange(Color::rgba(
+                        240, 240, 240, 255,
+                    )),
+                    click_change: CheckboxChangeType::ColorChange(Color::rgba(
+                        80, 80, 80, 255,
+                    )),
+                }),
+                false,
+                None,
+            ),
         ];
         checkbox[0].set_value(systems, systems.config.show_fps);
         checkbox[1].set_value(systems, systems.config.show_ping);
         checkbox[2].set_value(systems, systems.config.show_average_ping);
         checkbox[3].set_value(systems, systems.config.show_frame_loop);
+        checkbox[4].set_value(systems, systems.config.present_mode.is_vsync());
 
         Setting {
             visible: false,
@@ -470,6 +659,8 @@ impl Setting {
             header_size,
             did_button_click: false,
             did_checkbox_click: false,
+            page: SettingPage::General,
+            capture: None,
 
             min_bound: Vec2::new(
                 systems.size.width - w_size.x - 1.0,
@@ -514,19 +705,50 @@ impl Setting {
         systems.gfx.set_visible(&self.bg, visible);
         systems.gfx.set_visible(&self.header, visible);
         systems.gfx.set_visible(&self.header_text, visible);
-        self.sfx_scroll.set_visible(systems, visible);
-        self.bgm_scroll.set_visible(systems, visible);
-        self.button.iter_mut().for_each(|button| {
+
+        if !visible && self.capture.take().is_some() {
+            self.set_header(systems, self.page.title());
+        }
+
+        self.show_page(systems);
+    }
+
+    pub fn set_page(&mut self, systems: &mut SystemHolder, page: SettingPage) {
+        self.capture = None;
+        self.page = page;
+        self.set_header(systems, page.title());
+        self.show_page(systems);
+    }
+
+    fn show_page(&mut self, systems: &mut SystemHolder) {
+        let general = self.visible && self.page == SettingPage::General;
+        let keys = self.visible && self.page == SettingPage::Keys;
+
+        self.sfx_scroll.set_visible(systems, general);
+        self.bgm_scroll.set_visible(systems, general);
+        for (index, button) in self.button.iter_mut().enumerate() {
+            let visible = match button_page(index) {
+                None => self.visible,
+                Some(SettingPage::General) => general,
+                Some(SettingPage::Keys) => keys,
+            };
             button.set_visible(systems, visible);
-        });
+        }
         self.checkbox.iter_mut().for_each(|checkbox| {
-            checkbox.set_visible(systems, visible);
+            checkbox.set_visible(systems, general);
         });
         self.label.iter().for_each(|text| {
-            systems.gfx.set_visible(text, visible);
+            systems.gfx.set_visible(text, general);
         });
-        systems.gfx.set_visible(&self.bgm_label, visible);
-        systems.gfx.set_visible(&self.sfx_label, visible);
+        systems.gfx.set_visible(&self.bgm_label, general);
+        systems.gfx.set_visible(&self.sfx_label, general);
+    }
+
+    fn set_header(&mut self, systems: &mut SystemHolder, msg: &str) {
+        systems
+            .gfx
+            .set_text(&mut systems.renderer, &self.header_text, msg);
+        systems.gfx.center_text(&self.header_text);
     }
 
     pub fn can_hold(&mut self, screen_pos: Vec2) -> bool {
@@ -724,7 +946,10 @@ impl Setting {
         systems: &mut SystemHolder,
         screen_pos: Vec2,
     ) {
-        if !self.visible || self.order_index != 0 {
+        if !self.visible
+            || self.order_index != 0
+            || self.page != SettingPage::General
+        {
             return;
         }
 
@@ -749,7 +974,11 @@ impl Setting {
             return;
         }
 
-        for button in self.button.iter_mut() {
+        for (index, button) in self.button.iter_mut().enumerate() {
+            if button_page(index).is_some_and(|page| page != self.page) {
+                continue;
+            }
+
             if is_within_area(
                 screen_pos,
                 Vec2::new(
@@ -778,6 +1007,10 @@ impl Setting {
 
         let mut button_found = None;
         for (index, button) in self.button.iter_mut().enumerate() {
+            if button_page(index).is_some_and(|page| page != self.page) {
+                continue;
+            }
+
             if is_within_area(
                 screen_pos,
                 Vec2::new(
@@ -812,6 +1045,10 @@ impl Setting {
         tooltip: &mut Tooltip,
         screen_pos: Vec2,
     ) {
+        if self.page != SettingPage::General {
+            return;
+        }
+
         for checkbox in self.checkbox.iter_mut() {
             if is_within_area(
                 screen_pos,
@@ -845,6 +1082,10 @@ impl Setting {
         systems: &mut SystemHolder,
         screen_pos: Vec2,
     ) -> Option<usize> {
+        if self.page != SettingPage::General {
+            return None;
+        }
+
         let mut checkbox_found = None;
         for (index, checkbox) in self.checkbox.iter_mut().enumerate() {
             if is_within_area(
@@ -870,47 +1111,120 @@ impl Setting {
         checkbox_found
     }
 
-    pub fn trigger_checkbox(
+    /// The setting a clicked checkbox stands for, with its new value.
+    pub fn trigger_checkbox(&self, index: usize) -> Option<SettingChange> {
+        let value = self.checkbox.get(index)?.value;
+
+        match index {
+            0 => Some(SettingChange::ShowFps(value)),
+            1 => Some(SettingChange::ShowPing(value)),
+            2 => Some(SettingChange::ShowAveragePing(value)),
+            3 => Some(SettingChange::ShowFrameLoop(value)),
+            4 => Some(SettingChange::PresentMode(if value {
+                ClientPresentMode::AutoVsync
+            } else {
+                ClientPresentMode::AutoNoVsync
+            })),
+            _ => None,
+        }
+    }
+
+    /// Cycles the UI scale button to the next step.
+    pub fn trigger_ui_scale(
+        &mut self,
+        systems: &mut SystemHolder,
+    ) -> SettingChange {
+        let scale = next_ui_scale(systems.config.ui_scale);
+        self.button[SETTING_BUTTON_UI_SCALE]
+            .change_text(systems, ui_scale_text(scale));
+        SettingChange::UiScale(scale)
+    }
+
+    /// Syncs every control with the values in the config, used after the
+    /// settings were reset.
+    pub fn refresh(&mut self, systems: &mut SystemHolder) {
+        let bgm_volume = systems.config.bgm_volume as usize;
+        let sfx_volume = systems.config.sfx_volume as usize;
+
+        self.bgm_scroll.set_value(systems, bgm_volume);
+        self.sfx_scroll.set_value(systems, sfx_volume);
+        self.update_bgm_value(systems, bgm_volume);
+        self.update_sfx_value(systems, sfx_volume);
+
+        let values = [
+            systems.config.show_fps,
+            systems.config.show_ping,
+            systems.config.show_average_ping,
+            systems.config.show_frame_loop,
+            systems.config.present_mode.is_vsync(),
+        ];
+
+        for (checkbox, value) in self.checkbox.iter_mut().zip(values) {
+            checkbox.set_value(systems, value);
+        }
+
+        self.button[SETTING_BUTTON_UI_SCALE]
+            .change_text(systems, ui_scale_text(systems.config.ui_scale));
+    }
+
+    /// The action of a key binding row.
+    pub fn key_row_action(index: usize) -> Option<Action> {
+        index
+            .checked_sub(SETTING_BUTTON_KEY_ROW)
+            .and_then(|row| Action::GAMEPLAY.get(row).copied())
+    }
+
+    /// Waits for the next key press to bind it to `action`.
+    pub fn start_capture(
         &mut self,
         systems: &mut SystemHolder,
-        index: usize,
-        ping_index: &GfxType,
-        average_ping_index: &GfxType,
-        frame_jitter_index: &GfxType,
+        action: Action,
     ) {
-        #[allow(clippy::single_match)]
-        match index {
-            0 => {
-                systems.config.show_fps = self.checkbox[index].value;
-                systems
-                    .gfx
-                    .set_visible(&systems.fps, systems.config.show_fps);
-                systems.config.save_config("settings.toml");
-            }
-            1 => {
-                systems.config.show_ping = self.checkbox[index].value;
-                systems
-                    .gfx
-                    .set_visible(ping_index, systems.config.show_ping);
-                systems.config.save_config("settings.toml");
-            }
-            2 => {
-                systems.config.show_average_ping = self.checkbox[index].value;
-                systems.gfx.set_visible(
-                    average_ping_index,
-                    systems.config.show_average_ping,
-                );
-                systems.config.save_config("settings.toml");
+        self.capture = Some(action);
+        self.set_header(systems, &format!("Press a key: {}", action.label()));
+    }
+
+    /// Binds the pressed key to the action waiting for one. Escape cancels,
+    /// and a key used by another action is refused with its name shown.
+    pub fn capture_key(&mut self, systems: &mut SystemHolder, key: &Key) {
+        let Some(action) = self.capture.take() else {
+            return;
+        };
+
+        if let Key::Named(NamedKey::Escape) = key {
+            self.set_header(systems, self.page.title());
+            return;
+        }
+
+        match systems.keybinds.rebind(action, key) {
+            Ok(()) => {
+                systems.keybinds.save(KEYBINDS_FILE);
+                self.refresh_keys(systems);
+                self.set_header(systems, self.page.title());
             }
-            3 => {
-                systems.config.show_frame_loop = self.checkbox[index].value;
-                systems.gfx.set_visible(
-                    frame_jitter_index,
-                    systems.config.show_frame_loop,
+            Err(Some(other)) => {
+                let name = key_name(key).unwrap_or_default();
+                self.set_header(
+                    systems,
+                    &format!("{} is used by {}", name, other.label()),
                 );
-                systems.config.save_config("settings.toml");
             }
-            _ => {}
+            Err(None) => self.set_header(systems, "That key can not be bound"),
+        }
+    }
+
+    pub fn reset_keys(&mut self, systems: &mut SystemHolder) {
+        self.capture = None;
+        systems.keybinds.reset();
+        systems.keybinds.save(KEYBINDS_FILE);
+        self.refresh_keys(systems);
+        self.set_header(systems, self.page.title());
+    }
+
+    fn refresh_keys(&mut self, systems: &mut SystemHolder) {
+        for (i, action) in Action::GAMEPLAY.into_iter().enumerate() {
+            let text = key_row_text(&systems.keybinds, action);
+            self.button[SETTING_BUTTON_KEY_ROW + i].change_text(systems, text);
         }
     }
 
@@ -2,7 +2,7 @@ use graphics::*;
 
 use crate::{data_types::*, is_within_area, logic::*, widget::*, SystemHolder};
 
-use super::ItemDescription;
+use super::{FocusArea, ItemDescription};
 
 pub struct ShopItem {
     got_data: bool,
@@ -17,6 +17,7 @@ pub struct ShopItem {
     item_index: usize,
 }
 
+#[derive(Default)]
 pub struct Shop {
     pub visible: bool,
     bg: GfxType,
@@ -514,6 +515,15 @@ impl Shop {
         )
     }
 
+    /// The close, scroll and buy buttons that are showing.
+    pub fn focus_areas(&self, systems: &SystemHolder) -> Vec<FocusArea> {
+        self.button
+            .iter()
+            .filter(|button| button.is_visible())
+            .map(|button| FocusArea::from_button(button, systems))
+            .collect()
+    }
+
     pub fn in_window(&mut self, screen_pos: Vec2) -> bool {
         if !self.visible {
             return false;
@@ -900,7 +910,7 @@ impl Shop {
     }
 
     pub fn set_shop(&mut self, systems: &mut SystemHolder, shop_index: usize) {
-        let shopdata = systems.base.shop[shop_index].clone();
+        let shopdata = systems.base.get_shop(shop_index).clone();
 
         let shop_max_item = shopdata.max_item as usize;
 
@@ -925,8 +935,10 @@ impl Shop {
 
         let max_item = shop_max_item.min(5);
         (0..max_item).for_each(|index| {
-            let item_data =
-                systems.base.item[shopdata.item[index].index as usize].clone();
+            let item_data = systems
+                .base
+                .get_item(shopdata.item[index].index as usize)
+                .clone();
 
             self.item[index].got_data = true;
             self.button[3 + index].set_visible(systems, self.visible);
@@ -1003,15 +1015,17 @@ impl Shop {
             return;
         }
 
-        let shopdata = systems.base.shop[self.shop_index].clone();
+        let shopdata = systems.base.get_shop(self.shop_index).clone();
 
         let detail_origin = ORDER_GUI_WINDOW.sub_f32(self.z_order, 3);
         let item_zpos = detail_origin.sub_f32(0.002, 3);
 
         self.shop_start_pos = self.item_scroll.value;
         (self.shop_start_pos..self.shop_start_pos + 5).for_each(|index| {
-            let item_data =
-                systems.base.item[shopdata.item[index].index as usize].clone();
+            let item_data = systems
+                .base
+                .get_item(shopdata.item[index].index as usize)
+                .clone();
 
             let default_index = index - self.shop_start_pos;
 
@@ -1,3 +1,4 @@
+use educe::Educe;
 use graphics::*;
 
 use crate::{
@@ -41,12 +42,16 @@ impl ItemSlot {
     }
 }
 
+#[derive(Educe)]
+#[educe(Default)]
 pub struct Storage {
     pub visible: bool,
     bg: GfxType,
     header: GfxType,
     header_text: GfxType,
+    #[educe(Default = [GfxType::None; MAX_STORAGE])]
     slot: [GfxType; MAX_STORAGE],
+    #[educe(Default = [ItemSlot::default(); MAX_STORAGE])]
     item_slot: [ItemSlot; MAX_STORAGE],
     button: Vec<Button>,
 
@@ -375,12 +380,7 @@ impl Storage {
                     .floor(),
         );
 
-        let sprite =
-            if let Some(itemdata) = systems.base.item.get(data.num as usize) {
-                itemdata.sprite as usize
-            } else {
-                0
-            };
+        let sprite = systems.base.get_item(data.num as usize).sprite as usize;
 
         let mut image = Image::new(
             Some(systems.resource.items[sprite].allocation),
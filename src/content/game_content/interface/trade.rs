@@ -1,10 +1,11 @@
+use educe::Educe;
 use graphics::*;
 
 use crate::{
     Item, SystemHolder, data_types::*, is_within_area, logic::*, widget::*,
 };
 
-use super::ItemDescription;
+use super::{FocusArea, ItemDescription};
 
 const MAX_TRADE_X: f32 = 5.0;
 
@@ -20,6 +21,8 @@ pub struct ItemSlot {
     pub count_data: u16,
 }
 
+#[derive(Educe)]
+#[educe(Default)]
 pub struct Trade {
     pub visible: bool,
     bg: GfxType,
@@ -28,6 +31,7 @@ pub struct Trade {
     pub button: Vec<Button>,
     name_bg: [GfxType; 2],
     money_icon: [GfxType; 2],
+    #[educe(Default = [GfxType::None; MAX_TRADE_SLOT * 2])]
     slot: [GfxType; MAX_TRADE_SLOT * 2],
     pub money_input: Textbox,
     their_money: GfxType,
@@ -575,6 +579,40 @@ impl Trade {
         )
     }
 
+    /// Our offered slots followed by the buttons that are showing.
+    pub fn focus_areas(&self, systems: &SystemHolder) -> Vec<FocusArea> {
+        let mut areas: Vec<FocusArea> = (0..MAX_TRADE_SLOT)
+            .map(|slot| {
+                let frame_pos = Vec2::new(
+                    slot as f32 % MAX_TRADE_X,
+                    (slot as f32 / MAX_TRADE_X).floor(),
+                );
+
+                FocusArea::new(
+                    Vec2::new(
+                        self.pos.x
+                            + ((10.0 + (37.0 * frame_pos.x))
+                                * systems.scale as f32)
+                                .floor(),
+                        self.pos.y
+                            + ((287.0 - (37.0 * frame_pos.y))
+                                * systems.scale as f32)
+                                .floor(),
+                    ),
+                    (Vec2::new(32.0, 32.0) * systems.scale as f32).floor(),
+                )
+            })
+            .collect();
+
+        areas.extend(
+            self.button
+                .iter()
+                .filter(|button| button.is_visible())
+                .map(|button| FocusArea::from_button(button, systems)),
+        );
+        areas
+    }
+
     pub fn in_window(&mut self, screen_pos: Vec2) -> bool {
         if !self.visible {
             return false;
@@ -1209,12 +1247,7 @@ impl Trade {
             )
         };
 
-        let sprite =
-            if let Some(itemdata) = systems.base.item.get(data.num as usize) {
-                itemdata.sprite as usize
-            } else {
-                0
-            };
+        let sprite = systems.base.get_item(data.num as usize).sprite as usize;
 
         let mut image = Image::new(
             Some(systems.resource.items[sprite].allocation),
@@ -0,0 +1,149 @@
+use std::collections::VecDeque;
+
+use crate::MovementData;
+
+/// How far behind the server clock remote players and npcs are drawn.
+pub const INTERP_DELAY_MS: u64 = 100;
+/// A buffered move this far behind the playback time is skipped, and the
+/// entity jumps to the tile it ended on.
+pub const MAX_PLAYBACK_LAG_MS: u64 = 500;
+/// The most a step is sped up while there are due moves waiting behind it.
+pub const MAX_CATCH_UP: f32 = 3.0;
+
+/// The client's guess of the server clock, in milliseconds. Servers with
+/// `Capabilities::MOVE_TIME` stamp their moves. Without it every move is
+/// stamped with the time it arrived.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ServerClock {
+    offset_ms: Option<i64>,
+}
+
+impl ServerClock {
+    pub fn local_ms(seconds: f32) -> u64 {
+        (seconds as f64 * 1000.0) as u64
+    }
+
+    /// The time to stamp a move received at `seconds` with.
+    pub fn stamp(&mut self, server_ms: Option<u64>, seconds: f32) -> u64 {
+        let Some(server_ms) = server_ms else {
+            return self.now(seconds);
+        };
+
+        let sample = server_ms as i64 - Self::local_ms(seconds) as i64;
+
+        // Latency only ever makes a packet late, so the smallest offset is
+        // the closest. Larger ones are followed slowly for clock drift.
+        self.offset_ms = Some(match self.offset_ms {
+            Some(offset) if sample < offset => sample,
+            Some(offset) => offset + (sample - offset) / 16,
+            None => sample,
+        });
+
+        server_ms
+    }
+
+    pub fn now(&self, seconds: f32) -> u64 {
+        (Self::local_ms(seconds) as i64 + self.offset_ms.unwrap_or_default())
+            .max(0) as u64
+    }
+
+    /// The server time remote entities are drawn at.
+    pub fn playback_time(&self, seconds: f32) -> u64 {
+        self.now(seconds).saturating_sub(INTERP_DELAY_MS)
+    }
+
+    pub fn reset(&mut self) {
+        self.offset_ms = None;
+    }
+}
+
+/// Takes the next move to play once the playback time reaches it. Moves
+/// that fell too far behind are dropped, and the tile the last of them
+/// ended on is returned with the move so the entity can jump there first.
+pub fn take_due_move(
+    buffer: &mut VecDeque<MovementData>,
+    playback_ms: u64,
+) -> Option<(MovementData, Option<MovementData>)> {
+    let mut skipped = None;
+
+    while buffer.len() > 1
+        && buffer
+            .front()
+            .is_some_and(|data| data.time + MAX_PLAYBACK_LAG_MS < playback_ms)
+    {
+        skipped = buffer.pop_front();
+    }
+
+    if buffer.front()?.time > playback_ms {
+        return None;
+    }
+
+    buffer.pop_front().map(|data| (data, skipped))
+}
+
+/// How much faster to play the current step. Each due move waiting in the
+/// buffer adds one step's worth of speed, up to `MAX_CATCH_UP`.
+pub fn catch_up(buffer: &VecDeque<MovementData>, playback_ms: u64) -> f32 {
+    let due = buffer
+        .iter()
+        .take_while(|data| data.time <= playback_ms)
+        .count();
+
+    (1.0 + due as f32).min(MAX_CATCH_UP)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Position;
+
+    fn stamped(x: i32, time: u64) -> MovementData {
+        MovementData {
+            end_pos: Position {
+                x,
+                ..Default::default()
+            },
+            dir: 1,
+            time,
+        }
+    }
+
+    #[test]
+    fn moves_play_at_their_time_and_skip_when_far_behind() {
+        let mut buffer: VecDeque<MovementData> =
+            [stamped(1, 1000), stamped(2, 1125), stamped(3, 1250)].into();
+
+        assert_eq!(take_due_move(&mut buffer, 999), None);
+        assert_eq!(catch_up(&buffer, 999), 1.0);
+        assert_eq!(
+            take_due_move(&mut buffer, 1000),
+            Some((stamped(1, 1000), None))
+        );
+        // A burst arriving together still plays at the server's spacing.
+        assert_eq!(take_due_move(&mut buffer, 1100), None);
+        assert_eq!(catch_up(&buffer, 1300), 3.0);
+
+        // Far behind, the older moves are skipped.
+        assert_eq!(
+            take_due_move(&mut buffer, 1700),
+            Some((stamped(3, 1250), Some(stamped(2, 1125))))
+        );
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn clock_keeps_the_smallest_offset() {
+        let mut clock = ServerClock::default();
+        assert_eq!(clock.stamp(None, 2.0), 2000);
+
+        assert_eq!(clock.stamp(Some(50_000), 2.0), 50_000);
+        assert_eq!(clock.now(3.0), 51_000);
+
+        // A packet delayed by 80 ms barely moves the guess.
+        clock.stamp(Some(51_000), 2.92);
+        assert_eq!(clock.now(3.0), 51_005);
+
+        clock.stamp(Some(51_000), 3.0);
+        assert_eq!(clock.playback_time(3.0), 51_000 - INTERP_DELAY_MS);
+    }
+}
@@ -174,12 +174,12 @@ impl MapContent {
     }
 }
 
-pub fn find_entity(
-    world: &mut World,
+/// The tile under a screen position.
+pub fn screen_to_tile(
     systems: &mut SystemHolder,
-    content: &mut GameContent,
+    content: &GameContent,
     screen_pos: Vec2,
-) -> Option<GlobalKey> {
+) -> Position {
     let center_pos = get_map_pos(systems, content.map.mapindex[0]);
     let adjusted_pos = screen_pos - center_pos;
     let tile_pos = Vec2::new(
@@ -212,6 +212,17 @@ pub fn find_entity(
         target_pos.map.y -= 1;
     }
 
+    target_pos
+}
+
+pub fn find_entity(
+    world: &mut World,
+    systems: &mut SystemHolder,
+    content: &mut GameContent,
+    screen_pos: Vec2,
+) -> Option<GlobalKey> {
+    let target_pos = screen_to_tile(systems, content, screen_pos);
+
     let target_entity = world.entities.iter().find_map(|(key, entity_data)| {
         match entity_data {
             Entity::Player(p_data) => {
@@ -15,7 +15,7 @@ pub fn add_npc(
     entity: GlobalKey,
     npcnum: usize,
 ) -> Result<GlobalKey> {
-    let npc_data = &systems.base.npc[npcnum];
+    let npc_data = systems.base.get_npc(npcnum);
     let start_pos = get_start_map_pos(cur_map, pos.map)
         .unwrap_or_else(|| Vec2::new(0.0, 0.0));
     let texture_pos = Vec2::new(pos.x as f32, pos.y as f32) * TILE_SIZE as f32;
@@ -169,17 +169,27 @@ pub fn move_npc(
         }
 
         let (dir, end) = match move_type {
-            MovementType::MovementBuffer => {
-                if n_data.movement_buffer.is_empty() {
+            MovementType::MovementBuffer(None) => {
+                let Some(data) = n_data.movement_buffer.pop_front() else {
                     return Ok(());
-                }
-                let movement_data = n_data.movement_buffer.pop_front();
+                };
 
-                if let Some(data) = movement_data {
-                    (dir_to_enum(data.dir), Some(data.end_pos))
-                } else {
+                (dir_to_enum(data.dir), Some(data.end_pos))
+            }
+            MovementType::MovementBuffer(Some(playback_ms)) => {
+                let Some((data, skipped)) =
+                    take_due_move(&mut n_data.movement_buffer, playback_ms)
+                else {
                     return Ok(());
+                };
+
+                // Fell too far behind, so jump to where the skipped moves
+                // ended instead of walking them.
+                if let Some(skipped) = skipped {
+                    n_data.pos = skipped.end_pos;
                 }
+
+                (dir_to_enum(data.dir), Some(data.end_pos))
             }
             MovementType::Manual(m_dir, m_end) => (dir_to_enum(m_dir), m_end),
         };
@@ -440,23 +450,27 @@ pub fn process_npc_movement(
     entity: GlobalKey,
     socket: &mut Poller,
     content: &mut GameContent,
+    playback_ms: u64,
 ) -> Result<()> {
     if !world.entities.contains_key(entity) {
         return Ok(());
     }
 
-    let movement = if let Some(Entity::Npc(n_data)) = world.entities.get(entity)
-    {
-        n_data.movement
-    } else {
-        return Ok(());
-    };
+    let (movement, speed) =
+        if let Some(Entity::Npc(n_data)) = world.entities.get(entity) {
+            (
+                n_data.movement,
+                catch_up(&n_data.movement_buffer, playback_ms),
+            )
+        } else {
+            return Ok(());
+        };
 
     if !movement.is_moving {
         return Ok(());
     };
 
-    let add_offset = 2.0;
+    let add_offset = 2.0 * speed;
 
     if movement.move_offset + add_offset < TILE_SIZE as f32 {
         if let Some(Entity::Npc(n_data)) = world.entities.get_mut(entity) {
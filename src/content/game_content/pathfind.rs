@@ -0,0 +1,280 @@
+use bit_op::{BitOp, bit_u8::*};
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet, VecDeque},
+};
+
+use crate::{
+    Direction, MapAttribute, MapAttributes, MapDirBlock, MapPosition, Position,
+    database::map::*,
+};
+
+use super::is_map_connected;
+
+/// Tiles along one side of the 3x3 grid of loaded maps.
+pub const PATH_GRID_SIZE: i32 = 96;
+
+/// A tile on the 3x3 grid, as given by `Position::convert_to_screen_tile`.
+/// (0, 0) is the first tile of the map below and left of the center map.
+pub type GridPos = (i32, i32);
+
+/// A click-to-move walk in progress.
+#[derive(Clone, Debug)]
+pub struct MovePath {
+    pub goal: Position,
+    /// The steps left, each with the position it starts from.
+    pub steps: VecDeque<(Position, Direction)>,
+    /// Where the last move was sent from, so each step is sent once.
+    pub sent_from: Option<Position>,
+}
+
+/// The loaded maps as the pathfinder sees them, in `MapContent::mapindex`
+/// order. Maps that are not loaded yet can not be walked.
+#[derive(Default)]
+pub struct PathMaps<'a> {
+    pub maps: [Option<(&'a MapAttributes, &'a MapDirBlock)>; 9],
+    /// Tiles players and npcs stand on.
+    pub occupied: HashSet<GridPos>,
+}
+
+impl<'a> PathMaps<'a> {
+    fn tile(&self, pos: GridPos) -> Option<(&'a MapAttribute, u8)> {
+        if !(0..PATH_GRID_SIZE).contains(&pos.0)
+            || !(0..PATH_GRID_SIZE).contains(&pos.1)
+        {
+            return None;
+        }
+
+        let (attributes, dir_block) = self.maps[grid_map_index(pos)]?;
+        let tile_num = get_tile_pos(pos.0 % 32, pos.1 % 32);
+
+        Some((
+            attributes.attribute.get(tile_num)?,
+            dir_block.dir.get(tile_num).copied().unwrap_or_default(),
+        ))
+    }
+
+    /// The same checks `can_move` does for one step, without entities
+    /// other than those in `occupied`.
+    fn can_step(&self, from: GridPos, dir: Direction, goal: GridPos) -> bool {
+        let Some((_, dir_block)) = self.tile(from) else {
+            return false;
+        };
+
+        if match dir {
+            Direction::Down => dir_block.get(B0) == 0b00000001,
+            Direction::Right => dir_block.get(B3) == 0b00001000,
+            Direction::Up => dir_block.get(B1) == 0b00000010,
+            Direction::Left => dir_block.get(B2) == 0b00000100,
+        } {
+            return false;
+        }
+
+        let next = step_pos(from, dir);
+
+        if self.occupied.contains(&next) {
+            return false;
+        }
+
+        match self.tile(next) {
+            Some((
+                MapAttribute::Blocked
+                | MapAttribute::Storage
+                | MapAttribute::Shop(_),
+                _,
+            ))
+            | None => false,
+            // Warps move us to another map, so only walk onto one on purpose.
+            Some((MapAttribute::Warp(_), _)) => next == goal,
+            Some(_) => true,
+        }
+    }
+}
+
+/// The index into `MapContent::mapindex` holding a grid tile.
+pub fn grid_map_index(pos: GridPos) -> usize {
+    match (pos.0.div_euclid(32), pos.1.div_euclid(32)) {
+        (0, 0) => 1,
+        (1, 0) => 2,
+        (2, 0) => 3,
+        (0, 1) => 4,
+        (2, 1) => 5,
+        (0, 2) => 6,
+        (1, 2) => 7,
+        (2, 2) => 8,
+        _ => 0,
+    }
+}
+
+/// The grid tile of `pos` while `center` is the center map.
+pub fn to_grid_pos(pos: Position, center: MapPosition) -> Option<GridPos> {
+    if !is_map_connected(center, pos.map) {
+        return None;
+    }
+
+    let tile = pos.convert_to_screen_tile(center);
+    Some((tile.x, tile.y))
+}
+
+/// The world position of a grid tile while `center` is the center map.
+pub fn from_grid_pos(pos: GridPos, center: MapPosition) -> Position {
+    Position {
+        x: pos.0.rem_euclid(32),
+        y: pos.1.rem_euclid(32),
+        map: MapPosition {
+            x: center.x + pos.0.div_euclid(32) - 1,
+            y: center.y + pos.1.div_euclid(32) - 1,
+            group: center.group,
+        },
+    }
+}
+
+pub fn step_pos(pos: GridPos, dir: Direction) -> GridPos {
+    match dir {
+        Direction::Up => (pos.0, pos.1 + 1),
+        Direction::Down => (pos.0, pos.1 - 1),
+        Direction::Left => (pos.0 - 1, pos.1),
+        Direction::Right => (pos.0 + 1, pos.1),
+    }
+}
+
+/// A* from `start` to `goal` over the loaded maps. Returns the steps to
+/// walk, empty when already there, or None when the goal can not be
+/// reached.
+pub fn find_path(
+    maps: &PathMaps,
+    start: GridPos,
+    goal: GridPos,
+) -> Option<Vec<Direction>> {
+    if start == goal {
+        return Some(Vec::new());
+    }
+
+    let distance =
+        |pos: GridPos| (pos.0 - goal.0).abs() + (pos.1 - goal.1).abs();
+
+    let mut open = BinaryHeap::new();
+    let mut came_from: HashMap<GridPos, (GridPos, Direction)> = HashMap::new();
+    let mut cost: HashMap<GridPos, i32> = HashMap::new();
+
+    cost.insert(start, 0);
+    open.push(Reverse((distance(start), 0, start)));
+
+    while let Some(Reverse((_, steps, pos))) = open.pop() {
+        if pos == goal {
+            let mut path = Vec::with_capacity(steps as usize);
+            let mut at = goal;
+
+            while let Some(&(from, dir)) = came_from.get(&at) {
+                path.push(dir);
+                at = from;
+            }
+
+            path.reverse();
+            return Some(path);
+        }
+
+        if cost.get(&pos).is_some_and(|best| *best < steps) {
+            continue;
+        }
+
+        for dir in [
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ] {
+            if !maps.can_step(pos, dir, goal) {
+                continue;
+            }
+
+            let next = step_pos(pos, dir);
+            let next_steps = steps + 1;
+
+            if cost.get(&next).is_some_and(|best| *best <= next_steps) {
+                continue;
+            }
+
+            cost.insert(next, next_steps);
+            came_from.insert(next, (pos, dir));
+            open.push(Reverse((next_steps + distance(next), next_steps, next)));
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_map() -> (MapAttributes, MapDirBlock) {
+        (
+            MapAttributes {
+                attribute: vec![MapAttribute::Walkable; 1024],
+            },
+            MapDirBlock { dir: vec![0; 1024] },
+        )
+    }
+
+    #[test]
+    fn path_goes_around_walls_and_blocked_sides() {
+        let (mut attributes, mut dir_block) = open_map();
+
+        // A wall on x = 5 from y = 0 to y = 8 inside the center map.
+        for y in 0..9 {
+            attributes.attribute[get_tile_pos(5, y)] = MapAttribute::Blocked;
+        }
+        // Tile (4, 9) can not be left to the right.
+        dir_block.dir[get_tile_pos(4, 9)] = 0b00001000;
+
+        let mut maps = PathMaps::default();
+        maps.maps[0] = Some((&attributes, &dir_block));
+
+        let start = (32 + 2, 32 + 2);
+        let goal = (32 + 8, 32 + 2);
+        let path = find_path(&maps, start, goal).unwrap();
+
+        let mut pos = start;
+        for dir in path.iter() {
+            assert!(maps.can_step(pos, *dir, goal));
+            pos = step_pos(pos, *dir);
+        }
+        assert_eq!(pos, goal);
+        // Up 8 to y = 10, right 6 and down 8, since (4, 9) blocks the
+        // shorter way over y = 9.
+        assert_eq!(path.len(), 22);
+
+        maps.occupied.insert(goal);
+        assert_eq!(find_path(&maps, start, goal), None);
+        assert_eq!(find_path(&maps, start, start), Some(Vec::new()));
+    }
+
+    #[test]
+    fn path_crosses_into_loaded_neighbours_only() {
+        let (attributes, dir_block) = open_map();
+        let mut maps = PathMaps::default();
+        maps.maps[0] = Some((&attributes, &dir_block));
+
+        let start = (32 + 30, 32 + 5);
+        let goal = (64 + 2, 32 + 5);
+        assert_eq!(find_path(&maps, start, goal), None);
+
+        maps.maps[5] = Some((&attributes, &dir_block));
+        assert_eq!(
+            find_path(&maps, start, goal),
+            Some(vec![Direction::Right; 4])
+        );
+
+        let center = MapPosition {
+            x: 3,
+            y: 7,
+            group: 0,
+        };
+        let world = from_grid_pos(goal, center);
+        assert_eq!(world.map.x, 4);
+        assert_eq!((world.x, world.y), (2, 5));
+        assert_eq!(to_grid_pos(world, center), Some(goal));
+        assert_eq!(grid_map_index(goal), 5);
+    }
+}
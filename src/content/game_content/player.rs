@@ -178,17 +178,27 @@ pub fn move_player(
         }
 
         let (dir, end) = match move_type {
-            MovementType::MovementBuffer => {
-                if p_data.movement_buffer.is_empty() {
+            MovementType::MovementBuffer(None) => {
+                let Some(data) = p_data.movement_buffer.pop_front() else {
                     return Ok(());
-                }
-                let movement_data = p_data.movement_buffer.pop_front();
+                };
 
-                if let Some(data) = movement_data {
-                    (dir_to_enum(data.dir), Some(data.end_pos))
-                } else {
+                (dir_to_enum(data.dir), Some(data.end_pos))
+            }
+            MovementType::MovementBuffer(Some(playback_ms)) => {
+                let Some((data, skipped)) =
+                    take_due_move(&mut p_data.movement_buffer, playback_ms)
+                else {
                     return Ok(());
+                };
+
+                // Fell too far behind, so jump to where the skipped moves
+                // ended instead of walking them.
+                if let Some(skipped) = skipped {
+                    p_data.pos = skipped.end_pos;
                 }
+
+                (dir_to_enum(data.dir), Some(data.end_pos))
             }
             MovementType::Manual(m_dir, m_end) => (dir_to_enum(m_dir), m_end),
         };
@@ -462,14 +472,18 @@ pub fn process_player_movement(
     entity: GlobalKey,
     content: &mut GameContent,
     buffer: &mut BufferTask,
+    playback_ms: Option<u64>,
 ) -> Result<()> {
     if !world.entities.contains_key(entity) {
         return Ok(());
     }
 
-    let movement =
+    let (movement, speed) =
         if let Some(Entity::Player(p_data)) = world.entities.get(entity) {
-            p_data.movement
+            let speed = playback_ms
+                .map_or(1.0, |ms| catch_up(&p_data.movement_buffer, ms));
+
+            (p_data.movement, speed)
         } else {
             return Ok(());
         };
@@ -478,7 +492,7 @@ pub fn process_player_movement(
         return Ok(());
     };
 
-    let add_offset = 4.0;
+    let add_offset = 4.0 * speed;
 
     if movement.move_offset + add_offset < TILE_SIZE as f32 {
         if let Some(Entity::Player(p_data)) = world.entities.get_mut(entity) {
@@ -57,12 +57,11 @@ pub fn player_get_weapon_damage(
             let mut dmg = (0, 0);
 
             if p_data.equipment.items[EquipmentType::Weapon as usize].val > 0 {
-                if let Some(item) = systems.base.item.get(
+                let item = systems.base.get_item(
                     p_data.equipment.items[EquipmentType::Weapon as usize].num
                         as usize,
-                ) {
-                    dmg = (item.data[0], item.data[1]);
-                }
+                );
+                dmg = (item.data[0], item.data[1]);
             }
 
             dmg
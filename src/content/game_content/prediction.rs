@@ -0,0 +1,290 @@
+use std::collections::VecDeque;
+
+use crate::{
+    Direction, Entity, GlobalKey, MovementData, Position, TILE_SIZE, World,
+    enum_to_dir,
+};
+
+/// A step the local player started before the server confirmed it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PredictedMove {
+    /// Sent with the step's Move when the server enabled `MOVE_SEQ`.
+    pub seq: u32,
+    pub from: Position,
+    pub to: Position,
+    pub dir: Direction,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Reconcile {
+    /// The server agreed with the oldest predicted step.
+    Confirmed(PredictedMove),
+    /// The server has us somewhere else. Every pending step was dropped.
+    Mispredicted(PredictedMove),
+    /// Nothing was predicted, so this is a plain server move.
+    Untracked,
+}
+
+/// Steps the local player took ahead of the server. Servers with the
+/// `MOVE_SEQ` capability echo each step's sequence number, so their answers
+/// are matched by it. Older servers answer moves in the order they were
+/// sent, so each of their Move packets for us is matched to the oldest
+/// pending step.
+#[derive(Debug, Default)]
+pub struct MovePrediction {
+    next_seq: u32,
+    pending: VecDeque<PredictedMove>,
+    pub predicted: u64,
+    pub mispredicted: u64,
+}
+
+impl MovePrediction {
+    /// The sequence number for the next Move we send, predicted or not.
+    pub fn next_seq(&mut self) -> u32 {
+        let seq = self.next_seq;
+
+        self.next_seq = self.next_seq.wrapping_add(1);
+        seq
+    }
+
+    pub fn push(
+        &mut self,
+        seq: u32,
+        from: Position,
+        to: Position,
+        dir: Direction,
+    ) {
+        self.predicted += 1;
+        self.pending.push_back(PredictedMove { seq, from, to, dir });
+    }
+
+    pub fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Where the step the server answered is in `pending`. Without a
+    /// sequence number it is the oldest one.
+    fn answered(&self, seq: Option<u32>) -> Option<usize> {
+        match seq {
+            Some(seq) => self.pending.iter().position(|step| step.seq == seq),
+            None => (!self.pending.is_empty()).then_some(0),
+        }
+    }
+
+    /// Matches a server position for the local player against the step
+    /// with `seq`, or against the oldest pending step when the server does
+    /// not echo sequence numbers.
+    pub fn reconcile(
+        &mut self,
+        server_pos: Position,
+        seq: Option<u32>,
+    ) -> Reconcile {
+        let Some(index) = self.answered(seq) else {
+            return Reconcile::Untracked;
+        };
+
+        // Steps sent before it were answered by packets we never matched.
+        self.pending.drain(..index);
+
+        let Some(step) = self.pending.pop_front() else {
+            return Reconcile::Untracked;
+        };
+
+        if step.to == server_pos {
+            return Reconcile::Confirmed(step);
+        }
+
+        self.mispredicted += 1 + self.pending.len() as u64;
+        self.pending.clear();
+        Reconcile::Mispredicted(step)
+    }
+
+    /// The server refused the move with `seq`, or the oldest one without
+    /// it. Drops that step and every later one and returns the refused
+    /// step, whose `from` is where the server still has us.
+    pub fn reject(&mut self, seq: Option<u32>) -> Option<PredictedMove> {
+        let index = self.answered(seq)?;
+        let step = self.pending.get(index).copied()?;
+
+        self.mispredicted += (self.pending.len() - index) as u64;
+        self.pending.truncate(index);
+        Some(step)
+    }
+
+    /// Forgets pending steps without counting them wrong, such as when a
+    /// warp moves us.
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Share of predicted steps the server disagreed with, from 0 to 1.
+    pub fn error_rate(&self) -> f64 {
+        if self.predicted == 0 {
+            0.0
+        } else {
+            self.mispredicted as f64 / self.predicted as f64
+        }
+    }
+}
+
+/// The direction from `from` to a neighbouring tile on the same map.
+pub fn step_between(from: Position, to: Position) -> Option<Direction> {
+    if from.map != to.map {
+        return None;
+    }
+
+    match (to.x - from.x, to.y - from.y) {
+        (0, 1) => Some(Direction::Up),
+        (0, -1) => Some(Direction::Down),
+        (-1, 0) => Some(Direction::Left),
+        (1, 0) => Some(Direction::Right),
+        _ => None,
+    }
+}
+
+fn reverse(dir: Direction) -> Direction {
+    match dir {
+        Direction::Up => Direction::Down,
+        Direction::Down => Direction::Up,
+        Direction::Left => Direction::Right,
+        Direction::Right => Direction::Left,
+    }
+}
+
+/// Brings the local player back to where the server has it after a wrong
+/// prediction. A step still in progress is walked back from where it is
+/// drawn, a neighbouring tile is walked to, and anything further snaps.
+pub fn rollback_player(
+    world: &mut World,
+    entity: GlobalKey,
+    server_pos: Position,
+) {
+    let Some(Entity::Player(p_data)) = world.entities.get_mut(entity) else {
+        return;
+    };
+
+    p_data.movement_buffer.clear();
+
+    if p_data.movement.is_moving
+        && p_data.pos == server_pos
+        && p_data.end_movement.map == server_pos.map
+    {
+        // Swap the ends of the step so the sprite turns around where it is.
+        p_data.pos = p_data.end_movement;
+        p_data.end_movement = server_pos;
+        p_data.movement.move_direction =
+            reverse(p_data.movement.move_direction);
+        p_data.movement.move_offset =
+            TILE_SIZE as f32 - p_data.movement.move_offset;
+        return;
+    }
+
+    let at = if p_data.movement.is_moving {
+        p_data.end_movement
+    } else {
+        p_data.pos
+    };
+
+    if let Some(dir) = step_between(at, server_pos) {
+        p_data.movement_buffer.push_back(MovementData {
+            end_pos: server_pos,
+            dir: enum_to_dir(dir),
+            time: 0,
+        });
+    } else if server_pos.map == p_data.pos.map {
+        p_data.movement.is_moving = false;
+        p_data.movement.move_offset = 0.0;
+        p_data.pos = server_pos;
+        p_data.pos_offset = Default::default();
+    } else {
+        // A map change goes through the usual movement so the maps load.
+        p_data.movement_buffer.push_back(MovementData {
+            end_pos: server_pos,
+            dir: p_data.dir,
+            time: 0,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile(x: i32, y: i32) -> Position {
+        Position {
+            x,
+            y,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn server_moves_confirm_or_roll_back_in_order() {
+        let mut prediction = MovePrediction::default();
+        assert_eq!(
+            prediction.reconcile(tile(1, 0), None),
+            Reconcile::Untracked
+        );
+
+        prediction.push(0, tile(0, 0), tile(1, 0), Direction::Right);
+        prediction.push(1, tile(1, 0), tile(2, 0), Direction::Right);
+
+        assert!(matches!(
+            prediction.reconcile(tile(1, 0), None),
+            Reconcile::Confirmed(step) if step.from == tile(0, 0)
+        ));
+
+        // The server stopped us at (1, 0), so the second step was wrong.
+        assert!(matches!(
+            prediction.reconcile(tile(1, 0), None),
+            Reconcile::Mispredicted(step) if step.to == tile(2, 0)
+        ));
+        assert!(!prediction.is_pending());
+        assert_eq!(prediction.error_rate(), 0.5);
+
+        prediction.push(2, tile(1, 0), tile(1, 1), Direction::Up);
+        prediction.push(3, tile(1, 1), tile(1, 2), Direction::Up);
+        assert_eq!(
+            prediction.reject(None).map(|step| step.from),
+            Some(tile(1, 0))
+        );
+        assert_eq!(prediction.mispredicted, 3);
+        assert_eq!(prediction.reject(None), None);
+
+        assert_eq!(step_between(tile(1, 1), tile(1, 0)), Some(Direction::Down));
+        assert_eq!(step_between(tile(1, 1), tile(3, 1)), None);
+    }
+
+    #[test]
+    fn echoed_sequence_numbers_pick_the_step() {
+        let mut prediction = MovePrediction::default();
+        let seqs: Vec<u32> = (0..4).map(|_| prediction.next_seq()).collect();
+        assert_eq!(seqs, vec![0, 1, 2, 3]);
+
+        prediction.push(seqs[0], tile(0, 0), tile(1, 0), Direction::Right);
+        prediction.push(seqs[1], tile(1, 0), tile(2, 0), Direction::Right);
+        prediction.push(seqs[2], tile(2, 0), tile(3, 0), Direction::Right);
+
+        // A move we did not predict, such as a stop, is not matched.
+        assert_eq!(
+            prediction.reconcile(tile(1, 0), Some(seqs[3])),
+            Reconcile::Untracked
+        );
+        assert!(prediction.is_pending());
+
+        // The answer to the first step was lost, the second still matches.
+        assert!(matches!(
+            prediction.reconcile(tile(2, 0), Some(seqs[1])),
+            Reconcile::Confirmed(step) if step.seq == seqs[1]
+        ));
+
+        // Refusing the last step keeps the count to that one step.
+        assert!(matches!(
+            prediction.reject(Some(seqs[2])),
+            Some(step) if step.from == tile(2, 0)
+        ));
+        assert!(!prediction.is_pending());
+        assert_eq!(prediction.mispredicted, 1);
+        assert_eq!(prediction.reject(Some(seqs[2])), None);
+    }
+}
@@ -4,6 +4,7 @@ use crate::{
 };
 use graphics::*;
 
+#[derive(Default)]
 pub struct Target {
     pub entity: Option<GlobalKey>,
     img_index: GfxType,
@@ -4,7 +4,8 @@ use input::Key;
 use winit::{event_loop::ActiveEventLoop, keyboard::NamedKey};
 
 use crate::{
-    Alert, Direction, EntityKind, Result, SystemHolder, Tooltip,
+    Action, Alert, Direction, EntityKind, PadCommand, Result, SystemHolder,
+    Tooltip,
     content::{menu_content::content_input::*, *},
     socket::*,
 };
@@ -43,6 +44,8 @@ pub fn handle_mouse_input(
                 input_type, screen_pos,
             )?;
         }
+        // The content sync screen covers the menu until the download ends.
+        ContentType::Menu if socket.content_sync.is_busy() => {}
         ContentType::Menu => {
             MenuContent::mouse_input(
                 content, world, systems, socket, elwt, alert, tooltip,
@@ -68,6 +71,7 @@ pub fn handle_key_input(
                 content, world, systems, socket, alert, key, pressed,
             )?;
         }
+        ContentType::Menu if socket.content_sync.is_busy() => {}
         ContentType::Menu => {
             MenuContent::key_input(
                 content, world, systems, socket, alert, key, pressed,
@@ -100,6 +104,130 @@ pub fn handle_key_input(
             }
             _ => {}
         }
+
+        if systems.keybinds.action_for(key) == Some(Action::ToggleNetStats) {
+            content.net_stats.toggle(
+                systems,
+                socket,
+                &content.game_content.prediction,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs this frame's gamepad input. Outside focus mode the pad drives the
+/// same actions as the keyboard. In focus mode it moves the window focus and
+/// clicks what is focused through handle_mouse_input.
+#[allow(clippy::too_many_arguments)]
+pub fn handle_gamepad_input(
+    world: &mut World,
+    systems: &mut SystemHolder,
+    socket: &mut Poller,
+    elwt: &ActiveEventLoop,
+    content: &mut Content,
+    alert: &mut Alert,
+    tooltip: &mut Tooltip,
+) -> Result<()> {
+    let commands = systems.gamepad.poll();
+
+    if content.content_type != ContentType::Game {
+        return Ok(());
+    }
+
+    for command in commands {
+        let interface = &mut content.game_content.interface;
+
+        let (pos, input_type) = match command {
+            PadCommand::Action(action, pressed) => {
+                if pressed {
+                    content.game_content.cancel_path(world, socket)?;
+                }
+
+                // Releases still go through so nothing stays held.
+                if !(alert.visible && pressed) {
+                    GameContent::action_input(
+                        content,
+                        world,
+                        systems,
+                        socket,
+                        Some(action),
+                        pressed,
+                    )?;
+                }
+                continue;
+            }
+            PadCommand::FocusMode(active) => {
+                interface.set_focus_mode(systems, active);
+
+                if !active {
+                    continue;
+                }
+
+                (interface.focus_pos(systems), MouseInputType::MouseMove)
+            }
+            PadCommand::Navigate(dir) => {
+                let input_type = if interface.is_holding_slot() {
+                    MouseInputType::MouseLeftDownMove
+                } else {
+                    MouseInputType::MouseMove
+                };
+
+                (interface.move_focus(systems, dir), input_type)
+            }
+            PadCommand::NextWindow | PadCommand::PrevWindow => (
+                interface.cycle_focus_window(
+                    systems,
+                    command == PadCommand::NextWindow,
+                ),
+                MouseInputType::MouseMove,
+            ),
+            PadCommand::Confirm => {
+                // Confirming on an item picks it up, and the next confirm
+                // drops it wherever the focus moved to.
+                let input_type = if interface.is_holding_slot() {
+                    MouseInputType::MouseRelease
+                } else {
+                    MouseInputType::MouseLeftDown
+                };
+
+                (interface.focus_pos(systems), input_type)
+            }
+            PadCommand::Use => (
+                interface.focus_pos(systems),
+                MouseInputType::MouseDoubleLeftDown,
+            ),
+        };
+
+        let Some(pos) = pos else {
+            continue;
+        };
+
+        // handle_mouse_input takes window positions, where y grows downward.
+        let mouse_pos = Vec2::new(pos.x, systems.size.height - pos.y);
+
+        let press = matches!(input_type, MouseInputType::MouseLeftDown);
+
+        handle_mouse_input(
+            world, systems, socket, elwt, input_type, &mouse_pos, content,
+            alert, tooltip,
+        )?;
+
+        // A press that did not pick up an item is a plain click.
+        if press && !content.game_content.interface.is_holding_slot() {
+            handle_mouse_input(
+                world,
+                systems,
+                socket,
+                elwt,
+                MouseInputType::MouseRelease,
+                &mouse_pos,
+                content,
+                alert,
+                tooltip,
+            )?;
+        }
     }
 
     Ok(())
@@ -3,31 +3,40 @@ use graphics::{cosmic_text::Attrs, *};
 pub mod content_input;
 pub mod login;
 pub mod register;
+pub mod server_select;
 
 pub use content_input::*;
 pub use login::*;
 pub use register::*;
+pub use server_select::*;
 
 use crate::{
     SystemHolder, content::*, data_types::*, is_within_area, widget::*,
 };
 
+#[derive(Default)]
 pub enum WindowType {
+    #[default]
     None,
+    ServerSelect,
     Login,
     Register,
 }
 
+#[derive(Default)]
 pub struct MenuContent {
     bg: GfxType,
     cur_window: WindowType,
 
+    server_select: ServerSelect,
     login: Login,
     register: Register,
 
     selected_textbox: Option<usize>,
 
     pub content_data: usize,
+    /// Set once a realm was chosen and the Poller connected to it.
+    pub realm_picked: bool,
 
     pub did_button_click: bool,
     pub did_checkbox_click: bool,
@@ -46,23 +55,39 @@ impl MenuContent {
         bg_image.uv = Vec4::new(0.0, 0.0, 800.0, 600.0);
 
         let bg = systems.gfx.add_image(bg_image, 0, "Menu BG", true);
+        let server_select = ServerSelect::new(systems);
+        let has_realms = server_select.has_realms();
 
         MenuContent {
             bg,
             cur_window: WindowType::None,
-            login: Login::new(systems),
+            server_select,
+            login: Login::new(systems, has_realms),
             register: Register::new(systems),
 
             did_button_click: false,
             did_checkbox_click: false,
             selected_textbox: None,
             content_data: 0,
+            realm_picked: !has_realms,
         }
     }
 
     pub fn show(&mut self, systems: &mut SystemHolder) {
         systems.gfx.set_visible(&self.bg, true);
-        create_window(systems, self, WindowType::Login);
+
+        if self.realm_picked {
+            create_window(systems, self, WindowType::Login);
+        } else {
+            create_window(systems, self, WindowType::ServerSelect);
+        }
+    }
+
+    /// Per frame menu logic, currently only the realm probes.
+    pub fn update(&mut self, systems: &mut SystemHolder) {
+        if let WindowType::ServerSelect = self.cur_window {
+            self.server_select.update_probes(systems);
+        }
     }
 
     pub fn hide(&mut self, systems: &mut SystemHolder) {
@@ -71,7 +96,16 @@ impl MenuContent {
         self.clear_window(systems)
     }
 
+    pub fn unload(&mut self, systems: &mut SystemHolder) {
+        self.hide(systems);
+        systems.gfx.remove_gfx(&mut systems.renderer, &self.bg);
+        self.server_select.unload(systems);
+        self.login.unload(systems);
+        self.register.unload(systems);
+    }
+
     pub fn clear_window(&mut self, systems: &mut SystemHolder) {
+        self.server_select.set_visible(systems, false);
         self.login.set_visible(systems, false);
         self.register.set_visible(systems, false);
         self.selected_textbox = None;
@@ -88,6 +122,10 @@ pub fn create_window(
     content.clear_window(systems);
 
     match content.cur_window {
+        WindowType::ServerSelect => {
+            content.server_select.set_visible(systems, true);
+            content.server_select.refresh_probes(systems);
+        }
         WindowType::Login => {
             content.login.set_visible(systems, true);
         }
@@ -11,9 +11,11 @@ use crate::{
 
 mod login_input;
 mod register_input;
+mod server_select_input;
 
 use login_input::*;
 use register_input::*;
+use server_select_input::*;
 
 impl MenuContent {
     pub fn mouse_input(
@@ -34,6 +36,18 @@ impl MenuContent {
         }
 
         match content.menu_content.cur_window {
+            WindowType::ServerSelect => {
+                server_select_mouse_input(
+                    &mut content.menu_content,
+                    world,
+                    systems,
+                    socket,
+                    alert,
+                    tooltip,
+                    input_type,
+                    screen_pos,
+                );
+            }
             WindowType::Register => {
                 register_mouse_input(
                     &mut content.menu_content,
@@ -144,17 +144,35 @@ fn trigger_button(
             let username = menu_content.login.textbox[0].text.clone();
             let password = menu_content.login.textbox[1].text.clone();
 
+            // A token only belongs to the account it was issued for.
+            if username != systems.config.username {
+                systems.config.login_token.clear();
+            }
+
             systems.config.username.clone_from(&username);
-            systems.config.password.clone_from(&password);
             systems.config.save_config("settings.toml");
 
-            match send_login(
-                socket,
-                username,
-                password,
-                (APP_MAJOR, APP_MINOR, APP_REV),
-                &systems.config.reconnect_code,
-            ) {
+            let result = if password.is_empty()
+                && !systems.config.login_token.is_empty()
+            {
+                send_token_login(
+                    socket,
+                    username,
+                    &systems.config.login_token,
+                    (APP_MAJOR, APP_MINOR, APP_REV),
+                    &systems.config.reconnect_code,
+                )
+            } else {
+                send_login(
+                    socket,
+                    username,
+                    password,
+                    (APP_MAJOR, APP_MINOR, APP_REV),
+                    &systems.config.reconnect_code,
+                )
+            };
+
+            match result {
                 Ok(_) => {}
                 Err(e) => {
                     error!("send_login error: {:?}", e);
@@ -174,6 +192,15 @@ fn trigger_button(
             // Register
             create_window(systems, menu_content, WindowType::Register);
         }
+        2 => {
+            // Realms, a replay keeps its loopback channels.
+            if systems.config.launch.replay.is_none() {
+                socket.disconnect();
+            }
+
+            menu_content.realm_picked = false;
+            create_window(systems, menu_content, WindowType::ServerSelect);
+        }
         _ => {}
     }
 }
@@ -187,7 +214,7 @@ fn trigger_checkbox(
     match index {
         0 => {
             // Remember Account
-            systems.config.save_password = menu_content.login.checkbox.value;
+            systems.config.remember_login = menu_content.login.checkbox.value;
             menu_content
                 .login
                 .checkbox
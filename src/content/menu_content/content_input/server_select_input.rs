@@ -0,0 +1,81 @@
+use graphics::*;
+
+use log::error;
+
+use crate::{
+    Alert, MouseInputType, SystemHolder, Tooltip,
+    content::*,
+    socket::{self, *},
+};
+
+pub fn server_select_mouse_input(
+    menu_content: &mut MenuContent,
+    _world: &mut World,
+    systems: &mut SystemHolder,
+    socket: &mut Poller,
+    alert: &mut Alert,
+    tooltip: &mut Tooltip,
+    input_type: MouseInputType,
+    screen_pos: Vec2,
+) {
+    match input_type {
+        MouseInputType::MouseMove => {
+            menu_content
+                .server_select
+                .hover_buttons(systems, tooltip, screen_pos);
+        }
+        MouseInputType::MouseLeftDown => {
+            let button_index = menu_content
+                .server_select
+                .click_buttons(systems, screen_pos);
+
+            if let Some(index) = button_index {
+                menu_content.did_button_click = true;
+                trigger_button(menu_content, systems, socket, alert, index);
+            }
+        }
+        MouseInputType::MouseRelease => {
+            reset_server_select_buttons(menu_content, systems);
+        }
+        _ => {}
+    }
+}
+
+fn trigger_button(
+    menu_content: &mut MenuContent,
+    systems: &mut SystemHolder,
+    socket: &mut Poller,
+    alert: &mut Alert,
+    index: usize,
+) {
+    let realm = match menu_content.server_select.realms.get(index) {
+        Some(realm) => realm.clone(),
+        None => {
+            // Refresh
+            menu_content.server_select.refresh_probes(systems);
+            return;
+        }
+    };
+
+    // A replay plays back recorded frames, a live connection would mix in
+    // another server's packets.
+    if systems.config.launch.replay.is_some() {
+        menu_content.realm_picked = true;
+        create_window(systems, menu_content, WindowType::Login);
+        return;
+    }
+
+    realm.apply_to(&mut systems.config);
+    systems.config.save_config("settings.toml");
+
+    match socket.connect(&systems.config) {
+        Ok(_) => {
+            menu_content.realm_picked = true;
+            create_window(systems, menu_content, WindowType::Login);
+        }
+        Err(e) => {
+            error!("Failed to connect to realm {}: {:?}", realm.name, e);
+            alert_connect_error(systems, alert, &e, &realm.name);
+        }
+    }
+}
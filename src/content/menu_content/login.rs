@@ -5,6 +5,7 @@ use crate::{
     SCREEN_WIDTH,
 };
 
+#[derive(Default)]
 pub struct Login {
     window: Vec<GfxType>,
     label: Vec<GfxType>,
@@ -14,10 +15,10 @@ pub struct Login {
 }
 
 impl Login {
-    pub fn new(systems: &mut SystemHolder) -> Self {
+    pub fn new(systems: &mut SystemHolder, has_realms: bool) -> Self {
         let mut window = Vec::with_capacity(6);
         let mut label = Vec::with_capacity(3);
-        let mut button = Vec::with_capacity(2);
+        let mut button = Vec::with_capacity(3);
         let mut textbox = Vec::with_capacity(2);
         let size = Vec2::new(
             348.0 * systems.scale as f32,
@@ -171,9 +172,10 @@ impl Login {
                 disable_option,
             );
 
-            match index {
-                1 => txtbox.set_text(systems, systems.config.password.clone()),
-                _ => txtbox.set_text(systems, systems.config.username.clone()),
+            // The password is never stored, a saved login token is used
+            // when the field is left empty.
+            if index == 0 {
+                txtbox.set_text(systems, systems.config.username.clone());
             }
 
             textbox.push(txtbox);
@@ -239,6 +241,33 @@ impl Login {
             None,
         ));
 
+        if has_realms {
+            button.push(Button::new(
+                systems,
+                ButtonType::None,
+                ButtonContentType::Text(ButtonContentText {
+                    text: "Realms".to_string(),
+                    pos: Vec2::new(0.0, 0.0),
+                    color: Color::rgba(80, 80, 80, 255),
+                    render_layer: 1,
+                    hover_change: ButtonChangeType::ColorChange(Color::rgba(
+                        240, 240, 240, 255,
+                    )),
+                    click_change: ButtonChangeType::ColorChange(Color::rgba(
+                        80, 80, 80, 255,
+                    )),
+                }),
+                Vec2::new(pos.x, pos.y),
+                Vec2::new(254.0, 19.0),
+                ORDER_MENU_WINDOW_CONTENT,
+                (0.01, 2),
+                Vec2::new(70.0, 20.0),
+                0,
+                true,
+                Some("Change Realm".to_string()),
+            ));
+        }
+
         let mut checkbox = Checkbox::new(
             systems,
             CheckboxType::Rect(CheckboxRect {
@@ -284,7 +313,7 @@ impl Login {
             None,
         );
 
-        checkbox.set_value(systems, systems.config.save_password);
+        checkbox.set_value(systems, systems.config.remember_login);
 
         Login {
             window,
@@ -295,6 +324,22 @@ impl Login {
         }
     }
 
+    pub fn unload(&mut self, systems: &mut SystemHolder) {
+        self.window
+            .iter()
+            .chain(self.label.iter())
+            .for_each(|index| {
+                systems.gfx.remove_gfx(&mut systems.renderer, index);
+            });
+        self.button.iter_mut().for_each(|button| {
+            button.unload(systems);
+        });
+        self.textbox.iter_mut().for_each(|textbox| {
+            textbox.unload(systems);
+        });
+        self.checkbox.unload(systems);
+    }
+
     pub fn set_visible(&mut self, systems: &mut SystemHolder, visible: bool) {
         self.window.iter().for_each(|index| {
             systems.gfx.set_visible(index, visible);
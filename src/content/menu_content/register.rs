@@ -5,6 +5,7 @@ use crate::{
     SCREEN_WIDTH,
 };
 
+#[derive(Default)]
 pub struct Register {
     window: Vec<GfxType>,
     label: Vec<GfxType>,
@@ -416,6 +417,22 @@ impl Register {
         }
     }
 
+    pub fn unload(&mut self, systems: &mut SystemHolder) {
+        self.window
+            .iter()
+            .chain(self.label.iter())
+            .chain([&self.unique_label, &self.image])
+            .for_each(|index| {
+                systems.gfx.remove_gfx(&mut systems.renderer, index);
+            });
+        self.button.iter_mut().for_each(|button| {
+            button.unload(systems);
+        });
+        self.textbox.iter_mut().for_each(|textbox| {
+            textbox.unload(systems);
+        });
+    }
+
     pub fn set_visible(&mut self, systems: &mut SystemHolder, visible: bool) {
         self.window.iter().for_each(|index| {
             systems.gfx.set_visible(index, visible);
@@ -0,0 +1,371 @@
+use graphics::*;
+
+use crate::{
+    RealmEntry, SCREEN_WIDTH, ServerList, SystemHolder,
+    content::*,
+    data_types::*,
+    is_within_area,
+    socket::{
+        build_tls_config,
+        probe::{RealmProbe, RealmStatus},
+    },
+    widget::*,
+};
+
+pub const MAX_REALM_ROWS: usize = 5;
+
+#[derive(Default)]
+pub struct ServerSelect {
+    window: Vec<GfxType>,
+    label: Vec<GfxType>,
+    status: Vec<GfxType>,
+    button: Vec<crate::widget::Button>,
+    pub realms: Vec<RealmEntry>,
+    probes: Vec<RealmProbe>,
+}
+
+impl ServerSelect {
+    pub fn new(systems: &mut SystemHolder) -> Self {
+        let mut list = ServerList::read_list("servers.toml");
+
+        if list.server.len() > MAX_REALM_ROWS {
+            log::warn!(
+                "servers.toml has {} realms, only the first {} are shown",
+                list.server.len(),
+                MAX_REALM_ROWS
+            );
+            list.server.truncate(MAX_REALM_ROWS);
+        }
+
+        let mut window = Vec::with_capacity(2);
+        let mut label = Vec::with_capacity(1);
+        let mut status = Vec::with_capacity(list.server.len());
+        let mut button = Vec::with_capacity(list.server.len() + 1);
+        let size = (Vec2::new(348.0, 226.0) * systems.scale as f32).floor();
+        let pos = Vec2::new((SCREEN_WIDTH as f32 - size.x) * 0.5, 80.0).floor();
+        let mut menu_rect = Rect::new(&mut systems.renderer, 0);
+
+        menu_rect
+            .set_position(Vec3::new(
+                pos.x - 1.0,
+                pos.y - 1.0,
+                ORDER_MENU_WINDOW,
+            ))
+            .set_size(size + 2.0)
+            .set_color(Color::rgba(160, 160, 160, 255))
+            .set_border_color(Color::rgba(10, 10, 10, 255))
+            .set_border_width(1.0);
+        window.push(systems.gfx.add_rect(
+            menu_rect,
+            0,
+            "Server Select Window",
+            false,
+        ));
+
+        let mut header_rect = Rect::new(&mut systems.renderer, 0);
+
+        header_rect
+            .set_position(Vec3::new(
+                pos.x,
+                pos.y + (196.0 * systems.scale as f32).floor(),
+                ORDER_MENU_WINDOW_CONTENT,
+            ))
+            .set_size(Vec2::new(size.x, (30.0 * systems.scale as f32).floor()))
+            .set_color(Color::rgba(120, 120, 120, 255));
+        window.push(systems.gfx.add_rect(
+            header_rect,
+            0,
+            "Server Select Header",
+            false,
+        ));
+
+        let header_text = create_label(
+            systems,
+            Vec3::new(
+                pos.x,
+                pos.y + (199.0 * systems.scale as f32).floor(),
+                ORDER_MENU_WINDOW_CONTENT_DETAIL,
+            ),
+            Vec2::new(size.x, (20.0 * systems.scale as f32).floor()),
+            Bounds::new(
+                pos.x,
+                pos.y + (199.0 * systems.scale as f32).floor(),
+                pos.x + size.x,
+                pos.y + (219.0 * systems.scale as f32).floor(),
+            ),
+            Color::rgba(240, 240, 240, 255),
+        );
+        let text_index = systems.gfx.add_text(
+            header_text,
+            1,
+            "Server Select Header Text",
+            false,
+        );
+
+        systems.gfx.set_text(
+            &mut systems.renderer,
+            &text_index,
+            "Select Realm",
+        );
+        systems.gfx.center_text(&text_index);
+        label.push(text_index);
+
+        for (index, realm) in list.server.iter().enumerate() {
+            let addy = 160.0 - (index as f32 * 28.0);
+
+            button.push(Button::new(
+                systems,
+                ButtonType::Rect(ButtonRect {
+                    rect_color: Color::rgba(100, 100, 100, 255),
+                    got_border: true,
+                    border_color: Color::rgba(70, 70, 70, 255),
+                    border_radius: 0.0,
+                    hover_change: ButtonChangeType::ColorChange(Color::rgba(
+                        180, 180, 180, 255,
+                    )),
+                    click_change: ButtonChangeType::ColorChange(Color::rgba(
+                        40, 40, 40, 255,
+                    )),
+                }),
+                ButtonContentType::Text(ButtonContentText {
+                    text: realm.name.clone(),
+                    pos: Vec2::new(0.0, 3.0),
+                    color: Color::rgba(230, 230, 230, 255),
+                    render_layer: 1,
+                    hover_change: ButtonChangeType::ColorChange(Color::rgba(
+                        80, 80, 80, 255,
+                    )),
+                    click_change: ButtonChangeType::ColorChange(Color::rgba(
+                        170, 170, 170, 255,
+                    )),
+                }),
+                Vec2::new(pos.x, pos.y),
+                Vec2::new(24.0, addy),
+                ORDER_MENU_WINDOW_CONTENT,
+                (0.01, 2),
+                Vec2::new(190.0, 24.0),
+                0,
+                false,
+                Some(format!("{}:{}", realm.host, realm.tls_port)),
+            ));
+
+            let tpos = Vec2::new(
+                pos.x + (220.0 * systems.scale as f32).floor(),
+                pos.y + ((addy + 2.0) * systems.scale as f32).floor(),
+            );
+            let text = create_label(
+                systems,
+                Vec3::new(tpos.x, tpos.y, ORDER_MENU_WINDOW_CONTENT_DETAIL),
+                (Vec2::new(110.0, 20.0) * systems.scale as f32).floor(),
+                Bounds::new(
+                    tpos.x,
+                    tpos.y,
+                    tpos.x + (110.0 * systems.scale as f32).floor(),
+                    tpos.y + (20.0 * systems.scale as f32).floor(),
+                ),
+                Color::rgba(80, 80, 80, 255),
+            );
+            status.push(systems.gfx.add_text(
+                text,
+                1,
+                "Server Select Status",
+                false,
+            ));
+        }
+
+        button.push(Button::new(
+            systems,
+            ButtonType::None,
+            ButtonContentType::Text(ButtonContentText {
+                text: "Refresh".to_string(),
+                pos: Vec2::new(0.0, 0.0),
+                color: Color::rgba(80, 80, 80, 255),
+                render_layer: 1,
+                hover_change: ButtonChangeType::ColorChange(Color::rgba(
+                    240, 240, 240, 255,
+                )),
+                click_change: ButtonChangeType::ColorChange(Color::rgba(
+                    80, 80, 80, 255,
+                )),
+            }),
+            Vec2::new(pos.x, pos.y),
+            Vec2::new(104.0, 12.0),
+            ORDER_MENU_WINDOW_CONTENT,
+            (0.01, 2),
+            Vec2::new(140.0, 20.0),
+            0,
+            false,
+            None,
+        ));
+
+        ServerSelect {
+            window,
+            label,
+            status,
+            button,
+            realms: list.server,
+            probes: Vec::new(),
+        }
+    }
+
+    pub fn has_realms(&self) -> bool {
+        !self.realms.is_empty()
+    }
+
+    pub fn unload(&mut self, systems: &mut SystemHolder) {
+        self.window
+            .iter()
+            .chain(self.label.iter())
+            .chain(self.status.iter())
+            .for_each(|index| {
+                systems.gfx.remove_gfx(&mut systems.renderer, index);
+            });
+        self.button.iter_mut().for_each(|button| {
+            button.unload(systems);
+        });
+        self.probes.clear();
+    }
+
+    pub fn set_visible(&mut self, systems: &mut SystemHolder, visible: bool) {
+        self.window.iter().for_each(|index| {
+            systems.gfx.set_visible(index, visible);
+        });
+        self.label.iter().for_each(|index| {
+            systems.gfx.set_visible(index, visible);
+        });
+        self.status.iter().for_each(|index| {
+            systems.gfx.set_visible(index, visible);
+        });
+        self.button.iter_mut().for_each(|button| {
+            button.set_visible(systems, visible);
+        });
+
+        if !visible {
+            self.probes.clear();
+        }
+    }
+
+    /// Starts a new OnlineCheck probe against every realm.
+    pub fn refresh_probes(&mut self, systems: &mut SystemHolder) {
+        let trust = systems.config.trust_settings();
+        let tls_config = match build_tls_config(&trust) {
+            Ok(tls_config) => Some(tls_config),
+            Err(e) => {
+                log::warn!("Realm probes can not use TLS: {}", e);
+                None
+            }
+        };
+
+        self.probes = self
+            .realms
+            .iter()
+            .map(|realm| match &tls_config {
+                Some(tls_config) => {
+                    RealmProbe::new(&realm.endpoint(&trust), tls_config.clone())
+                }
+                None => RealmProbe::offline(),
+            })
+            .collect();
+
+        for index in 0..self.probes.len() {
+            self.set_status_text(systems, index);
+        }
+    }
+
+    pub fn update_probes(&mut self, systems: &mut SystemHolder) {
+        for index in 0..self.probes.len() {
+            if self.probes[index].update() {
+                self.set_status_text(systems, index);
+            }
+        }
+    }
+
+    fn set_status_text(&mut self, systems: &mut SystemHolder, index: usize) {
+        let (msg, color) = match self.probes[index].status {
+            RealmStatus::Checking => {
+                ("Checking...".to_string(), Color::rgba(80, 80, 80, 255))
+            }
+            RealmStatus::Online(rtt) => {
+                (format!("Online {}ms", rtt), Color::rgba(20, 120, 20, 255))
+            }
+            RealmStatus::Offline => {
+                ("Offline".to_string(), Color::rgba(150, 20, 20, 255))
+            }
+        };
+
+        systems.gfx.set_color(&self.status[index], color);
+        systems
+            .gfx
+            .set_text(&mut systems.renderer, &self.status[index], &msg);
+    }
+
+    pub fn hover_buttons(
+        &mut self,
+        systems: &mut SystemHolder,
+        tooltip: &mut Tooltip,
+        screen_pos: Vec2,
+    ) {
+        for button in self.button.iter_mut() {
+            if is_within_area(
+                screen_pos,
+                Vec2::new(
+                    button.base_pos.x
+                        + (button.adjust_pos.x * systems.scale as f32).floor(),
+                    button.base_pos.y
+                        + (button.adjust_pos.y * systems.scale as f32).floor(),
+                ),
+                (button.size * systems.scale as f32).floor(),
+            ) {
+                button.set_hover(systems, true);
+
+                if let Some(msg) = &button.tooltip {
+                    tooltip.init_tooltip(systems, screen_pos, msg.clone());
+                }
+            } else {
+                button.set_hover(systems, false);
+            }
+        }
+    }
+
+    pub fn click_buttons(
+        &mut self,
+        systems: &mut SystemHolder,
+        screen_pos: Vec2,
+    ) -> Option<usize> {
+        let mut button_found = None;
+        for (index, button) in self.button.iter_mut().enumerate() {
+            if is_within_area(
+                screen_pos,
+                Vec2::new(
+                    button.base_pos.x
+                        + (button.adjust_pos.x * systems.scale as f32).floor(),
+                    button.base_pos.y
+                        + (button.adjust_pos.y * systems.scale as f32).floor(),
+                ),
+                (button.size * systems.scale as f32).floor(),
+            ) {
+                button.set_click(systems, true);
+                button_found = Some(index)
+            }
+        }
+        button_found
+    }
+}
+
+pub fn reset_server_select_buttons(
+    menu_content: &mut MenuContent,
+    systems: &mut SystemHolder,
+) {
+    if !menu_content.did_button_click {
+        return;
+    }
+
+    menu_content.did_button_click = false;
+    menu_content
+        .server_select
+        .button
+        .iter_mut()
+        .for_each(|button| {
+            button.set_click(systems, false);
+        });
+}
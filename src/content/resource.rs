@@ -6,6 +6,7 @@ use graphics::*;
 
 use crate::{ClientError, TILE_SIZE};
 
+#[derive(Default)]
 pub struct TextureData {
     pub name: String,
     pub allocation: usize,
@@ -16,6 +17,7 @@ pub struct TilesheetData {
     pub tile: TileSheet,
 }
 
+#[derive(Default)]
 pub struct TextureAllocation {
     pub menu_bg: TextureData,
     pub horizontal_arrow: TextureData,
@@ -225,6 +225,22 @@ pub enum ClientError {
         #[snafu(backtrace)]
         backtrace: Backtrace,
     },
+    #[snafu(display(
+        "Session file is invalid: {message}. BACKTRACE: {backtrace:?}"
+    ))]
+    InvalidSession {
+        message: String,
+        #[snafu(backtrace)]
+        backtrace: Backtrace,
+    },
+    #[snafu(display(
+        "TLS trust setup failed: {message}. BACKTRACE: {backtrace:?}"
+    ))]
+    TlsTrust {
+        message: String,
+        #[snafu(backtrace)]
+        backtrace: Backtrace,
+    },
     #[snafu(display("Mutex PoisonError Occured. BACKTRACE: {backtrace:?}"))]
     MutexLockError {
         #[snafu(backtrace)]
@@ -1,6 +1,6 @@
 use std::{borrow::Cow, default};
 
-use crate::info;
+use crate::{ClientRenderer, info};
 use cosmic_text::Attrs;
 use graphics::*;
 use slab::Slab;
@@ -150,7 +150,11 @@ impl GfxCollection {
         GfxType::Light(self.light_storage.insert(GfxLight { data, gfx }))
     }
 
-    pub fn remove_gfx(&mut self, renderer: &mut GpuRenderer, index: &GfxType) {
+    pub fn remove_gfx(
+        &mut self,
+        renderer: &mut ClientRenderer,
+        index: &GfxType,
+    ) {
         match index {
             GfxType::Image(gfx_index) => {
                 if let Some(gfx) = self.image_storage.remove(*gfx_index) {
@@ -387,7 +391,7 @@ impl GfxCollection {
 
     pub fn set_text(
         &mut self,
-        renderer: &mut GpuRenderer,
+        renderer: &mut ClientRenderer,
         index: &GfxType,
         msg: &str,
     ) {
@@ -405,7 +409,7 @@ impl GfxCollection {
 
     pub fn set_rich_text<'s, 'r, I>(
         &mut self,
-        renderer: &mut GpuRenderer,
+        renderer: &mut ClientRenderer,
         index: &GfxType,
         msg: I,
     ) where
@@ -426,7 +430,7 @@ impl GfxCollection {
 
     pub fn set_text_wrap(
         &mut self,
-        renderer: &mut GpuRenderer,
+        renderer: &mut ClientRenderer,
         index: &GfxType,
         can_wrap: bool,
     ) {
@@ -170,9 +170,15 @@ pub const ORDER_VITAL_HP: f32 = 3.7;
 pub const ORDER_GUI_BUTTON: f32 = 3.9;
 pub const ORDER_GUI_BUTTON_DETAIL: f32 = 3.8;
 pub const ORDER_GUI_WINDOW: f32 = 2.999; // The whole 2.9xxx will be used for Game Window
+pub const ORDER_FOCUS_HIGHLIGHT: f32 = 1.991;
 pub const ORDER_HOLD_ITEM: f32 = 1.99;
 pub const ORDER_ITEM_DESC: f32 = 1.989;
 pub const ORDER_ITEM_DESC_TEXT: f32 = 1.988;
+pub const ORDER_SYNC_SHADE: f32 = 1.65;
+pub const ORDER_SYNC_WINDOW: f32 = 1.64;
+pub const ORDER_SYNC_BAR: f32 = 1.63;
+pub const ORDER_SYNC_FILL: f32 = 1.62;
+pub const ORDER_SYNC_TEXT: f32 = 1.62;
 pub const ORDER_ALERT_BG: f32 = 1.59;
 pub const ORDER_ALERT: f32 = 1.58;
 pub const ORDER_ALERT_HEADER: f32 = 1.57;
@@ -181,6 +187,10 @@ pub const ORDER_ALERT_TEXT: f32 = 1.57;
 pub const ORDER_ALERT_TEXTBOX_BG: f32 = 1.57;
 pub const ORDER_ALERT_TEXTBOX: f32 = 1.56;
 pub const ORDER_ALERT_BUTTON: f32 = 1.57;
+pub const ORDER_NET_STATS_BG: f32 = 1.4;
+pub const ORDER_NET_STATS_TEXT: f32 = 1.39;
+pub const ORDER_RECONNECT_BG: f32 = 1.3;
+pub const ORDER_RECONNECT_TEXT: f32 = 1.29;
 pub const ORDER_TOOLTIP: f32 = 1.05;
 pub const ORDER_TOOLTIP_TEXT: f32 = 1.0;
 pub const ORDER_FADE: f32 = 0.9;
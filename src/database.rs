@@ -1,9 +1,16 @@
+pub mod archive;
+pub mod data_file;
+pub mod datatool;
 pub mod items;
 pub mod map;
 pub mod npc;
 pub mod shops;
+pub mod table;
 
+pub use archive::*;
+pub use data_file::*;
 pub use items::*;
 pub use map::*;
 pub use npc::*;
 pub use shops::*;
+pub use table::*;
@@ -0,0 +1,277 @@
+use speedy::{Endianness, Readable, Writable};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, ErrorKind, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use crate::{DataFileFault, sha256_hex};
+
+/// The packed data files, inside the data root. Files it does not hold are
+/// read loose from the data root, which is how development data works.
+pub const ARCHIVE_FILE: &str = "content.pak";
+pub const ARCHIVE_MAGIC: [u8; 4] = *b"ASCP";
+/// Bumped whenever the header or table of contents layout changes.
+pub const ARCHIVE_VERSION: u32 = 1;
+/// The header is written before the table of contents offset is known, so
+/// it must keep a fixed size.
+const HEADER_LEN: u64 = 24;
+
+#[derive(Clone, Debug, PartialEq, Eq, Readable, Writable)]
+struct ArchiveHeader {
+    magic: [u8; 4],
+    version: u32,
+    toc_offset: u64,
+    toc_len: u64,
+}
+
+/// One packed file in the table of contents.
+#[derive(Clone, Debug, PartialEq, Eq, Readable, Writable)]
+pub struct ArchiveEntry {
+    /// Relative to the data root, for example `items/3.bin`.
+    pub path: String,
+    pub offset: u64,
+    pub len: u64,
+    /// Lower case hex SHA-256 of the entry's bytes.
+    pub sha256: String,
+}
+
+/// An open archive. Only the header and table of contents are read up
+/// front, each entry is read from disk when it is asked for.
+pub struct ContentArchive {
+    file: File,
+    entries: HashMap<String, ArchiveEntry>,
+}
+
+impl ContentArchive {
+    /// None when there is no archive at `path`.
+    pub fn open(path: &Path) -> Result<Option<Self>, DataFileFault> {
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(DataFileFault::Io(e.to_string())),
+        };
+
+        let mut bytes = [0; HEADER_LEN as usize];
+        file.read_exact(&mut bytes)
+            .map_err(|e| DataFileFault::Io(e.to_string()))?;
+
+        let header = ArchiveHeader::read_from_buffer_with_ctx(
+            Endianness::LittleEndian,
+            &bytes,
+        )
+        .map_err(|e| DataFileFault::Decode(e.to_string()))?;
+
+        if header.magic != ARCHIVE_MAGIC {
+            return Err(DataFileFault::Decode("not a content archive".into()));
+        }
+
+        if header.version != ARCHIVE_VERSION {
+            return Err(DataFileFault::Decode(format!(
+                "archive version {} is not {}",
+                header.version, ARCHIVE_VERSION
+            )));
+        }
+
+        let toc = read_at(&file, header.toc_offset, header.toc_len)?;
+        let entries = Vec::<ArchiveEntry>::read_from_buffer_with_ctx(
+            Endianness::LittleEndian,
+            &toc,
+        )
+        .map_err(|e| DataFileFault::Decode(e.to_string()))?;
+
+        Ok(Some(ContentArchive {
+            file,
+            entries: entries
+                .into_iter()
+                .map(|entry| (entry.path.clone(), entry))
+                .collect(),
+        }))
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.entries.contains_key(path)
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(String::as_str)
+    }
+
+    /// The checksum the table of contents lists for an entry.
+    pub fn sha256(&self, path: &str) -> Option<&str> {
+        self.entries.get(path).map(|entry| entry.sha256.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The bytes of one entry, checked against its checksum. None when the
+    /// archive does not hold it.
+    pub fn read(&self, path: &str) -> Option<Result<Vec<u8>, DataFileFault>> {
+        let entry = self.entries.get(path)?;
+
+        Some(
+            read_at(&self.file, entry.offset, entry.len).and_then(|bytes| {
+                let found = sha256_hex(&bytes);
+
+                if found == entry.sha256 {
+                    Ok(bytes)
+                } else {
+                    Err(DataFileFault::Checksum {
+                        expected: entry.sha256.clone(),
+                        found,
+                    })
+                }
+            }),
+        )
+    }
+
+    /// Packs the given files, relative to `root`, into an archive at `out`.
+    /// It is written next to `out` first and then renamed over it, so a
+    /// running client never sees half an archive.
+    pub fn pack<'a>(
+        root: &Path,
+        out: &Path,
+        files: impl IntoIterator<Item = &'a str>,
+    ) -> io::Result<usize> {
+        let temp = out.with_extension("pak.tmp");
+        let mut writer = File::create(&temp)?;
+        let mut entries = Vec::new();
+        let mut offset = HEADER_LEN;
+
+        writer.write_all(&[0; HEADER_LEN as usize])?;
+
+        for path in files {
+            let bytes = fs::read(root.join(path))?;
+
+            writer.write_all(&bytes)?;
+            entries.push(ArchiveEntry {
+                path: path.to_string(),
+                offset,
+                len: bytes.len() as u64,
+                sha256: sha256_hex(&bytes),
+            });
+            offset += bytes.len() as u64;
+        }
+
+        let toc = entries
+            .write_to_vec_with_ctx(Endianness::LittleEndian)
+            .map_err(io::Error::other)?;
+        let header = ArchiveHeader {
+            magic: ARCHIVE_MAGIC,
+            version: ARCHIVE_VERSION,
+            toc_offset: offset,
+            toc_len: toc.len() as u64,
+        }
+        .write_to_vec_with_ctx(Endianness::LittleEndian)
+        .map_err(io::Error::other)?;
+
+        writer.write_all(&toc)?;
+        writer.seek(SeekFrom::Start(0))?;
+        writer.write_all(&header)?;
+        writer.sync_all()?;
+        drop(writer);
+
+        fs::rename(&temp, out)?;
+        Ok(entries.len())
+    }
+}
+
+fn read_at(
+    file: &File,
+    offset: u64,
+    len: u64,
+) -> Result<Vec<u8>, DataFileFault> {
+    let file_len = file
+        .metadata()
+        .map_err(|e| DataFileFault::Io(e.to_string()))?
+        .len();
+
+    // Offsets come from the file itself, check them before allocating so
+    // a damaged header or table of contents can not ask for gigabytes.
+    if offset.checked_add(len).is_none_or(|end| end > file_len) {
+        return Err(DataFileFault::Decode(format!(
+            "{len} bytes at {offset} is past the end of the archive"
+        )));
+    }
+
+    let mut file = file;
+    let mut bytes = vec![0; len as usize];
+
+    file.seek(SeekFrom::Start(offset))
+        .and_then(|_| file.read_exact(&mut bytes))
+        .map_err(|e| match e.kind() {
+            ErrorKind::UnexpectedEof => {
+                DataFileFault::Decode("archive is cut short".into())
+            }
+            _ => DataFileFault::Io(e.to_string()),
+        })?;
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packed_entries_are_read_back_and_checked() {
+        let root = std::env::temp_dir()
+            .join(format!("ascending_archive_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("items")).unwrap();
+        fs::write(root.join("items/0.bin"), b"sword").unwrap();
+        fs::write(root.join("items/1.bin"), b"shield").unwrap();
+
+        let out = root.join(ARCHIVE_FILE);
+        assert_eq!(
+            ContentArchive::pack(&root, &out, ["items/0.bin", "items/1.bin"])
+                .unwrap(),
+            2
+        );
+
+        let archive = ContentArchive::open(&out).unwrap().unwrap();
+        assert_eq!(archive.read("items/1.bin").unwrap().unwrap(), b"shield");
+        assert!(archive.read("items/2.bin").is_none());
+
+        // Flip a byte of the first entry.
+        let mut bytes = fs::read(&out).unwrap();
+        bytes[HEADER_LEN as usize] ^= 0xff;
+        fs::write(&out, &bytes).unwrap();
+        let archive = ContentArchive::open(&out).unwrap().unwrap();
+        assert!(matches!(
+            archive.read("items/0.bin").unwrap(),
+            Err(DataFileFault::Checksum { .. })
+        ));
+        assert_eq!(archive.read("items/1.bin").unwrap().unwrap(), b"shield");
+
+        // A damaged table of contents length fails before allocating.
+        let mut huge = bytes.clone();
+        huge[16..24].copy_from_slice(&u64::MAX.to_le_bytes());
+        fs::write(&out, &huge).unwrap();
+        assert!(matches!(
+            ContentArchive::open(&out),
+            Err(DataFileFault::Decode(_))
+        ));
+
+        bytes[4] = 9;
+        fs::write(&out, &bytes).unwrap();
+        assert!(matches!(
+            ContentArchive::open(&out),
+            Err(DataFileFault::Decode(_))
+        ));
+        assert!(
+            ContentArchive::open(&root.join("none.pak"))
+                .unwrap()
+                .is_none()
+        );
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,769 @@
+use crate::{ARCHIVE_FILE, ContentArchive};
+use log::{info, warn};
+use ring::digest::{SHA256, digest};
+use serde::{Deserialize, Serialize};
+use speedy::{Endianness, Readable};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt::{self, Write},
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+pub const DATA_ROOT: &str = "./data";
+/// SHA-256 of every shipped data file, relative to `DATA_ROOT`.
+pub const DATA_MANIFEST: &str = "manifest.toml";
+/// Damaged files are moved here, under their own relative path.
+pub const QUARANTINE_DIR: &str = "quarantine";
+/// Files downloaded from the server, under their own relative path. They
+/// are read before the archive and the loose files.
+pub const PATCH_DIR: &str = "patch";
+/// Files the server does not have, one path per line, inside the patch
+/// folder. They are treated as missing until the server sends them again.
+pub const REMOVED_LIST: &str = "removed.txt";
+/// The folders of numbered data files under the data root.
+pub const DATA_DIRS: [&str; 4] = ["items", "npcs", "shops", "maps"];
+
+/// Why a data file could not be used.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DataFileFault {
+    /// Listed in the manifest but not on disk.
+    Missing,
+    Io(String),
+    Checksum {
+        expected: String,
+        found: String,
+    },
+    /// The file could not be parsed, usually because it was cut short or
+    /// was written by another version.
+    Decode(String),
+}
+
+impl fmt::Display for DataFileFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataFileFault::Missing => write!(f, "missing"),
+            DataFileFault::Io(e) => write!(f, "could not be read, {e}"),
+            DataFileFault::Checksum { expected, found } => {
+                write!(f, "checksum {found} does not match {expected}")
+            }
+            DataFileFault::Decode(e) => write!(f, "could not be parsed, {e}"),
+        }
+    }
+}
+
+/// Where the bytes of a data file were read from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DataSource {
+    Patch,
+    Archive,
+    Loose,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DataFileError {
+    /// Relative to the data root, for example `items/3.bin`.
+    pub path: String,
+    pub fault: DataFileFault,
+    /// The copy that was read, None when there was none to read.
+    pub origin: Option<DataSource>,
+}
+
+impl DataFileError {
+    fn new(
+        path: &str,
+        fault: DataFileFault,
+        origin: Option<DataSource>,
+    ) -> Self {
+        DataFileError {
+            path: path.to_string(),
+            fault,
+            origin,
+        }
+    }
+}
+
+impl fmt::Display for DataFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.path, self.fault)
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct DataManifest {
+    pub version: u32,
+    /// Relative path to lower case hex SHA-256.
+    pub files: BTreeMap<String, String>,
+}
+
+impl DataManifest {
+    /// None when the data folder has no manifest, so nothing is checked.
+    pub fn load(root: &Path) -> Option<Self> {
+        let path = root.join(DATA_MANIFEST);
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                info!("No {DATA_MANIFEST}, data files are not checksummed");
+                return None;
+            }
+            Err(e) => {
+                warn!("Could not read {}: {}", path.display(), e);
+                return None;
+            }
+        };
+
+        match toml::from_str(&text) {
+            Ok(manifest) => Some(manifest),
+            Err(e) => {
+                warn!("Ignoring {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Hashes the given files to build a manifest for a release.
+    pub fn generate<'a>(
+        root: &Path,
+        files: impl IntoIterator<Item = &'a str>,
+    ) -> std::io::Result<Self> {
+        let mut manifest = DataManifest {
+            version: 1,
+            ..Default::default()
+        };
+
+        for file in files {
+            let bytes = fs::read(root.join(file))?;
+            manifest.files.insert(file.to_string(), sha256_hex(&bytes));
+        }
+
+        Ok(manifest)
+    }
+
+    pub fn check(&self, path: &str, bytes: &[u8]) -> Result<(), DataFileFault> {
+        let Some(expected) = self.files.get(path) else {
+            return Ok(());
+        };
+
+        let found = sha256_hex(bytes);
+
+        if found.eq_ignore_ascii_case(expected) {
+            Ok(())
+        } else {
+            Err(DataFileFault::Checksum {
+                expected: expected.clone(),
+                found,
+            })
+        }
+    }
+}
+
+/// True for a path such as `items/3.bin` in one of `DATA_DIRS`. Paths
+/// from the server are checked with this before anything is written.
+pub fn is_data_path(path: &str) -> bool {
+    let Some((dir, name)) = path.split_once('/') else {
+        return false;
+    };
+
+    DATA_DIRS.contains(&dir)
+        && name.strip_suffix(".bin").is_some_and(|stem| {
+            !stem.is_empty()
+                && stem
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        })
+}
+
+/// Hash of a whole content set, one `path sha256` line per file in path
+/// order. The server hashes its own files the same way.
+pub fn content_hash(files: &BTreeMap<String, String>) -> String {
+    let mut lines = String::new();
+
+    for (path, sha256) in files {
+        let _ = writeln!(lines, "{path} {sha256}");
+    }
+
+    sha256_hex(lines.as_bytes())
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(64);
+
+    for byte in digest(&SHA256, bytes).as_ref() {
+        let _ = write!(hex, "{:02x}", byte);
+    }
+
+    hex
+}
+
+/// Reads data files for the database loaders, from the patch folder, then
+/// the content archive, then loose from the data root. Files that
+/// fail their checksum or do not parse are moved to the quarantine folder
+/// and remembered, so the player can be told which ones to repair.
+pub struct DataFiles {
+    root: PathBuf,
+    manifest: Option<DataManifest>,
+    archive: Option<ContentArchive>,
+    /// Path to SHA-256 of every file as it would be read, built when first
+    /// asked for.
+    hashes: Option<BTreeMap<String, String>>,
+    removed: BTreeSet<String>,
+    damaged: Vec<DataFileError>,
+    reported: HashSet<String>,
+    announced: usize,
+}
+
+impl DataFiles {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        let mut damaged = Vec::new();
+
+        // A damaged archive is left in place for the updater to replace,
+        // and everything is read loose until then.
+        let archive = match ContentArchive::open(&root.join(ARCHIVE_FILE)) {
+            Ok(Some(archive)) => {
+                info!("Reading {} entries from {ARCHIVE_FILE}", archive.len());
+                Some(archive)
+            }
+            Ok(None) => None,
+            Err(fault) => {
+                let error = DataFileError::new(ARCHIVE_FILE, fault, None);
+
+                warn!("Not using data archive {}", error);
+                damaged.push(error);
+                None
+            }
+        };
+
+        let removed =
+            fs::read_to_string(root.join(PATCH_DIR).join(REMOVED_LIST))
+                .map(|text| {
+                    text.lines()
+                        .filter(|path| is_data_path(path))
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default();
+
+        DataFiles {
+            manifest: DataManifest::load(&root),
+            archive,
+            root,
+            hashes: None,
+            removed,
+            damaged,
+            reported: HashSet::new(),
+            announced: 0,
+        }
+    }
+
+    pub fn exists(&self, path: &str) -> bool {
+        if self.removed.contains(path) {
+            return false;
+        }
+
+        self.root.join(PATCH_DIR).join(path).exists()
+            || self
+                .archive
+                .as_ref()
+                .is_some_and(|archive| archive.contains(path))
+            || self.root.join(path).exists()
+    }
+
+    /// The stems of the `.bin` files in `dir`, from the patch folder, the
+    /// archive, the loose folder and the manifest, so the loaders only open
+    /// files that exist or are expected to. Removed files are left out.
+    pub fn names(&self, dir: &str) -> BTreeSet<String> {
+        let prefix = format!("{dir}/");
+        let stem = |path: &str| {
+            path.strip_prefix(&prefix)?
+                .strip_suffix(".bin")
+                .filter(|stem| !stem.contains('/'))
+                .map(str::to_string)
+        };
+
+        let mut names: BTreeSet<String> = self
+            .archive
+            .iter()
+            .flat_map(|archive| archive.paths())
+            .chain(
+                self.manifest
+                    .iter()
+                    .flat_map(|manifest| manifest.files.keys())
+                    .map(String::as_str),
+            )
+            .filter_map(stem)
+            .collect();
+
+        for folder in [self.root.join(PATCH_DIR).join(dir), self.root.join(dir)]
+        {
+            if let Ok(entries) = fs::read_dir(folder) {
+                names.extend(entries.filter_map(|entry| {
+                    let name = entry.ok()?.file_name().into_string().ok()?;
+                    Some(name.strip_suffix(".bin")?.to_string())
+                }));
+            }
+        }
+
+        names.retain(|name| {
+            !self.removed.contains(&format!("{dir}/{name}.bin"))
+        });
+        names
+    }
+
+    /// The numbered files in `dir` below `max`, in order.
+    pub fn ids(&self, dir: &str, max: usize) -> Vec<usize> {
+        let ids: BTreeSet<usize> = self
+            .names(dir)
+            .iter()
+            .filter_map(|name| name.parse().ok())
+            .filter(|id| *id < max)
+            .collect();
+
+        ids.into_iter().collect()
+    }
+
+    /// Reads and parses one file. Ok(None) when it is not there and the
+    /// manifest does not expect it.
+    pub fn read<T>(&self, path: &str) -> Result<Option<T>, DataFileError>
+    where
+        T: for<'a> Readable<'a, Endianness>,
+    {
+        if self.removed.contains(path) {
+            return Ok(None);
+        }
+
+        let (bytes, origin) =
+            match self.read_loose(&format!("{PATCH_DIR}/{path}")) {
+                Ok(bytes) => (bytes, DataSource::Patch),
+                Err(DataFileFault::Missing) => match self.read_shipped(path)? {
+                    Some(shipped) => shipped,
+                    None => return Ok(None),
+                },
+                Err(fault) => {
+                    return Err(DataFileError::new(
+                        path,
+                        fault,
+                        Some(DataSource::Patch),
+                    ));
+                }
+            };
+
+        T::read_from_buffer_with_ctx(Endianness::LittleEndian, &bytes)
+            .map(Some)
+            .map_err(|e| {
+                let fault = DataFileFault::Decode(e.to_string());
+                DataFileError::new(path, fault, Some(origin))
+            })
+    }
+
+    /// Reads a file from the archive or loose, along with where it came
+    /// from. Patches were checked against the server's checksum when they
+    /// were downloaded, so only shipped files are checked against the
+    /// manifest.
+    fn read_shipped(
+        &self,
+        path: &str,
+    ) -> Result<Option<(Vec<u8>, DataSource)>, DataFileError> {
+        let expected = self
+            .manifest
+            .as_ref()
+            .is_some_and(|manifest| manifest.files.contains_key(path));
+
+        let (read, origin) = match self
+            .archive
+            .as_ref()
+            .and_then(|archive| archive.read(path))
+        {
+            Some(packed) => (packed, DataSource::Archive),
+            None => (self.read_loose(path), DataSource::Loose),
+        };
+
+        let bytes = match read {
+            Ok(bytes) => bytes,
+            Err(DataFileFault::Missing) if !expected => return Ok(None),
+            Err(DataFileFault::Missing) => {
+                return Err(DataFileError::new(
+                    path,
+                    DataFileFault::Missing,
+                    None,
+                ));
+            }
+            Err(fault) => {
+                return Err(DataFileError::new(path, fault, Some(origin)));
+            }
+        };
+
+        if let Some(manifest) = &self.manifest {
+            manifest.check(path, &bytes).map_err(|fault| {
+                DataFileError::new(path, fault, Some(origin))
+            })?;
+        }
+
+        Ok(Some((bytes, origin)))
+    }
+
+    fn read_loose(&self, path: &str) -> Result<Vec<u8>, DataFileFault> {
+        fs::read(self.root.join(path)).map_err(|e| match e.kind() {
+            ErrorKind::NotFound => DataFileFault::Missing,
+            _ => DataFileFault::Io(e.to_string()),
+        })
+    }
+
+    /// The SHA-256 of every data file as the loaders would read it. Only
+    /// loose files are read to hash them, archive entries use their table
+    /// of contents.
+    pub fn content_hashes(&mut self) -> &BTreeMap<String, String> {
+        if self.hashes.is_none() {
+            let mut hashes = BTreeMap::new();
+
+            for dir in DATA_DIRS {
+                for name in self.names(dir) {
+                    let path = format!("{dir}/{name}.bin");
+                    let patched = self.root.join(PATCH_DIR).join(&path);
+                    let packed = self
+                        .archive
+                        .as_ref()
+                        .and_then(|archive| archive.sha256(&path))
+                        .filter(|_| !patched.exists());
+
+                    let hash = match packed {
+                        Some(hash) => Some(hash.to_string()),
+                        None => fs::read(&patched)
+                            .or_else(|_| fs::read(self.root.join(&path)))
+                            .ok()
+                            .map(|bytes| sha256_hex(&bytes)),
+                    };
+
+                    if let Some(hash) = hash {
+                        hashes.insert(path, hash);
+                    }
+                }
+            }
+
+            self.hashes = Some(hashes);
+        }
+
+        self.hashes.get_or_insert_default()
+    }
+
+    /// Writes a file downloaded from the server to the patch folder, where
+    /// it is read before the archive.
+    pub fn write_patch(
+        &mut self,
+        path: &str,
+        bytes: &[u8],
+    ) -> std::io::Result<()> {
+        if !is_data_path(path) {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("{path} is not a data file"),
+            ));
+        }
+
+        let to = self.root.join(PATCH_DIR).join(path);
+        let temp = to.with_extension("bin.tmp");
+
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(&temp, bytes)?;
+        fs::rename(&temp, &to)?;
+
+        // A fixed file can be reported again if it ever breaks.
+        self.reported.remove(path);
+
+        if self.removed.remove(path) {
+            self.save_removed()?;
+        }
+
+        if let Some(hashes) = &mut self.hashes {
+            hashes.insert(path.to_string(), sha256_hex(bytes));
+        }
+
+        Ok(())
+    }
+
+    /// Drops files the server does not have, so they are neither loaded nor
+    /// hashed. Their patches are deleted, and shipped files are listed in
+    /// `REMOVED_LIST` since the archive can not be changed.
+    pub fn remove(&mut self, paths: &[String]) -> std::io::Result<()> {
+        for path in paths.iter().filter(|path| is_data_path(path)) {
+            match fs::remove_file(self.root.join(PATCH_DIR).join(path)) {
+                Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+
+            self.removed.insert(path.clone());
+
+            if let Some(hashes) = &mut self.hashes {
+                hashes.remove(path);
+            }
+        }
+
+        self.save_removed()
+    }
+
+    fn save_removed(&self) -> std::io::Result<()> {
+        let dir = self.root.join(PATCH_DIR);
+        let mut text = String::new();
+
+        for path in &self.removed {
+            let _ = writeln!(text, "{path}");
+        }
+
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join(REMOVED_LIST), text)
+    }
+
+    /// Logs and quarantines a file that failed to load.
+    pub fn reject(&mut self, error: DataFileError) {
+        if !self.reported.insert(error.path.clone()) {
+            return;
+        }
+
+        warn!("Skipping data file {}", error);
+
+        self.quarantine(&error);
+        self.damaged.push(error);
+    }
+
+    /// Moves the copy that failed out of the way. An archive entry can not
+    /// be moved, and the loose file next to it was never read, so it stays.
+    fn quarantine(&self, error: &DataFileError) {
+        let path = &error.path;
+        let from = match error.origin {
+            Some(DataSource::Patch) => self.root.join(PATCH_DIR).join(path),
+            Some(DataSource::Loose) => self.root.join(path),
+            Some(DataSource::Archive) | None => return,
+        };
+        let to = self.root.join(QUARANTINE_DIR).join(path);
+
+        if !from.exists() {
+            return;
+        }
+
+        if let Some(parent) = to.parent()
+            && let Err(e) = fs::create_dir_all(parent)
+        {
+            warn!("Could not create {}: {}", parent.display(), e);
+            return;
+        }
+
+        if let Err(e) = fs::rename(&from, &to) {
+            warn!("Could not quarantine {}: {}", path, e);
+        }
+    }
+
+    pub fn damaged(&self) -> &[DataFileError] {
+        &self.damaged
+    }
+
+    /// The alert text for files damaged since the last call, if any.
+    pub fn take_alert(&mut self) -> Option<String> {
+        let new = self.damaged.get(self.announced..)?;
+
+        if new.is_empty() {
+            return None;
+        }
+
+        let mut list = new
+            .iter()
+            .take(5)
+            .map(|error| error.path.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        if new.len() > 5 {
+            let _ = write!(list, " and {} more", new.len() - 5);
+        }
+
+        self.announced = self.damaged.len();
+
+        Some(format!(
+            "Some data files are damaged, please repair the game: {list}"
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ShopItem;
+    use speedy::Writable;
+
+    fn shop_item(price: u64) -> ShopItem {
+        ShopItem {
+            index: 3,
+            amount: 1,
+            price,
+        }
+    }
+
+    #[test]
+    fn bad_files_are_reported_and_quarantined() {
+        let root = std::env::temp_dir()
+            .join(format!("ascending_data_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("shops")).unwrap();
+
+        let good = shop_item(50).write_to_vec().unwrap();
+        fs::write(root.join("shops/0.bin"), &good).unwrap();
+        fs::write(root.join("shops/1.bin"), &good[..5]).unwrap();
+        fs::write(
+            root.join("shops/2.bin"),
+            shop_item(1).write_to_vec().unwrap(),
+        )
+        .unwrap();
+
+        let mut manifest =
+            DataManifest::generate(&root, ["shops/0.bin", "shops/1.bin"])
+                .unwrap();
+        manifest
+            .files
+            .insert("shops/2.bin".into(), sha256_hex(&good));
+        manifest
+            .files
+            .insert("shops/3.bin".into(), sha256_hex(&good));
+        fs::write(
+            root.join(DATA_MANIFEST),
+            toml::to_string(&manifest).unwrap(),
+        )
+        .unwrap();
+
+        let mut files = DataFiles::new(&root);
+        assert_eq!(
+            files
+                .read::<ShopItem>("shops/0.bin")
+                .unwrap()
+                .unwrap()
+                .price,
+            50
+        );
+        assert!(files.read::<ShopItem>("shops/9.bin").unwrap().is_none());
+
+        // The manifest catches the truncated file before it is parsed, and
+        // a whole file with the wrong contents.
+        for path in ["shops/1.bin", "shops/2.bin"] {
+            let error = files.read::<ShopItem>(path).unwrap_err();
+            assert!(matches!(error.fault, DataFileFault::Checksum { .. }));
+            files.reject(error);
+        }
+        let error = files.read::<ShopItem>("shops/3.bin").unwrap_err();
+        assert_eq!(error.fault, DataFileFault::Missing);
+        files.reject(error.clone());
+        files.reject(error);
+
+        assert_eq!(files.damaged().len(), 3);
+        assert!(root.join(QUARANTINE_DIR).join("shops/1.bin").exists());
+        assert!(!root.join("shops/1.bin").exists());
+
+        let alert = files.take_alert().unwrap();
+        assert!(alert.ends_with("shops/1.bin, shops/2.bin, shops/3.bin"));
+        assert_eq!(files.take_alert(), None);
+
+        // Without a manifest a truncated file fails to parse.
+        fs::remove_file(root.join(DATA_MANIFEST)).unwrap();
+        fs::write(root.join("shops/4.bin"), &good[..5]).unwrap();
+        let mut files = DataFiles::new(&root);
+        assert!(matches!(
+            files.read::<ShopItem>("shops/4.bin").unwrap_err().fault,
+            DataFileFault::Decode(_)
+        ));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn only_the_copy_that_was_read_is_quarantined() {
+        let root = std::env::temp_dir()
+            .join(format!("ascending_origin_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("shops")).unwrap();
+
+        let good = shop_item(50).write_to_vec().unwrap();
+        fs::write(root.join("shops/0.bin"), &good).unwrap();
+        fs::write(root.join("shops/1.bin"), &good).unwrap();
+
+        let out = root.join(ARCHIVE_FILE);
+        ContentArchive::pack(&root, &out, ["shops/0.bin"]).unwrap();
+        let mut packed = fs::read(&out).unwrap();
+        let at = packed
+            .windows(good.len())
+            .position(|window| window == good.as_slice())
+            .unwrap();
+        packed[at] ^= 0xff;
+        fs::write(&out, &packed).unwrap();
+
+        // A damaged archive entry leaves the loose file it shadows alone.
+        let mut files = DataFiles::new(&root);
+        let error = files.read::<ShopItem>("shops/0.bin").unwrap_err();
+        assert!(matches!(error.fault, DataFileFault::Checksum { .. }));
+        assert_eq!(error.origin, Some(DataSource::Archive));
+        files.reject(error);
+        assert!(root.join("shops/0.bin").exists());
+        assert!(!root.join(QUARANTINE_DIR).join("shops/0.bin").exists());
+
+        // A broken patch is moved, not the shipped file under it.
+        files.write_patch("shops/1.bin", &good[..5]).unwrap();
+        let error = files.read::<ShopItem>("shops/1.bin").unwrap_err();
+        assert_eq!(error.origin, Some(DataSource::Patch));
+        files.reject(error);
+        assert!(root.join("shops/1.bin").exists());
+        assert!(!root.join(PATCH_DIR).join("shops/1.bin").exists());
+        assert!(root.join(QUARANTINE_DIR).join("shops/1.bin").exists());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn patches_are_read_first_and_change_the_content_hash() {
+        let root = std::env::temp_dir()
+            .join(format!("ascending_patch_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("shops")).unwrap();
+
+        let shipped = shop_item(50).write_to_vec().unwrap();
+        fs::write(root.join("shops/0.bin"), &shipped).unwrap();
+
+        let mut files = DataFiles::new(&root);
+        let before = content_hash(files.content_hashes());
+
+        let patch = shop_item(75).write_to_vec().unwrap();
+        files.write_patch("shops/0.bin", &patch).unwrap();
+        assert!(files.write_patch("../settings.toml", &patch).is_err());
+        assert!(files.write_patch("shops/../../x.bin", &patch).is_err());
+
+        assert_eq!(
+            files
+                .read::<ShopItem>("shops/0.bin")
+                .unwrap()
+                .unwrap()
+                .price,
+            75
+        );
+        assert_ne!(content_hash(files.content_hashes()), before);
+        assert_eq!(
+            DataFiles::new(&root).content_hashes(),
+            files.content_hashes()
+        );
+
+        // Files the server does not have stay out of the hash for good.
+        fs::write(root.join("shops/1.bin"), &shipped).unwrap();
+        let mut files = DataFiles::new(&root);
+        let extra = content_hash(files.content_hashes());
+        files
+            .remove(&["shops/0.bin".into(), "shops/1.bin".into()])
+            .unwrap();
+        assert!(files.read::<ShopItem>("shops/1.bin").unwrap().is_none());
+        assert!(!root.join(PATCH_DIR).join("shops/0.bin").exists());
+        assert_ne!(content_hash(files.content_hashes()), extra);
+        assert!(DataFiles::new(&root).content_hashes().is_empty());
+
+        files.write_patch("shops/1.bin", &patch).unwrap();
+        assert_eq!(DataFiles::new(&root).ids("shops", 10), vec![1]);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,781 @@
+use log::{LevelFilter, Metadata, Record};
+use serde::{Serialize, de::DeserializeOwned};
+use speedy::{Endianness, Readable, Writable};
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    ARCHIVE_FILE, ContentArchive, DATA_MANIFEST, DATA_ROOT, DataFiles,
+    DataManifest, ItemData, MapAttribute, MapData, NpcData, ShopData,
+    data_types::*,
+};
+
+/// Where `export` writes and `import` reads the text files by default.
+pub const TEXT_ROOT: &str = "./data_src";
+
+const USAGE: &str = "\
+usage: client --datatool <command> [kinds] [options]
+
+commands:
+  export    write the data files out as text files
+  import    write text files back as data/<kind>/*.bin
+  validate  check every data file and the references between them
+  manifest  write data/manifest.toml with the checksum of every data file
+  pack      pack every data/<kind>/*.bin into data/content.pak
+
+kinds: items, npcs, shops, maps or all (the default)
+
+options:
+  --format json|toml  text format, json by default
+  --data <dir>        binary data folder, ./data by default
+  --text <dir>        text folder, ./data_src by default";
+
+/// Prints the loaders' log lines to stderr with the tool's own problems,
+/// instead of to the client's log.txt.
+struct ToolLogger;
+
+impl log::Log for ToolLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::Level::Info
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("{} - {}", record.level(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static TOOL_LOGGER: ToolLogger = ToolLogger;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DataKind {
+    Items,
+    Npcs,
+    Shops,
+    Maps,
+}
+
+impl DataKind {
+    pub const ALL: [DataKind; 4] = [
+        DataKind::Items,
+        DataKind::Npcs,
+        DataKind::Shops,
+        DataKind::Maps,
+    ];
+
+    /// The folder under the data root, also the name on the command line.
+    pub fn dir(self) -> &'static str {
+        match self {
+            DataKind::Items => "items",
+            DataKind::Npcs => "npcs",
+            DataKind::Shops => "shops",
+            DataKind::Maps => "maps",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TextFormat {
+    Json,
+    Toml,
+}
+
+impl TextFormat {
+    pub fn ext(self) -> &'static str {
+        match self {
+            TextFormat::Json => "json",
+            TextFormat::Toml => "toml",
+        }
+    }
+
+    fn to_text<T: Serialize>(self, data: &T) -> Result<String, String> {
+        match self {
+            TextFormat::Json => {
+                serde_json::to_string_pretty(data).map_err(|e| e.to_string())
+            }
+            TextFormat::Toml => {
+                toml::to_string_pretty(data).map_err(|e| e.to_string())
+            }
+        }
+    }
+
+    fn from_text<T: DeserializeOwned>(self, text: &str) -> Result<T, String> {
+        match self {
+            TextFormat::Json => {
+                serde_json::from_str(text).map_err(|e| e.to_string())
+            }
+            TextFormat::Toml => toml::from_str(text).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DataCommand {
+    Export,
+    Import,
+    Validate,
+    Manifest,
+    Pack,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DataToolArgs {
+    pub command: DataCommand,
+    pub kinds: Vec<DataKind>,
+    pub format: TextFormat,
+    pub data: PathBuf,
+    pub text: PathBuf,
+}
+
+impl DataToolArgs {
+    /// Parses the arguments after the program name. Accepts both
+    /// `--key value` and `--key=value`.
+    pub fn parse<I>(args: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut args = args.into_iter();
+        let command = match args.next().as_deref() {
+            Some("export") => DataCommand::Export,
+            Some("import") => DataCommand::Import,
+            Some("validate") => DataCommand::Validate,
+            Some("manifest") => DataCommand::Manifest,
+            Some("pack") => DataCommand::Pack,
+            Some(other) => return Err(format!("unknown command {other}")),
+            None => return Err("no command given".into()),
+        };
+
+        let mut parsed = DataToolArgs {
+            command,
+            kinds: Vec::new(),
+            format: TextFormat::Json,
+            data: PathBuf::from(DATA_ROOT),
+            text: PathBuf::from(TEXT_ROOT),
+        };
+
+        while let Some(arg) = args.next() {
+            let (key, value) = match arg.split_once('=') {
+                Some((key, value)) => {
+                    (key.to_string(), Some(value.to_string()))
+                }
+                None => (arg, None),
+            };
+
+            if !key.starts_with("--") {
+                match key.as_str() {
+                    "all" => parsed.kinds.extend(DataKind::ALL),
+                    kind => parsed.kinds.push(
+                        DataKind::ALL
+                            .into_iter()
+                            .find(|k| k.dir() == kind)
+                            .ok_or_else(|| format!("unknown kind {kind}"))?,
+                    ),
+                }
+                continue;
+            }
+
+            let value = value
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("{key} needs a value"))?;
+
+            match key.as_str() {
+                "--format" => {
+                    parsed.format = match value.as_str() {
+                        "json" => TextFormat::Json,
+                        "toml" => TextFormat::Toml,
+                        other => return Err(format!("unknown format {other}")),
+                    }
+                }
+                "--data" => parsed.data = PathBuf::from(value),
+                "--text" => parsed.text = PathBuf::from(value),
+                other => return Err(format!("unknown option {other}")),
+            }
+        }
+
+        if parsed.kinds.is_empty() {
+            parsed.kinds.extend(DataKind::ALL);
+        }
+
+        Ok(parsed)
+    }
+}
+
+/// Runs the tool and returns the process exit code.
+pub fn run<I>(args: I) -> i32
+where
+    I: IntoIterator<Item = String>,
+{
+    if log::set_logger(&TOOL_LOGGER).is_ok() {
+        log::set_max_level(LevelFilter::Info);
+    }
+
+    let args = match DataToolArgs::parse(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            return 2;
+        }
+    };
+
+    let problems = match args.command {
+        DataCommand::Export => args
+            .kinds
+            .iter()
+            .flat_map(|kind| export(*kind, args.format, &args.data, &args.text))
+            .collect(),
+        DataCommand::Import => args
+            .kinds
+            .iter()
+            .flat_map(|kind| import(*kind, args.format, &args.text, &args.data))
+            .collect(),
+        DataCommand::Validate => validate(&args.data),
+        DataCommand::Manifest => write_manifest(&args.data),
+        DataCommand::Pack => pack(&args.data),
+    };
+
+    for problem in problems.iter() {
+        eprintln!("{problem}");
+    }
+
+    if problems.is_empty() {
+        0
+    } else {
+        eprintln!("{} problem(s) found", problems.len());
+        1
+    }
+}
+
+/// The files in `dir` with the extension, as (file stem, path), sorted.
+fn list_files(dir: &Path, ext: &str) -> Vec<(String, PathBuf)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut files: Vec<(String, PathBuf)> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            (path.extension()? == ext).then_some(())?;
+            Some((path.file_stem()?.to_str()?.to_string(), path))
+        })
+        .collect();
+
+    files.sort();
+    files
+}
+
+/// Every loose `.bin` file under the data folder, relative to it.
+fn loose_files(data: &Path) -> Vec<String> {
+    DataKind::ALL
+        .into_iter()
+        .flat_map(|kind| {
+            list_files(&data.join(kind.dir()), "bin")
+                .into_iter()
+                .map(move |(stem, _)| format!("{}/{stem}.bin", kind.dir()))
+        })
+        .collect()
+}
+
+fn convert_out<T>(
+    files: &DataFiles,
+    from: &str,
+    to: &Path,
+    format: TextFormat,
+) -> Result<(), String>
+where
+    T: for<'a> Readable<'a, Endianness> + Serialize,
+{
+    let data: T = files
+        .read(from)
+        .map_err(|e| e.fault.to_string())?
+        .ok_or("missing")?;
+    fs::write(to, format.to_text(&data)?).map_err(|e| e.to_string())
+}
+
+fn convert_in<T>(
+    from: &Path,
+    to: &Path,
+    format: TextFormat,
+) -> Result<(), String>
+where
+    T: Writable<Endianness> + DeserializeOwned,
+{
+    let text = fs::read_to_string(from).map_err(|e| e.to_string())?;
+    let data: T = format.from_text(&text)?;
+    let bytes = data
+        .write_to_vec_with_ctx(Endianness::LittleEndian)
+        .map_err(|e| e.to_string())?;
+    fs::write(to, bytes).map_err(|e| e.to_string())
+}
+
+pub fn export(
+    kind: DataKind,
+    format: TextFormat,
+    data: &Path,
+    text: &Path,
+) -> Vec<String> {
+    let out = text.join(kind.dir());
+
+    if let Err(e) = fs::create_dir_all(&out) {
+        return vec![format!("{}: {}", out.display(), e)];
+    }
+
+    let files = DataFiles::new(data);
+
+    files
+        .names(kind.dir())
+        .into_iter()
+        .filter_map(|stem| {
+            let from = format!("{}/{stem}.bin", kind.dir());
+            let to = out.join(format!("{stem}.{}", format.ext()));
+            let result = match kind {
+                DataKind::Items => {
+                    convert_out::<ItemData>(&files, &from, &to, format)
+                }
+                DataKind::Npcs => {
+                    convert_out::<NpcData>(&files, &from, &to, format)
+                }
+                DataKind::Shops => {
+                    convert_out::<ShopData>(&files, &from, &to, format)
+                }
+                DataKind::Maps => {
+                    convert_out::<MapData>(&files, &from, &to, format)
+                }
+            };
+
+            result.err().map(|e| format!("{from}: {e}"))
+        })
+        .collect()
+}
+
+/// Writes the text files back as binary data files. A data manifest, if
+/// there is one, gets the new checksums.
+pub fn import(
+    kind: DataKind,
+    format: TextFormat,
+    text: &Path,
+    data: &Path,
+) -> Vec<String> {
+    let out = data.join(kind.dir());
+
+    if let Err(e) = fs::create_dir_all(&out) {
+        return vec![format!("{}: {}", out.display(), e)];
+    }
+
+    let mut written = Vec::new();
+    let mut problems: Vec<String> =
+        list_files(&text.join(kind.dir()), format.ext())
+            .into_iter()
+            .filter_map(|(stem, from)| {
+                let to = out.join(format!("{stem}.bin"));
+                let result = match kind {
+                    DataKind::Items => {
+                        convert_in::<ItemData>(&from, &to, format)
+                    }
+                    DataKind::Npcs => convert_in::<NpcData>(&from, &to, format),
+                    DataKind::Shops => {
+                        convert_in::<ShopData>(&from, &to, format)
+                    }
+                    DataKind::Maps => convert_in::<MapData>(&from, &to, format),
+                };
+
+                match result {
+                    Ok(()) => {
+                        written.push(format!("{}/{stem}.bin", kind.dir()));
+                        None
+                    }
+                    Err(e) => Some(format!("{}: {}", from.display(), e)),
+                }
+            })
+            .collect();
+
+    if let Some(mut manifest) = DataManifest::load(data) {
+        match DataManifest::generate(data, written.iter().map(String::as_str)) {
+            Ok(updated) => {
+                manifest.files.extend(updated.files);
+                problems.extend(save_manifest(data, &manifest));
+            }
+            Err(e) => problems.push(format!("{DATA_MANIFEST}: {e}")),
+        }
+    }
+
+    if data.join(ARCHIVE_FILE).exists() {
+        println!(
+            "{ARCHIVE_FILE} is read before loose files, run pack to use the \
+             imported files"
+        );
+    }
+
+    problems
+}
+
+fn save_manifest(data: &Path, manifest: &DataManifest) -> Option<String> {
+    let path = data.join(DATA_MANIFEST);
+    let result = toml::to_string_pretty(manifest)
+        .map_err(|e| e.to_string())
+        .and_then(|text| fs::write(&path, text).map_err(|e| e.to_string()));
+
+    result.err().map(|e| format!("{}: {}", path.display(), e))
+}
+
+pub fn write_manifest(data: &Path) -> Vec<String> {
+    let files = loose_files(data);
+
+    match DataManifest::generate(data, files.iter().map(String::as_str)) {
+        Ok(manifest) => save_manifest(data, &manifest).into_iter().collect(),
+        Err(e) => vec![format!("{DATA_MANIFEST}: {e}")],
+    }
+}
+
+/// Packs the loose data files into the archive the client reads first.
+pub fn pack(data: &Path) -> Vec<String> {
+    let files = loose_files(data);
+    let out = data.join(ARCHIVE_FILE);
+
+    match ContentArchive::pack(data, &out, files.iter().map(String::as_str)) {
+        Ok(count) => {
+            println!("Packed {count} files into {}", out.display());
+            Vec::new()
+        }
+        Err(e) => vec![format!("{}: {}", out.display(), e)],
+    }
+}
+
+/// Loads every data file the way the client does and checks the ids they
+/// refer to. Returns one line per problem.
+pub fn validate(data: &Path) -> Vec<String> {
+    let files = DataFiles::new(data);
+    let mut problems: Vec<String> =
+        files.damaged().iter().map(ToString::to_string).collect();
+
+    let paths = |kind: DataKind| -> Vec<String> {
+        files
+            .names(kind.dir())
+            .into_iter()
+            .map(|stem| format!("{}/{stem}.bin", kind.dir()))
+            .collect()
+    };
+
+    let mut item_ids = HashSet::new();
+    for path in paths(DataKind::Items) {
+        match (id_of(&path, MAX_ITEMS), files.read::<ItemData>(&path)) {
+            (Some(id), Ok(Some(_))) => {
+                item_ids.insert(id);
+            }
+            (None, _) => {
+                problems.push(format!("{path} is not a valid item id"))
+            }
+            (_, Err(e)) => problems.push(e.to_string()),
+            _ => {}
+        }
+    }
+
+    let mut shop_ids = HashSet::new();
+
+    for path in paths(DataKind::Shops) {
+        match (id_of(&path, MAX_SHOPS), files.read::<ShopData>(&path)) {
+            (Some(id), Ok(Some(shop))) => {
+                shop_ids.insert(id);
+                problems.extend(check_shop(&path, &shop, &item_ids));
+            }
+            (None, _) => {
+                problems.push(format!("{path} is not a valid shop id"))
+            }
+            (_, Err(e)) => problems.push(e.to_string()),
+            _ => {}
+        }
+    }
+
+    for path in paths(DataKind::Npcs) {
+        match (id_of(&path, MAX_NPCS), files.read::<NpcData>(&path)) {
+            (Some(_), Ok(Some(npc))) => {
+                problems.extend(check_npc(&path, &npc, &item_ids));
+            }
+            (None, _) => problems.push(format!("{path} is not a valid npc id")),
+            (_, Err(e)) => problems.push(e.to_string()),
+            _ => {}
+        }
+    }
+
+    let maps = paths(DataKind::Maps);
+    let map_names: HashSet<&str> = maps
+        .iter()
+        .filter_map(|path| Path::new(path).file_stem()?.to_str())
+        .collect();
+
+    for path in maps.iter() {
+        match files.read::<MapData>(path) {
+            Ok(Some(map)) => problems.extend(check_map(
+                path, &map, &item_ids, &shop_ids, &map_names,
+            )),
+            Ok(None) => {}
+            Err(e) => problems.push(e.to_string()),
+        }
+    }
+
+    problems
+}
+
+/// The id in a path such as `items/12.bin`, if it is below `max`.
+fn id_of(path: &str, max: usize) -> Option<usize> {
+    let stem = Path::new(path).file_stem()?.to_str()?;
+    stem.parse().ok().filter(|id| *id < max)
+}
+
+pub fn check_shop(
+    path: &str,
+    shop: &ShopData,
+    item_ids: &HashSet<usize>,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if shop.max_item as usize > MAX_SHOP_ITEM {
+        problems.push(format!(
+            "{path}: max_item {} is above {MAX_SHOP_ITEM}",
+            shop.max_item
+        ));
+    }
+
+    for (slot, item) in shop
+        .item
+        .iter()
+        .take((shop.max_item as usize).min(MAX_SHOP_ITEM))
+        .enumerate()
+    {
+        if !item_ids.contains(&(item.index as usize)) {
+            problems.push(format!(
+                "{path}: slot {slot} sells item {} which does not exist",
+                item.index
+            ));
+        }
+    }
+
+    problems
+}
+
+pub fn check_npc(
+    path: &str,
+    npc: &NpcData,
+    item_ids: &HashSet<usize>,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    for (drop_index, drop) in npc.drops.iter().enumerate() {
+        for item in drop.items.iter().filter(|item| item.amount > 0) {
+            if !item_ids.contains(&(item.item as usize)) {
+                problems.push(format!(
+                    "{path}: drop {drop_index} gives item {} which does not exist",
+                    item.item
+                ));
+            }
+        }
+    }
+
+    problems
+}
+
+/// `map_names` holds the existing maps as `x_y_group`.
+pub fn check_map(
+    path: &str,
+    map: &MapData,
+    item_ids: &HashSet<usize>,
+    shop_ids: &HashSet<usize>,
+    map_names: &HashSet<&str>,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    // The client indexes these by tile without checking.
+    if map.tile.len() != 9 || map.tile.iter().any(|tile| tile.id.len() != 1024)
+    {
+        problems.push(format!("{path}: needs 9 layers of 1024 tiles"));
+    }
+    if map.dir_block.len() != 1024 || map.attribute.len() != 1024 {
+        problems.push(format!(
+            "{path}: needs 1024 dir_block and attribute entries"
+        ));
+    }
+
+    for (tile, attribute) in map.attribute.iter().enumerate() {
+        let at = format!("{path}: tile ({}, {})", tile % 32, tile / 32);
+
+        match attribute {
+            MapAttribute::Warp(warp) => {
+                let target =
+                    format!("{}_{}_{}", warp.map_x, warp.map_y, warp.map_group);
+
+                if !map_names.contains(target.as_str()) {
+                    problems
+                        .push(format!("{at} warps to missing map {target}"));
+                } else if warp.tile_x >= 32 || warp.tile_y >= 32 {
+                    problems.push(format!(
+                        "{at} warps outside map {target} to ({}, {})",
+                        warp.tile_x, warp.tile_y
+                    ));
+                }
+            }
+            MapAttribute::ItemSpawn(spawn)
+                if !item_ids.contains(&(spawn.index as usize)) =>
+            {
+                problems.push(format!(
+                    "{at} spawns item {} which does not exist",
+                    spawn.index
+                ));
+            }
+            MapAttribute::Shop(shop)
+                if !shop_ids.contains(&(*shop as usize)) =>
+            {
+                problems.push(format!("{at} opens missing shop {shop}"));
+            }
+            _ => {}
+        }
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DropItem, ShopItem, WarpData};
+
+    #[test]
+    fn references_to_missing_content_are_reported() {
+        let item_ids: HashSet<usize> = [1, 2].into();
+        let shop_ids: HashSet<usize> = [0].into();
+        let map_names: HashSet<&str> = ["0_0_0", "1_0_0"].into();
+
+        let mut shop = ShopData {
+            max_item: 2,
+            ..Default::default()
+        };
+        shop.item[0].index = 1;
+        shop.item[1] = ShopItem {
+            index: 7,
+            amount: 1,
+            price: 10,
+        };
+        // Past max_item, so not checked.
+        shop.item[2].index = 9;
+        assert_eq!(
+            check_shop("shops/0.bin", &shop, &item_ids),
+            vec!["shops/0.bin: slot 1 sells item 7 which does not exist"]
+        );
+
+        let mut npc = NpcData::default();
+        npc.drops[0].items[0].item = 2;
+        npc.drops[0].items[0].amount = 1;
+        npc.drops[3].items[1].item = 5;
+        npc.drops[3].items[1].amount = 1;
+        assert_eq!(check_npc("npcs/4.bin", &npc, &item_ids).len(), 1);
+
+        let mut map = MapData::new(0, 0, 0);
+        map.attribute[0] = MapAttribute::Warp(WarpData {
+            map_x: 1,
+            ..Default::default()
+        });
+        map.attribute[1] = MapAttribute::Warp(WarpData {
+            map_x: 2,
+            ..Default::default()
+        });
+        map.attribute[33] = MapAttribute::Shop(3);
+        assert_eq!(
+            check_map("maps/0_0_0.bin", &map, &item_ids, &shop_ids, &map_names),
+            vec![
+                "maps/0_0_0.bin: tile (1, 0) warps to missing map 2_0_0",
+                "maps/0_0_0.bin: tile (1, 1) opens missing shop 3",
+            ]
+        );
+    }
+
+    #[test]
+    fn args_pick_command_kinds_and_format() {
+        let args = |list: &[&str]| {
+            DataToolArgs::parse(list.iter().map(|arg| arg.to_string()))
+        };
+
+        let parsed =
+            args(&["export", "items", "maps", "--format=toml"]).unwrap();
+        assert_eq!(parsed.command, DataCommand::Export);
+        assert_eq!(parsed.kinds, vec![DataKind::Items, DataKind::Maps]);
+        assert_eq!(parsed.format, TextFormat::Toml);
+
+        let parsed = args(&["validate", "--data", "other"]).unwrap();
+        assert_eq!(parsed.kinds, DataKind::ALL.to_vec());
+        assert_eq!(parsed.data, PathBuf::from("other"));
+
+        assert!(args(&["export", "spells"]).is_err());
+        assert!(args(&[]).is_err());
+    }
+
+    #[test]
+    fn export_and_import_round_trip_in_both_formats() {
+        let root = std::env::temp_dir()
+            .join(format!("ascending_datatool_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let data = root.join("data");
+
+        let item = ItemData {
+            name: "Sword".into(),
+            animation: Some(3),
+            ..Default::default()
+        };
+        let mut npc = NpcData {
+            name: "Slime".into(),
+            enemies: vec![2],
+            ..Default::default()
+        };
+        npc.drops[1].items[0] = DropItem { item: 1, amount: 2 };
+        let mut shop = ShopData {
+            name: "General Store".into(),
+            max_item: 1,
+            ..Default::default()
+        };
+        shop.item[0].price = 25;
+        let mut map = MapData::new(0, 0, 0);
+        map.attribute[5] = MapAttribute::Warp(WarpData {
+            map_x: 1,
+            tile_y: 4,
+            ..Default::default()
+        });
+        map.attribute[6] = MapAttribute::Sign("Welcome".into());
+        map.zones[0] = (3, [Some(1), None, Some(4), None, None]);
+        map.music = Some("town.ogg".into());
+
+        let files = [
+            ("items/0.bin", item.write_to_vec().unwrap()),
+            ("npcs/0.bin", npc.write_to_vec().unwrap()),
+            ("shops/0.bin", shop.write_to_vec().unwrap()),
+            ("maps/0_0_0.bin", map.write_to_vec().unwrap()),
+        ];
+
+        for (path, bytes) in &files {
+            fs::create_dir_all(data.join(path).parent().unwrap()).unwrap();
+            fs::write(data.join(path), bytes).unwrap();
+        }
+
+        for format in [TextFormat::Json, TextFormat::Toml] {
+            let text = root.join(format.ext());
+            let back = root.join(format!("{}_data", format.ext()));
+
+            for kind in DataKind::ALL {
+                assert!(export(kind, format, &data, &text).is_empty());
+                assert!(import(kind, format, &text, &back).is_empty());
+            }
+
+            for (path, bytes) in &files {
+                assert_eq!(
+                    &fs::read(back.join(path)).unwrap(),
+                    bytes,
+                    "{path} through {format:?}"
+                );
+            }
+        }
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -1,11 +1,12 @@
-use crate::{data_types::*, socket::*, Result};
-use log::warn;
+use crate::{
+    ContentTable, DataFileError, DataFiles, MISSING_NAME, data_types::*,
+    socket::*,
+};
+use log::info;
 use serde::{Deserialize, Serialize};
 use speedy::{Endianness, Readable, Writable};
-use std::fs::{File, OpenOptions};
-use std::io::{BufReader, Read};
 
-#[derive(Clone, Debug, Deserialize, Serialize, Readable, Writable)]
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Readable, Writable)]
 pub struct ItemData {
     pub name: String,
     pub levelreq: u16,
@@ -24,31 +25,40 @@ pub struct ItemData {
     pub sound_index: Option<String>,
 }
 
-pub fn load_items() -> Result<Vec<ItemData>> {
-    let mut item_data: Vec<ItemData> = Vec::with_capacity(MAX_ITEMS);
-    let mut buffer = Vec::with_capacity(2048);
-
-    for i in 0..MAX_ITEMS {
-        if let Some(data) = load_file(i, &mut buffer)? {
-            item_data.push(data);
+impl ItemData {
+    /// Stands in for an id without a data file.
+    pub fn placeholder() -> Self {
+        ItemData {
+            name: MISSING_NAME.to_string(),
+            ..Default::default()
         }
     }
-
-    Ok(item_data)
 }
 
-fn load_file(id: usize, buffer: &mut Vec<u8>) -> Result<Option<ItemData>> {
-    let name = format!("./data/items/{}.bin", id);
-    buffer.clear();
+pub fn load_items(files: &mut DataFiles) -> ContentTable<ItemData> {
+    let mut item_data: Vec<Option<ItemData>> =
+        (0..MAX_ITEMS).map(|_| None).collect();
 
-    match OpenOptions::new().read(true).open(name) {
-        Ok(mut file) => {
-            file.read_to_end(buffer)?;
-            Ok(Some(ItemData::read_from_buffer(buffer).unwrap()))
-        }
-        Err(e) => {
-            warn!("Item Load File Num {} Err: {}", id, e);
-            Ok(None)
+    // Only ids with a file in the archive, the data folder or the
+    // manifest are opened.
+    for id in files.ids("items", MAX_ITEMS) {
+        match load_file(files, id) {
+            Ok(data) => item_data[id] = data,
+            Err(e) => files.reject(e),
         }
     }
+
+    info!(
+        "Loaded {} items",
+        item_data.iter().filter(|data| data.is_some()).count()
+    );
+
+    ContentTable::new(item_data, ItemData::placeholder())
+}
+
+pub fn load_file(
+    files: &DataFiles,
+    id: usize,
+) -> std::result::Result<Option<ItemData>, DataFileError> {
+    files.read(&format!("items/{}.bin", id))
 }
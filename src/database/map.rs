@@ -1,13 +1,11 @@
 use crate::{
-    BufferTask, BufferTaskEnum, MapAttributes, MapDirBlock, MapPosition,
-    Result, SystemHolder, data_types::*, socket::*,
+    BufferTask, BufferTaskEnum, DataFiles, MapAttributes, MapDirBlock,
+    MapPosition, Result, SystemHolder, data_types::*, socket::*,
 };
 use graphics::*;
 use log::{error, info};
 use serde::{Deserialize, Serialize};
 use speedy::{Endianness, Readable, Writable};
-use std::fs::{File, OpenOptions};
-use std::io::{BufReader, Read};
 use std::path::Path;
 
 #[derive(
@@ -147,6 +145,7 @@ pub struct MapData {
     pub dir_block: Vec<u8>,
     pub attribute: Vec<MapAttribute>,
     pub zonespawns: [Vec<(u16, u16)>; 5],
+    #[serde(with = "zone_text")]
     pub zones: [(u64, [Option<u64>; 5]); 5],
     pub music: Option<String>,
     pub weather: Weather,
@@ -168,15 +167,77 @@ impl MapData {
     }
 }
 
+/// Writes each zone as `{ max, npcs }` with only the filled npc slots, as
+/// `[slot, npc]` pairs, since TOML can not hold an empty value in an array.
+mod zone_text {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error};
+
+    type Zones = [(u64, [Option<u64>; 5]); 5];
+
+    #[derive(Serialize, Deserialize)]
+    struct ZoneText {
+        max: u64,
+        npcs: Vec<(usize, u64)>,
+    }
+
+    pub fn serialize<S: Serializer>(
+        zones: &Zones,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        zones
+            .iter()
+            .map(|(max, npcs)| ZoneText {
+                max: *max,
+                npcs: npcs
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(slot, npc)| Some((slot, (*npc)?)))
+                    .collect(),
+            })
+            .collect::<Vec<_>>()
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Zones, D::Error> {
+        let text = Vec::<ZoneText>::deserialize(deserializer)?;
+        let mut zones = Zones::default();
+
+        if text.len() > zones.len() {
+            return Err(D::Error::custom(format!(
+                "{} zones, a map has {}",
+                text.len(),
+                zones.len()
+            )));
+        }
+
+        for (zone, text) in zones.iter_mut().zip(text) {
+            zone.0 = text.max;
+
+            for (slot, npc) in text.npcs {
+                *zone.1.get_mut(slot).ok_or_else(|| {
+                    D::Error::custom(format!("zone npc slot {slot} is past 4"))
+                })? = Some(npc);
+            }
+        }
+
+        Ok(zones)
+    }
+}
+
 pub fn load_map_data(
     systems: &mut SystemHolder,
     key: Index,
     mappos: MapPosition,
 ) -> Result<()> {
     if let Some(mapslotdata) = systems.base.mapdata.get_mut(key) {
-        let mut buffer = Vec::with_capacity(131_072);
-        let mapdata =
-            load_file(mappos.x, mappos.y, mappos.group as u64, &mut buffer)?;
+        let mapdata = load_file(
+            &mut systems.base.data_files,
+            mappos.x,
+            mappos.y,
+            mappos.group as u64,
+        );
 
         (0..32).for_each(|x| {
             (0..32).for_each(|y| {
@@ -271,6 +332,38 @@ pub fn clear_map_data(systems: &mut SystemHolder) {
     systems.base.mappos_key.clear();
 }
 
+/// Drops the maps cached out of view and queues the `loaded` ones to be
+/// read again, after a content sync replaced map files under them.
+pub fn reload_map_data(
+    systems: &mut SystemHolder,
+    loaded: &[Index],
+    buffer: &mut BufferTask,
+) {
+    let cached: Vec<Index> = systems
+        .base
+        .mapdata
+        .keys()
+        .filter(|key| !loaded.contains(key))
+        .collect();
+
+    for key in cached {
+        if let Some(mapslotdata) = systems.base.mapdata.remove(key) {
+            systems
+                .gfx
+                .remove_gfx(&mut systems.renderer, &mapslotdata.map_index);
+            systems.base.mappos_key.remove(&mapslotdata.mappos);
+        }
+
+        systems.base.map_cache.pop(&key);
+    }
+
+    for key in loaded {
+        if let Some(mapslotdata) = systems.base.mapdata.get(*key) {
+            buffer.add_task(BufferTaskEnum::ApplyMap(mapslotdata.mappos, *key));
+        }
+    }
+}
+
 pub fn set_map_visible(systems: &mut SystemHolder, key: Index, visible: bool) {
     if let Some(mapslotdata) = systems.base.mapdata.get(key) {
         systems.gfx.set_visible(&mapslotdata.map_index, visible)
@@ -337,28 +430,15 @@ pub fn get_map_id(
     None
 }
 
-pub fn load_file(
-    x: i32,
-    y: i32,
-    group: u64,
-    buffer: &mut Vec<u8>,
-) -> Result<MapData> {
-    if !is_map_exist(x, y, group) {
-        return Ok(MapData::new(x, y, group));
-    }
-
-    buffer.clear();
-
-    let name = format!("./data/maps/{}_{}_{}.bin", x, y, group);
+pub fn load_file(files: &mut DataFiles, x: i32, y: i32, group: u64) -> MapData {
+    let path = format!("maps/{}_{}_{}.bin", x, y, group);
 
-    match OpenOptions::new().read(true).open(&name) {
-        Ok(mut file) => {
-            file.read_to_end(buffer)?;
-            Ok(MapData::read_from_buffer(buffer).unwrap())
-        }
+    match files.read(&path) {
+        Ok(Some(data)) => data,
+        Ok(None) => MapData::new(x, y, group),
         Err(e) => {
-            error!("Failed to load {}, Err {:?}", name, e);
-            Ok(MapData::new(x, y, group))
+            files.reject(e);
+            MapData::new(x, y, group)
         }
     }
 }
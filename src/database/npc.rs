@@ -1,23 +1,24 @@
-use crate::{data_types::*, socket::*, Result};
-use log::warn;
+use crate::{
+    ContentTable, DataFileError, DataFiles, MISSING_NAME, data_types::*,
+    socket::*,
+};
+use log::info;
 use serde::{Deserialize, Serialize};
 use speedy::{Endianness, Readable, Writable};
-use std::fs::{File, OpenOptions};
-use std::io::{BufReader, Read};
 
-#[derive(Clone, Debug, Deserialize, Serialize, Readable, Writable)]
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Readable, Writable)]
 pub struct DropItem {
     pub item: u32,
     pub amount: u32,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize, Readable, Writable)]
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Readable, Writable)]
 pub struct NpcDrop {
     pub items: [DropItem; 5],
     pub shares: u32,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize, Readable, Writable)]
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Readable, Writable)]
 pub struct NpcData {
     pub name: String,
     pub level: i32,
@@ -64,31 +65,38 @@ pub struct NpcData {
     pub exp: i64,
 }
 
-pub fn load_npcs() -> Result<Vec<NpcData>> {
-    let mut npc_data: Vec<NpcData> = Vec::with_capacity(MAX_NPCS);
-    let mut buffer = Vec::with_capacity(2048);
-    for i in 0..MAX_NPCS {
-        if let Some(data) = load_file(i, &mut buffer)? {
-            npc_data.push(data);
+impl NpcData {
+    /// Stands in for an id without a data file.
+    pub fn placeholder() -> Self {
+        NpcData {
+            name: MISSING_NAME.to_string(),
+            ..Default::default()
         }
     }
-
-    Ok(npc_data)
 }
 
-fn load_file(id: usize, buffer: &mut Vec<u8>) -> Result<Option<NpcData>> {
-    let name = format!("./data/npcs/{}.bin", id);
-
-    buffer.clear();
+pub fn load_npcs(files: &mut DataFiles) -> ContentTable<NpcData> {
+    let mut npc_data: Vec<Option<NpcData>> =
+        (0..MAX_NPCS).map(|_| None).collect();
 
-    match OpenOptions::new().read(true).open(name) {
-        Ok(mut file) => {
-            file.read_to_end(buffer)?;
-            Ok(Some(NpcData::read_from_buffer(buffer).unwrap()))
-        }
-        Err(e) => {
-            warn!("Npc Load File Num {} Err: {}", id, e);
-            Ok(None)
+    for id in files.ids("npcs", MAX_NPCS) {
+        match load_file(files, id) {
+            Ok(data) => npc_data[id] = data,
+            Err(e) => files.reject(e),
         }
     }
+
+    info!(
+        "Loaded {} npcs",
+        npc_data.iter().filter(|data| data.is_some()).count()
+    );
+
+    ContentTable::new(npc_data, NpcData::placeholder())
+}
+
+pub fn load_file(
+    files: &DataFiles,
+    id: usize,
+) -> std::result::Result<Option<NpcData>, DataFileError> {
+    files.read(&format!("npcs/{}.bin", id))
 }
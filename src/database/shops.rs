@@ -1,15 +1,17 @@
-use crate::{data_types::*, socket::*, Result};
+use crate::{
+    ContentTable, DataFileError, DataFiles, MISSING_NAME, data_types::*,
+    socket::*,
+};
 use educe::Educe;
-use log::warn;
+use log::info;
 use serde::{Deserialize, Serialize};
 use speedy::{Endianness, Readable, Writable};
-use std::fs::{File, OpenOptions};
-use std::io::{BufReader, Read};
 
 #[derive(
     Clone,
     Copy,
     Debug,
+    Default,
     Deserialize,
     Serialize,
     Readable,
@@ -26,6 +28,7 @@ pub struct ShopItem {
 #[derive(
     Clone,
     Debug,
+    Default,
     Deserialize,
     Serialize,
     Readable,
@@ -39,32 +42,38 @@ pub struct ShopData {
     pub item: [ShopItem; MAX_SHOP_ITEM],
 }
 
-pub fn load_shops() -> Result<Vec<ShopData>> {
-    let mut shop_data: Vec<ShopData> = Vec::with_capacity(MAX_SHOPS);
-    let mut buffer = Vec::with_capacity(2048);
-
-    for i in 0..MAX_SHOPS {
-        if let Some(data) = load_file(i, &mut buffer)? {
-            shop_data.push(data);
+impl ShopData {
+    /// Stands in for an id without a data file.
+    pub fn placeholder() -> Self {
+        ShopData {
+            name: MISSING_NAME.to_string(),
+            ..Default::default()
         }
     }
-
-    Ok(shop_data)
 }
 
-fn load_file(id: usize, buffer: &mut Vec<u8>) -> Result<Option<ShopData>> {
-    let name = format!("./data/shops/{}.bin", id);
+pub fn load_shops(files: &mut DataFiles) -> ContentTable<ShopData> {
+    let mut shop_data: Vec<Option<ShopData>> =
+        (0..MAX_SHOPS).map(|_| None).collect();
 
-    buffer.clear();
-
-    match OpenOptions::new().read(true).open(name) {
-        Ok(mut file) => {
-            file.read_to_end(buffer)?;
-            Ok(Some(ShopData::read_from_buffer(buffer).unwrap()))
-        }
-        Err(e) => {
-            warn!("Shop Load File Num {} Err: {}", id, e);
-            Ok(None)
+    for id in files.ids("shops", MAX_SHOPS) {
+        match load_file(files, id) {
+            Ok(data) => shop_data[id] = data,
+            Err(e) => files.reject(e),
         }
     }
+
+    info!(
+        "Loaded {} shops",
+        shop_data.iter().filter(|data| data.is_some()).count()
+    );
+
+    ContentTable::new(shop_data, ShopData::placeholder())
+}
+
+pub fn load_file(
+    files: &DataFiles,
+    id: usize,
+) -> std::result::Result<Option<ShopData>, DataFileError> {
+    files.read(&format!("shops/{}.bin", id))
 }
@@ -0,0 +1,63 @@
+/// Name shown for content the client has no data file for.
+pub const MISSING_NAME: &str = "???";
+
+/// Content loaded from numbered files, kept at its file number. A number
+/// without a usable file stays empty, so every later id keeps its place.
+#[derive(Clone, Debug)]
+pub struct ContentTable<T> {
+    entries: Vec<Option<T>>,
+    placeholder: T,
+}
+
+impl<T> ContentTable<T> {
+    pub fn new(entries: Vec<Option<T>>, placeholder: T) -> Self {
+        ContentTable {
+            entries,
+            placeholder,
+        }
+    }
+
+    pub fn get(&self, id: usize) -> Option<&T> {
+        self.entries.get(id)?.as_ref()
+    }
+
+    /// The entry, or the "???" placeholder when there is none.
+    pub fn get_or_placeholder(&self, id: usize) -> &T {
+        self.get(id).unwrap_or(&self.placeholder)
+    }
+
+    pub fn contains(&self, id: usize) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// The entries that were loaded, with their ids.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter_map(|(id, entry)| Some((id, entry.as_ref()?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_ids_keep_later_ids_in_place() {
+        let table = ContentTable::new(
+            vec![Some("zero"), None, Some("two")],
+            MISSING_NAME,
+        );
+
+        assert_eq!(table.get(2), Some(&"two"));
+        assert_eq!(table.get(1), None);
+        assert_eq!(*table.get_or_placeholder(1), MISSING_NAME);
+        assert_eq!(*table.get_or_placeholder(u16::MAX as usize), MISSING_NAME);
+        assert!(!table.contains(3));
+        assert_eq!(
+            table.iter().map(|(id, _)| id).collect::<Vec<_>>(),
+            vec![0, 2]
+        );
+    }
+}
@@ -51,11 +51,6 @@ use database::*;
 use systems::*;
 pub use widget::*;
 
-#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
-enum Action {
-    Quit,
-}
-
 #[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
 enum Axis {
     Forward,
@@ -75,9 +70,12 @@ static MY_LOGGER: MyLogger = MyLogger(log::Level::Trace);
 pub const APP_MAJOR: u16 = 1;
 pub const APP_MINOR: u16 = 1;
 pub const APP_REV: u16 = 1;
-pub const SERVER_ID: &str = "127.0.0.1";
-pub const SERVER_PORT: u16 = 7010;
-pub const TLS_SERVER_PORT: u16 = 7011;
+pub const DEFAULT_SERVER_HOST: &str = "127.0.0.1";
+pub const DEFAULT_SERVER_PORT: u16 = 7010;
+pub const DEFAULT_TLS_SERVER_PORT: u16 = 7011;
+pub const DEFAULT_CA_BUNDLE: &str = "keys/ca-crt.pem";
+pub const DEFAULT_DEVICE_CERT: &str = "keys/device-crt.pem";
+pub const DEFAULT_DEVICE_KEY: &str = "keys/device-key.pem";
 
 struct MyLogger(pub log::Level);
 
@@ -109,11 +107,21 @@ impl log::Log for MyLogger {
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    // `client --datatool <command>` runs the data file tool instead.
+    let mut args = env::args().skip(1).peekable();
+
+    if args.next_if_eq("--datatool").is_some() {
+        std::process::exit(database::datatool::run(args));
+    }
+
+    // Create logger to output to a File. It starts at Info so problems
+    // found while loading the config are logged.
+    log::set_logger(&MY_LOGGER).unwrap();
+    log::set_max_level(LevelFilter::Info);
+
     // Load config
     let config = Config::read_config("settings.toml");
 
-    // Create logger to output to a File
-    log::set_logger(&MY_LOGGER).unwrap();
     // Set the Max level we accept logging to the file for.
     log::set_max_level(config.level_filter.parse_enum());
 
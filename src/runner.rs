@@ -56,8 +56,8 @@ pub enum Runner {
         input_handler: InputHandler<Action, Axis>,
         frame_time: FrameTime,
         time: f32,
-        reconnect_time: f32,
-        reconnect_time2: f32,
+        reconnect_overlay: ReconnectOverlay,
+        content_sync_screen: ContentSyncScreen,
         reset_timer: f32,
         fps: u32,
         loop_timer: LoopTimer,
@@ -70,7 +70,8 @@ impl winit::application::ApplicationHandler for Runner {
     fn resumed(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
         if let Self::Loading = self {
             // Load config
-            let config = Config::read_config("settings.toml");
+            let mut config = Config::read_config("settings.toml");
+            config.launch = LaunchArgs::parse(env::args().skip(1));
 
             info!("loading initiation");
             let win_attrs = WindowAttributes::default()
@@ -187,12 +188,7 @@ impl winit::application::ApplicationHandler for Runner {
             });
 
             // get the Scale factor the pc currently is using for upscaling or downscaling the rendering.
-            let scale = renderer
-                .window()
-                .current_monitor()
-                .unwrap()
-                .scale_factor()
-                .clamp(1.0, 1.5);
+            let scale = ui_scale_factor(&renderer, config.ui_scale);
 
             // Load textures image
             let resource =
@@ -203,19 +199,23 @@ impl winit::application::ApplicationHandler for Runner {
             let volume = config.bgm_volume as f32 * 0.01;
             audio.set_music_volume(volume);
 
+            let mut data_files = DataFiles::new(DATA_ROOT);
             let database_holder = DatabaseHolder {
-                item: load_items().unwrap(),
-                shop: load_shops().unwrap(),
-                npc: load_npcs().unwrap(),
+                item: load_items(&mut data_files),
+                shop: load_shops(&mut data_files),
+                npc: load_npcs(&mut data_files),
                 mapdata: SlotMap::with_key(),
                 mappos_key: HashMap::default(),
                 map_cache: LruCache::new(NonZeroUsize::new(64).unwrap()),
+                data_files,
             };
 
             // Compile all rendering data in one type for quick access and passing
+            let gamepad = Gamepad::new(config.enable_gamepad);
+
             let mut systems = SystemHolder {
                 gfx: GfxCollection::new(),
-                renderer,
+                renderer: ClientRenderer::new(renderer),
                 size,
                 scale,
                 resource,
@@ -231,6 +231,9 @@ impl winit::application::ApplicationHandler for Runner {
                 },
                 try_once: true,
                 fps: GfxType::None,
+                settings: SettingsService::new(),
+                keybinds: Keybinds::load(KEYBINDS_FILE),
+                gamepad,
             };
 
             systems.fade.init_setup(
@@ -259,11 +262,37 @@ impl winit::application::ApplicationHandler for Runner {
             // Initiate Game Content
             let mut content = Content::new(&mut world, &mut systems).unwrap();
 
-            let alert = Alert::new();
+            let mut alert = Alert::new();
 
             let tooltip = Tooltip::new(&mut systems);
 
-            let socket = Poller::new().unwrap();
+            let reconnect_overlay = ReconnectOverlay::new(&mut systems);
+
+            let content_sync_screen = ContentSyncScreen::new(&mut systems);
+
+            let mut socket = Poller::new().unwrap();
+
+            // A replay never connects. Without a realm list we connect
+            // straight to the configured endpoint, otherwise the server
+            // select screen connects later.
+            if let Some(path) = &systems.config.launch.replay {
+                match Poller::replay(path) {
+                    Ok(replay) => socket = replay,
+                    Err(e) => error!("Failed to load session: {:?}", e),
+                }
+            } else if content.menu_content.realm_picked {
+                if let Err(e) = socket.connect(&systems.config) {
+                    error!("Failed to connect: {:?}", e);
+                    let host = systems.config.server_endpoint().host;
+                    alert_connect_error(&mut systems, &mut alert, &e, &host);
+                }
+            }
+
+            if let Some(path) = &systems.config.launch.record {
+                if let Err(e) = socket.start_recording(path) {
+                    error!("Failed to start recording: {:?}", e);
+                }
+            }
             let router = PacketRouter::init();
 
             // setup our system which includes Camera and projection as well as our controls.
@@ -325,9 +354,7 @@ impl winit::application::ApplicationHandler for Runner {
             };
 
             // Create the mouse/keyboard bindings for our stuff.
-            let mut bindings = Bindings::<Action, Axis>::new();
-            bindings
-                .insert_action(Action::Quit, vec![Key::Character('q').into()]);
+            let bindings = systems.keybinds.to_bindings();
 
             // set bindings and create our own input handler.
             // Increase the milli second to higher numbers if you need to support accessability for
@@ -353,8 +380,8 @@ impl winit::application::ApplicationHandler for Runner {
                 buffertask,
                 frame_time: FrameTime::new(),
                 time: 0.0f32,
-                reconnect_time: 0.0f32,
-                reconnect_time2: 0.0f32,
+                reconnect_overlay,
+                content_sync_screen,
                 reset_timer: 0.0f32,
                 fps: 0u32,
                 loop_timer: LoopTimer::default(),
@@ -383,8 +410,8 @@ impl winit::application::ApplicationHandler for Runner {
             buffertask,
             frame_time,
             time,
-            reconnect_time,
-            reconnect_time2,
+            reconnect_overlay,
+            content_sync_screen,
             reset_timer,
             fps,
             loop_timer,
@@ -538,6 +565,35 @@ impl winit::application::ApplicationHandler for Runner {
                 }
             }
 
+            handle_gamepad_input(
+                world, systems, socket, event_loop, content, alert, tooltip,
+            )
+            .unwrap();
+
+            // Damaged data files are found at startup and as maps load.
+            if let Some(msg) = systems.base.data_files.take_alert() {
+                alert.show_alert(
+                    systems,
+                    AlertType::Inform,
+                    msg,
+                    "Data Files Damaged".into(),
+                    250,
+                    AlertIndex::None,
+                    false,
+                );
+            }
+
+            // A new UI scale from the Setting window rebuilds the layout.
+            content.update_layout(world, systems).unwrap();
+
+            // Rebinding a key in the Setting window replaces the bindings.
+            if systems.keybinds.take_changed() {
+                *input_handler = InputHandler::new(
+                    systems.keybinds.to_bindings(),
+                    Duration::from_millis(180),
+                );
+            }
+
             // update our renderer based on events here
             if !systems.renderer.update(&event).unwrap() {
                 return;
@@ -560,6 +616,7 @@ impl winit::application::ApplicationHandler for Runner {
                 });
 
                 systems.renderer.update_depth_texture();
+                apply_present_mode(systems);
             }
 
             if let Some(gfx_index) = systems.caret.index {
@@ -624,65 +681,37 @@ impl winit::application::ApplicationHandler for Runner {
                 .queue()
                 .submit(std::iter::once(encoder.finish()));
 
-            if let Err(e) = socket.poll_events() {
-                error!("Poll event error: {:?}", e);
+            if let Err(e) = socket.flush_sends() {
+                error!("Flush sends error: {:?}", e);
             }
 
-            if socket.tls_socket.state == ClientState::Closed
-                && *reconnect_time < seconds
-            {
-                println!("Connecting TLS");
-                socket.reconnect(true).unwrap();
-
-                if content.content_type == ContentType::Game {
-                    send_tls_reconnect(socket, &systems.config.reconnect_code)
-                        .unwrap();
-                }
-
-                *reconnect_time = seconds + 1.0;
+            if let Err(e) = socket.poll_events() {
+                error!("Poll event error: {:?}", e);
             }
 
-            if socket.socket.state == ClientState::Closed
-                && *reconnect_time2 < seconds
-            {
-                println!("Connecting Non TLS");
-                socket.reconnect(false).unwrap();
+            update_reconnect(
+                socket,
+                systems,
+                content,
+                alert,
+                reconnect_overlay,
+                seconds,
+            );
 
-                if content.content_type == ContentType::Game {
-                    match content.game_content.reconnect_count {
-                        3 => {
-                            alert.show_alert(
-                                systems,
-                                AlertType::Inform,
-                                "You have been disconnected".into(),
-                                "Alert Message".into(),
-                                250,
-                                AlertIndex::Disconnect,
-                                false,
-                            );
-                        }
-                        i if (i < 3) => {
-                            send_reconnect(
-                                socket,
-                                &systems.config.reconnect_code,
-                            )
-                            .unwrap();
-                        }
-                        _ => {}
-                    }
+            update_content_sync(socket, systems, alert);
 
-                    content.game_content.reconnect_count = content
-                        .game_content
-                        .reconnect_count
-                        .saturating_add(1)
-                        .min(4);
-                }
+            // A sync on an in game reconnect runs under the game.
+            let sync_progress = match content.content_type {
+                ContentType::Menu => socket.content_sync.progress(),
+                ContentType::Game => None,
+            };
+            content_sync_screen.update(systems, sync_progress);
 
-                *reconnect_time2 = seconds + 1.0;
-            }
+            flush_settings(systems, false);
 
             if *reset_timer < seconds
                 && content.content_type == ContentType::Menu
+                && socket.is_connected()
             {
                 *reset_timer = seconds + 3.0;
                 send_ping(socket).unwrap();
@@ -713,6 +742,12 @@ impl winit::application::ApplicationHandler for Runner {
                 );
                 *fps = 0u32;
                 *time = seconds + 1.0;
+
+                content.net_stats.update(
+                    systems,
+                    socket,
+                    &content.game_content.prediction,
+                );
             }
 
             systems.audio.update_effects();
@@ -800,8 +835,8 @@ impl winit::application::ApplicationHandler for Runner {
             socket: _,
             frame_time: _,
             time: _,
-            reconnect_time: _,
-            reconnect_time2: _,
+            reconnect_overlay: _,
+            content_sync_screen: _,
             reset_timer: _,
             fps: _,
             loop_timer: _,
@@ -827,8 +862,8 @@ impl winit::application::ApplicationHandler for Runner {
             socket: _,
             frame_time: _,
             time: _,
-            reconnect_time: _,
-            reconnect_time2: _,
+            reconnect_overlay: _,
+            content_sync_screen: _,
             reset_timer: _,
             fps: _,
             loop_timer: _,
@@ -839,4 +874,24 @@ impl winit::application::ApplicationHandler for Runner {
             systems.renderer.window().request_redraw();
         }
     }
+
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        if let Self::Ready {
+            systems, socket, ..
+        } = self
+        {
+            flush_settings(systems, true);
+
+            if let Some(path) = &systems.config.net_stats_csv {
+                if socket.metrics.is_empty() {
+                    return;
+                }
+
+                match socket.metrics.export_csv(path) {
+                    Ok(_) => info!("Wrote network stats to {}", path),
+                    Err(e) => error!("Failed to write network stats: {:?}", e),
+                }
+            }
+        }
+    }
 }
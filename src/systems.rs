@@ -1,15 +1,25 @@
 pub mod audio;
 pub mod config;
+pub mod gamepad;
+pub mod keybinds;
 pub mod logic;
 pub mod mainloop;
 pub mod renderer;
+pub mod secrets;
+pub mod server_list;
+pub mod settings;
 pub mod socket;
 pub mod system_tasks;
 
 pub use audio::*;
 pub use config::*;
+pub use gamepad::*;
+pub use keybinds::*;
 pub use logic::*;
 pub use mainloop::*;
 pub use renderer::*;
+pub use secrets::*;
+pub use server_list::*;
+pub use settings::*;
 pub use socket::*;
 pub use system_tasks::*;
@@ -1,16 +1,13 @@
 use graphics::wgpu::{Backend, Backends};
-use log::{debug, LevelFilter};
-use rustls::{
-    client::danger,
-    crypto::{ring as provider, CryptoProvider},
-    pki_types::{CertificateDer, PrivateKeyDer},
-    server::WebPkiClientVerifier,
-    ClientConfig, RootCertStore, ServerConfig,
-};
+use log::{LevelFilter, debug, error, info, warn};
 use serde::{Deserialize, Serialize};
-use std::{fs, io::BufReader, sync::Arc};
+use std::fs;
 
-use crate::{renderer::*, Result};
+use crate::{
+    DEFAULT_DEVICE_CERT, DEFAULT_DEVICE_KEY, DEFAULT_SERVER_HOST,
+    DEFAULT_SERVER_PORT, DEFAULT_TLS_SERVER_PORT, Result, UI_SCALE_MAX,
+    UI_SCALE_MIN, renderer::*, socket::is_valid_pin, write_atomic,
+};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum ClientLevelFilter {
@@ -41,57 +38,468 @@ impl ClientLevelFilter {
     }
 }
 
+/// Server connection values given on the command line. These take priority
+/// over settings.toml until a realm is picked from the server list, and are
+/// never saved back into it.
+#[derive(Debug, Default, Clone)]
+pub struct LaunchArgs {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub tls_port: Option<u16>,
+    pub server_name: Option<String>,
+    /// Session file to write inbound frames to.
+    pub record: Option<String>,
+    /// Session file to play back instead of connecting.
+    pub replay: Option<String>,
+}
+
+impl LaunchArgs {
+    /// Parses `--host`, `--port`, `--tls-port`, `--server-name`, `--record`
+    /// and `--replay`.
+    /// Accepts both `--key value` and `--key=value`.
+    pub fn parse<I>(args: I) -> Self
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut launch = LaunchArgs::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let (key, value) = match arg.split_once('=') {
+                Some((key, value)) => {
+                    (key.to_string(), Some(value.to_string()))
+                }
+                None => (arg, None),
+            };
+
+            let mut take_value = || value.clone().or_else(|| args.next());
+
+            match key.as_str() {
+                "--host" => launch.host = take_value(),
+                "--port" => launch.port = parse_port(&key, take_value()),
+                "--tls-port" => {
+                    launch.tls_port = parse_port(&key, take_value())
+                }
+                "--server-name" => launch.server_name = take_value(),
+                "--record" => launch.record = take_value(),
+                "--replay" => launch.replay = take_value(),
+                _ => warn!("Unknown command line argument: {}", key),
+            }
+        }
+
+        launch
+    }
+
+    /// Drops the endpoint overrides, so a realm picked in the menu is the
+    /// one connected to. The record and replay files are kept.
+    pub fn clear_endpoint(&mut self) {
+        self.host = None;
+        self.port = None;
+        self.tls_port = None;
+        self.server_name = None;
+    }
+}
+
+fn parse_port(key: &str, value: Option<String>) -> Option<u16> {
+    let value = value?;
+
+    match value.parse::<u16>() {
+        Ok(port) => Some(port),
+        Err(_) => {
+            warn!("Invalid port {} for {}, using default", value, key);
+            None
+        }
+    }
+}
+
+/// The resolved address the Poller connects to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerEndpoint {
+    pub host: String,
+    pub port: u16,
+    pub tls_port: u16,
+    /// Name used for TLS SNI and certificate checks.
+    pub server_name: String,
+    pub trust: TrustSettings,
+}
+
+/// PEM files of the certificate the client presents on the TLS channel.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ClientIdentity {
+    pub cert: String,
+    pub key: String,
+    /// Create a self signed device certificate if the files are missing.
+    pub generate: bool,
+}
+
+/// Which server certificates the TLS channel accepts and which client
+/// certificate it presents.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TrustSettings {
+    /// PEM file with the CAs the server certificate must chain to, as set
+    /// in settings.toml. None reads DEFAULT_CA_BUNDLE and falls back to the
+    /// public webpki roots when that can not be read.
+    pub ca_bundle: Option<String>,
+    /// Hex SHA-256 hashes of the accepted server SubjectPublicKeyInfo.
+    /// Empty disables pinning.
+    pub pinned_spki: Vec<String>,
+    /// None connects without client authentication.
+    pub identity: Option<ClientIdentity>,
+}
+
+/// Bumped whenever a migration is added to MIGRATIONS.
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Each entry upgrades the raw settings table from the version equal to its
+/// index to the next one.
+const MIGRATIONS: [fn(&mut toml::Table); CONFIG_VERSION as usize] =
+    [migrate_v0_to_v1];
+
+/// Files from before versioning: a plain `password`, `save_password` and
+/// `graphic_backend` as one `|` separated string.
+fn migrate_v0_to_v1(table: &mut toml::Table) {
+    if table.remove("password").is_some() {
+        warn!("Removing the plain text password from settings.toml");
+    }
+
+    if let Some(remember) = table.remove("save_password") {
+        table.entry("remember_login").or_insert(remember);
+    }
+
+    if let Some(toml::Value::String(backends)) = table.get("graphic_backend") {
+        let list = backends
+            .split('|')
+            .filter(|name| !name.is_empty())
+            .map(|name| toml::Value::String(name.to_string()))
+            .collect();
+
+        table.insert("graphic_backend".into(), toml::Value::Array(list));
+    }
+}
+
+/// Upgrades the table to CONFIG_VERSION. Returns true when anything ran.
+fn migrate(table: &mut toml::Table) -> bool {
+    let version = table
+        .get("version")
+        .and_then(toml::Value::as_integer)
+        .unwrap_or(0);
+
+    if version > CONFIG_VERSION as i64 {
+        warn!(
+            "settings.toml version {} is newer than this client ({}), \
+             loading what it can",
+            version, CONFIG_VERSION
+        );
+        return false;
+    }
+
+    let from = version.max(0) as usize;
+
+    for (step, migration) in MIGRATIONS.iter().enumerate().skip(from) {
+        info!("Migrating settings.toml from version {}", step);
+        migration(table);
+    }
+
+    table.insert(
+        "version".into(),
+        toml::Value::Integer(CONFIG_VERSION as i64),
+    );
+    from < MIGRATIONS.len()
+}
+
+/// Deserializes the table, replacing any field that fails to parse with its
+/// default. Returns true when a field was reset.
+fn deserialize_lenient(mut table: toml::Table) -> (Config, bool) {
+    if let Ok(config) = Config::deserialize(toml::Value::Table(table.clone())) {
+        return (config, false);
+    }
+
+    let bad: Vec<String> = table
+        .iter()
+        .filter(|(key, value)| {
+            let single =
+                toml::Table::from_iter([((*key).clone(), (*value).clone())]);
+            Config::deserialize(toml::Value::Table(single)).is_err()
+        })
+        .map(|(key, _)| key.clone())
+        .collect();
+
+    for key in &bad {
+        warn!(
+            "Invalid {} = {} in settings.toml, using the default",
+            key, table[key]
+        );
+        table.remove(key);
+    }
+
+    match Config::deserialize(toml::Value::Table(table)) {
+        Ok(config) => (config, true),
+        Err(e) => {
+            error!("Could not read settings.toml, using the defaults: {}", e);
+            (Config::default(), true)
+        }
+    }
+}
+
+fn reset_invalid(name: &str, value: &dyn std::fmt::Debug) {
+    warn!(
+        "Invalid {} = {:?} in settings.toml, using the default",
+        name, value
+    );
+}
+
 #[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
 pub struct Config {
+    /// Schema version, see CONFIG_VERSION. Missing means a file from
+    /// before versioning.
+    #[serde(default)]
+    pub version: u32,
+    pub realm: Option<String>,
     pub host: Option<String>,
     pub port: Option<u16>,
+    pub tls_port: Option<u16>,
+    pub server_name: Option<String>,
+    /// CA bundle for the TLS channel, keys/ca-crt.pem when unset.
+    pub ca_bundle: Option<String>,
+    pub pinned_spki: Vec<String>,
+    /// Client certificate and key for accounts the server requires mutual
+    /// TLS for, such as admin and monitor accounts.
+    pub client_cert: Option<String>,
+    pub client_key: Option<String>,
+    /// Presents a self signed certificate created on first run when no
+    /// client_cert is set.
+    pub device_cert: bool,
     pub username: String,
-    pub password: String,
-    pub save_password: bool,
+    /// Keep the server issued login token for the next start.
+    pub remember_login: bool,
+    /// Opaque token the server issued for "remember me". It can be
+    /// revoked by the server and never reveals the password.
+    #[serde(with = "crate::sealed")]
+    pub login_token: String,
     pub bgm_volume: u8,
     pub sfx_volume: u8,
+    #[serde(with = "crate::sealed")]
     pub reconnect_code: String,
     pub level_filter: ClientLevelFilter,
     pub enable_backtrace: bool,
-    pub graphic_backend: String,
+    /// Backends the renderer may choose from.
+    pub graphic_backend: Vec<ClientBackend>,
     pub show_fps: bool,
+    /// Writes per packet traffic counters to this CSV file on exit.
+    pub net_stats_csv: Option<String>,
     pub show_ping: bool,
     pub show_average_ping: bool,
     pub show_frame_loop: bool,
     pub power_settings: ClientAdapterPowerSettings,
     pub present_mode: ClientPresentMode,
+    /// Multiplies the monitor scale factor for the interface.
+    pub ui_scale: f32,
+    /// Reads controllers through gilrs. Off skips probing for pads.
+    pub enable_gamepad: bool,
     pub gpu_instance: ClientGPUInstances,
+    #[serde(skip)]
+    pub launch: LaunchArgs,
 }
 
 impl Config {
     pub fn read_config(path: &str) -> Self {
-        match fs::read_to_string(path) {
-            Ok(data) => toml::from_str(&data).unwrap(),
+        let data = match fs::read_to_string(path) {
+            Ok(data) => data,
             Err(_) => {
                 let config = Config::default();
                 config.save_config(path);
-                config
+                return config;
             }
+        };
+
+        let (config, changed) = match Config::from_toml(&data) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                error!("{} is not valid TOML, using the defaults: {}", path, e);
+
+                let backup = format!("{path}.bak");
+                match fs::copy(path, &backup) {
+                    Ok(_) => {
+                        warn!("The unreadable file was kept as {}", backup)
+                    }
+                    Err(e) => error!("Failed to back up {}: {}", path, e),
+                }
+
+                (Config::default(), true)
+            }
+        };
+
+        if changed {
+            config.save_config(path);
         }
+
+        config
     }
 
+    /// Parses settings of any known version. The flag is true when the file
+    /// was migrated or had values reset and should be saved again.
+    pub fn from_toml(data: &str) -> Result<(Config, bool)> {
+        let mut table: toml::Table = toml::from_str(data)?;
+        let migrated = migrate(&mut table);
+        let (mut config, reset) = deserialize_lenient(table);
+        let invalid = config.validate();
+
+        Ok((config, migrated || reset || invalid))
+    }
+
+    /// Resets values that parsed but make no sense. Returns true when
+    /// anything was reset.
+    fn validate(&mut self) -> bool {
+        let defaults = Config::default();
+        let mut reset = false;
+
+        if self.bgm_volume > 100 {
+            reset_invalid("bgm_volume", &self.bgm_volume);
+            self.bgm_volume = defaults.bgm_volume;
+            reset = true;
+        }
+
+        if self.sfx_volume > 100 {
+            reset_invalid("sfx_volume", &self.sfx_volume);
+            self.sfx_volume = defaults.sfx_volume;
+            reset = true;
+        }
+
+        for (name, port) in
+            [("port", &mut self.port), ("tls_port", &mut self.tls_port)]
+        {
+            if *port == Some(0) {
+                reset_invalid(name, port);
+                *port = None;
+                reset = true;
+            }
+        }
+
+        for (name, value) in [
+            ("host", &mut self.host),
+            ("server_name", &mut self.server_name),
+            ("ca_bundle", &mut self.ca_bundle),
+            ("net_stats_csv", &mut self.net_stats_csv),
+        ] {
+            if value.as_ref().is_some_and(|value| value.trim().is_empty()) {
+                reset_invalid(name, value);
+                *value = None;
+                reset = true;
+            }
+        }
+
+        if self.graphic_backend.is_empty() {
+            reset_invalid("graphic_backend", &self.graphic_backend);
+            self.graphic_backend = defaults.graphic_backend;
+            reset = true;
+        }
+
+        if !(UI_SCALE_MIN..=UI_SCALE_MAX).contains(&self.ui_scale) {
+            reset_invalid("ui_scale", &self.ui_scale);
+            self.ui_scale = defaults.ui_scale;
+            reset = true;
+        }
+
+        let pins = self.pinned_spki.len();
+        self.pinned_spki.retain(|pin| {
+            let valid = is_valid_pin(pin);
+
+            if !valid {
+                reset_invalid("pinned_spki entry", pin);
+            }
+
+            valid
+        });
+        reset |= pins != self.pinned_spki.len();
+
+        reset
+    }
+
+    /// Writes the settings atomically and readable only by the current
+    /// user, as they hold the sealed login token.
     pub fn save_config(&self, path: &str) {
-        let toml_data = toml::to_string(self).unwrap();
-        fs::write(path, toml_data).unwrap();
+        let toml_data = match toml::to_string(self) {
+            Ok(data) => data,
+            Err(e) => {
+                error!("Failed to serialize {}: {}", path, e);
+                return;
+            }
+        };
+
+        if let Err(e) = write_atomic(path, toml_data.as_bytes()) {
+            error!("Failed to save {}: {:?}", path, e);
+        }
     }
 
-    pub fn append_graphic_backend(&self) -> Backends {
-        let text: Vec<&str> = self.graphic_backend.split('|').collect();
-        let mut backends = Backends::empty();
-        for data in text.iter() {
-            match *data {
-                "OpenGL" => backends |= Backends::GL,
-                "DX12" => backends |= Backends::DX12,
-                "Vulkan" => backends |= Backends::VULKAN,
-                "Metal" => backends |= Backends::METAL,
-                _ => {}
+    /// Resolves the server endpoint. Command line values win over
+    /// settings.toml, which wins over the built in defaults.
+    pub fn server_endpoint(&self) -> ServerEndpoint {
+        let host = self
+            .launch
+            .host
+            .clone()
+            .or_else(|| self.host.clone())
+            .unwrap_or_else(|| DEFAULT_SERVER_HOST.to_string());
+        let server_name = self
+            .launch
+            .server_name
+            .clone()
+            .or_else(|| self.server_name.clone())
+            .unwrap_or_else(|| host.clone());
+
+        ServerEndpoint {
+            port: self
+                .launch
+                .port
+                .or(self.port)
+                .unwrap_or(DEFAULT_SERVER_PORT),
+            tls_port: self
+                .launch
+                .tls_port
+                .or(self.tls_port)
+                .unwrap_or(DEFAULT_TLS_SERVER_PORT),
+            host,
+            server_name,
+            trust: self.trust_settings(),
+        }
+    }
+
+    pub fn trust_settings(&self) -> TrustSettings {
+        TrustSettings {
+            ca_bundle: self.ca_bundle.clone(),
+            pinned_spki: self.pinned_spki.clone(),
+            identity: self.client_identity(),
+        }
+    }
+
+    fn client_identity(&self) -> Option<ClientIdentity> {
+        match (&self.client_cert, &self.client_key) {
+            (Some(cert), Some(key)) => Some(ClientIdentity {
+                cert: cert.clone(),
+                key: key.clone(),
+                generate: false,
+            }),
+            (None, None) if self.device_cert => Some(ClientIdentity {
+                cert: DEFAULT_DEVICE_CERT.to_string(),
+                key: DEFAULT_DEVICE_KEY.to_string(),
+                generate: true,
+            }),
+            (None, None) => None,
+            _ => {
+                warn!("client_cert and client_key must be set together");
+                None
             }
         }
+    }
+
+    pub fn append_graphic_backend(&self) -> Backends {
+        let backends = self
+            .graphic_backend
+            .iter()
+            .fold(Backends::empty(), |backends, backend| {
+                backends | backend.to_backends()
+            });
         debug!("Backends: {:?}", backends);
         backends
     }
@@ -100,76 +508,125 @@ impl Config {
 impl Default for Config {
     fn default() -> Self {
         Config {
+            version: CONFIG_VERSION,
+            realm: None,
             host: None,
             port: None,
+            tls_port: None,
+            server_name: None,
+            ca_bundle: None,
+            pinned_spki: Vec::new(),
+            client_cert: None,
+            client_key: None,
+            device_cert: false,
             username: String::new(),
-            password: String::new(),
-            save_password: false,
+            remember_login: false,
+            login_token: String::new(),
             bgm_volume: 70,
             sfx_volume: 70,
             reconnect_code: String::new(),
             level_filter: ClientLevelFilter::Info,
             enable_backtrace: false,
-            graphic_backend: "OpenGL|DX12|Vulkan|Metal".to_string(),
+            graphic_backend: ClientBackend::ALL.to_vec(),
             show_fps: false,
+            net_stats_csv: None,
             show_ping: false,
             show_average_ping: false,
             show_frame_loop: false,
             power_settings: ClientAdapterPowerSettings::HighPower,
             present_mode: ClientPresentMode::AutoVsync,
+            ui_scale: 1.0,
+            enable_gamepad: true,
             gpu_instance: ClientGPUInstances::None,
+            launch: LaunchArgs::default(),
         }
     }
 }
 
-fn load_certs(filename: &str) -> Vec<CertificateDer<'static>> {
-    let certfile =
-        fs::File::open(filename).expect("cannot open certificate file");
-    let mut reader = BufReader::new(certfile);
-    rustls_pemfile::certs(&mut reader)
-        .map(|result| result.unwrap())
-        .collect()
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-fn load_private_key(filename: &str) -> PrivateKeyDer<'static> {
-    let keyfile =
-        fs::File::open(filename).expect("cannot open private key file");
-    let mut reader = BufReader::new(keyfile);
+    #[test]
+    fn unversioned_file_is_migrated_and_bad_values_reset() {
+        let data = r#"
+            username = "bob"
+            password = "hunter2"
+            save_password = true
+            bgm_volume = 150
+            sfx_volume = "loud"
+            level_filter = "Chatty"
+            graphic_backend = "DX12|Vulkan"
+            pinned_spki = ["not-a-hash", "AB:cd"]
+            some_old_setting = 4
+        "#;
 
-    loop {
-        match rustls_pemfile::read_one(&mut reader)
-            .expect("cannot parse private key .pem file")
-        {
-            Some(rustls_pemfile::Item::Pkcs1Key(key)) => return key.into(),
-            Some(rustls_pemfile::Item::Pkcs8Key(key)) => return key.into(),
-            Some(rustls_pemfile::Item::Sec1Key(key)) => return key.into(),
-            None => break,
-            _ => {}
-        }
+        let (config, changed) = Config::from_toml(data).unwrap();
+
+        assert!(changed);
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.username, "bob");
+        assert!(config.remember_login);
+        assert_eq!(config.bgm_volume, 70);
+        assert_eq!(config.sfx_volume, 70);
+        assert!(matches!(config.level_filter, ClientLevelFilter::Info));
+        assert_eq!(
+            config.graphic_backend,
+            vec![ClientBackend::DX12, ClientBackend::Vulkan]
+        );
+        assert!(config.pinned_spki.is_empty());
+
+        let saved = toml::to_string(&config).unwrap();
+        assert!(!saved.contains("hunter2"));
+        assert!(!saved.contains("save_password"));
     }
 
-    panic!(
-        "no keys found in {:?} (encrypted keys not supported)",
-        filename
-    );
-}
+    #[test]
+    fn current_file_round_trips_unchanged() {
+        let data = toml::to_string(&Config::default()).unwrap();
+        let (config, changed) = Config::from_toml(&data).unwrap();
 
-pub fn build_tls_config() -> Result<Arc<rustls::ClientConfig>> {
-    let mut root_store = RootCertStore::empty();
-    let ca_cert = load_certs("keys/ca-crt.pem");
-    root_store.add_parsable_certificates(ca_cert);
+        assert!(!changed);
+        assert_eq!(config.graphic_backend, ClientBackend::ALL.to_vec());
+    }
 
-    let config = ClientConfig::builder_with_provider(
-        CryptoProvider {
-            cipher_suites: provider::DEFAULT_CIPHER_SUITES.to_vec(),
-            ..provider::default_provider()
-        }
-        .into(),
-    )
-    .with_protocol_versions(rustls::DEFAULT_VERSIONS)
-    .expect("inconsistent cipher-suite/versions selected")
-    .with_root_certificates(root_store)
-    .with_no_client_auth();
+    #[test]
+    fn missing_fields_use_defaults() {
+        let (config, changed) =
+            Config::from_toml("version = 1\nshow_fps = true").unwrap();
+
+        assert!(!changed);
+        assert!(config.show_fps);
+        assert_eq!(config.sfx_volume, 70);
+        assert!(Config::from_toml("show_fps = [").is_err());
+    }
 
-    Ok(Arc::new(config))
+    #[test]
+    fn picked_realm_beats_command_line_endpoint() {
+        let mut config = Config {
+            launch: LaunchArgs::parse(
+                ["--host=override", "--port", "1", "--record", "a.session"]
+                    .map(String::from),
+            ),
+            ..Config::default()
+        };
+        assert_eq!(config.server_endpoint().host, "override");
+
+        let realm = crate::RealmEntry {
+            name: "Main".into(),
+            host: "main.example".into(),
+            port: 7010,
+            tls_port: 7011,
+            server_name: None,
+        };
+        realm.apply_to(&mut config);
+
+        let endpoint = config.server_endpoint();
+        assert_eq!(
+            (endpoint.host.as_str(), endpoint.port),
+            ("main.example", 7010)
+        );
+        assert_eq!(endpoint.server_name, "main.example");
+        assert_eq!(config.launch.record.as_deref(), Some("a.session"));
+    }
 }
@@ -0,0 +1,526 @@
+use log::{info, warn};
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+use crate::{Action, Direction};
+
+/// Stick values below this are treated as centered.
+pub const STICK_DEADZONE: f32 = 0.5;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PadButton {
+    South,
+    East,
+    North,
+    West,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+    LeftShoulder,
+    RightShoulder,
+    Start,
+    Select,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PadAxis {
+    LeftX,
+    LeftY,
+    /// Pads that report the d-pad as an axis instead of buttons.
+    DPadX,
+    DPadY,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PadEvent {
+    Button(PadButton, bool),
+    /// Positive values point right and up.
+    Axis(PadAxis, f32),
+    Disconnected,
+}
+
+/// What the game should do with the pad input.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PadCommand {
+    /// Same as pressing or releasing the key bound to the action.
+    Action(Action, bool),
+    FocusMode(bool),
+    Navigate(Direction),
+    /// Clicks the focused element.
+    Confirm,
+    /// Double clicks the focused element, such as using an item.
+    Use,
+    NextWindow,
+    PrevWindow,
+}
+
+/// Where pad events come from.
+pub trait PadSource {
+    fn next_event(&mut self) -> Option<PadEvent>;
+}
+
+/// Test side of a virtual pad. Queues events as if a controller sent them.
+#[derive(Clone, Default)]
+pub struct VirtualPadHandle(pub Rc<RefCell<VecDeque<PadEvent>>>);
+
+impl VirtualPadHandle {
+    pub fn push(&self, event: PadEvent) {
+        self.0.borrow_mut().push_back(event);
+    }
+
+    pub fn press(&self, button: PadButton) {
+        self.push(PadEvent::Button(button, true));
+    }
+
+    pub fn release(&self, button: PadButton) {
+        self.push(PadEvent::Button(button, false));
+    }
+
+    pub fn stick(&self, x: f32, y: f32) {
+        self.push(PadEvent::Axis(PadAxis::LeftX, x));
+        self.push(PadEvent::Axis(PadAxis::LeftY, y));
+    }
+}
+
+/// In memory pad source, so input handling can run without a controller.
+pub struct VirtualPad(VirtualPadHandle);
+
+impl VirtualPad {
+    pub fn new() -> (Self, VirtualPadHandle) {
+        let handle = VirtualPadHandle::default();
+        (VirtualPad(handle.clone()), handle)
+    }
+}
+
+impl PadSource for VirtualPad {
+    fn next_event(&mut self) -> Option<PadEvent> {
+        self.0.0.borrow_mut().pop_front()
+    }
+}
+
+/// Controllers found by gilrs.
+pub struct GilrsSource(gilrs::Gilrs);
+
+impl GilrsSource {
+    pub fn new() -> Option<Self> {
+        match gilrs::Gilrs::new() {
+            Ok(gilrs) => {
+                for (_, pad) in gilrs.gamepads() {
+                    info!("Gamepad found: {}", pad.name());
+                }
+
+                Some(GilrsSource(gilrs))
+            }
+            Err(e) => {
+                warn!("Gamepad support is unavailable: {}", e);
+                None
+            }
+        }
+    }
+
+    fn map_button(button: gilrs::Button) -> Option<PadButton> {
+        use gilrs::Button;
+
+        Some(match button {
+            Button::South => PadButton::South,
+            Button::East => PadButton::East,
+            Button::North => PadButton::North,
+            Button::West => PadButton::West,
+            Button::DPadUp => PadButton::DPadUp,
+            Button::DPadDown => PadButton::DPadDown,
+            Button::DPadLeft => PadButton::DPadLeft,
+            Button::DPadRight => PadButton::DPadRight,
+            Button::LeftTrigger => PadButton::LeftShoulder,
+            Button::RightTrigger => PadButton::RightShoulder,
+            Button::Start => PadButton::Start,
+            Button::Select => PadButton::Select,
+            _ => return None,
+        })
+    }
+
+    fn map_axis(axis: gilrs::Axis) -> Option<PadAxis> {
+        use gilrs::Axis;
+
+        Some(match axis {
+            Axis::LeftStickX => PadAxis::LeftX,
+            Axis::LeftStickY => PadAxis::LeftY,
+            Axis::DPadX => PadAxis::DPadX,
+            Axis::DPadY => PadAxis::DPadY,
+            _ => return None,
+        })
+    }
+}
+
+impl PadSource for GilrsSource {
+    fn next_event(&mut self) -> Option<PadEvent> {
+        use gilrs::EventType;
+
+        // Skip events we do not map instead of ending the poll on them.
+        while let Some(event) = self.0.next_event() {
+            let mapped = match event.event {
+                EventType::ButtonPressed(button, _) => Self::map_button(button)
+                    .map(|button| PadEvent::Button(button, true)),
+                EventType::ButtonReleased(button, _) => {
+                    Self::map_button(button)
+                        .map(|button| PadEvent::Button(button, false))
+                }
+                EventType::AxisChanged(axis, value, _) => {
+                    Self::map_axis(axis).map(|axis| PadEvent::Axis(axis, value))
+                }
+                EventType::Disconnected => Some(PadEvent::Disconnected),
+                _ => None,
+            };
+
+            if mapped.is_some() {
+                return mapped;
+            }
+        }
+
+        None
+    }
+}
+
+/// The direction a stick points to, None inside the deadzone.
+pub fn stick_direction(x: f32, y: f32) -> Option<Direction> {
+    if x.abs().max(y.abs()) < STICK_DEADZONE {
+        None
+    } else if x.abs() > y.abs() {
+        Some(if x > 0.0 {
+            Direction::Right
+        } else {
+            Direction::Left
+        })
+    } else {
+        Some(if y > 0.0 {
+            Direction::Up
+        } else {
+            Direction::Down
+        })
+    }
+}
+
+fn move_action(dir: Direction) -> Action {
+    match dir {
+        Direction::Up => Action::MoveUp,
+        Direction::Down => Action::MoveDown,
+        Direction::Left => Action::MoveLeft,
+        Direction::Right => Action::MoveRight,
+    }
+}
+
+/// Turns pad events into commands. Movement follows the d-pad while it is
+/// held and the left stick otherwise. In focus mode the same input moves
+/// the window focus instead of the player.
+#[derive(Debug, Default)]
+pub struct GamepadState {
+    pub focus: bool,
+    stick: (f32, f32),
+    dpad_axis: (f32, f32),
+    dpad: Vec<Direction>,
+    direction: Option<Direction>,
+    held: Vec<Action>,
+}
+
+impl GamepadState {
+    pub fn handle(&mut self, event: PadEvent) -> Vec<PadCommand> {
+        let mut commands = Vec::new();
+
+        match event {
+            PadEvent::Button(button, pressed) => {
+                self.handle_button(button, pressed, &mut commands)
+            }
+            PadEvent::Axis(axis, value) => {
+                match axis {
+                    PadAxis::LeftX => self.stick.0 = value,
+                    PadAxis::LeftY => self.stick.1 = value,
+                    PadAxis::DPadX => self.dpad_axis.0 = value,
+                    PadAxis::DPadY => self.dpad_axis.1 = value,
+                }
+                self.update_direction(&mut commands);
+            }
+            PadEvent::Disconnected => {
+                self.release_held(&mut commands);
+                *self = GamepadState {
+                    focus: self.focus,
+                    ..GamepadState::default()
+                };
+            }
+        }
+
+        commands
+    }
+
+    fn handle_button(
+        &mut self,
+        button: PadButton,
+        pressed: bool,
+        commands: &mut Vec<PadCommand>,
+    ) {
+        let dpad = match button {
+            PadButton::DPadUp => Some(Direction::Up),
+            PadButton::DPadDown => Some(Direction::Down),
+            PadButton::DPadLeft => Some(Direction::Left),
+            PadButton::DPadRight => Some(Direction::Right),
+            _ => None,
+        };
+
+        if let Some(dir) = dpad {
+            self.dpad.retain(|held| *held != dir);
+
+            if pressed {
+                self.dpad.insert(0, dir);
+            }
+
+            self.update_direction(commands);
+            return;
+        }
+
+        if button == PadButton::Start {
+            if pressed {
+                self.set_focus(!self.focus, commands);
+            }
+            return;
+        }
+
+        if self.focus {
+            if !pressed {
+                return;
+            }
+
+            match button {
+                PadButton::South => commands.push(PadCommand::Confirm),
+                PadButton::North => commands.push(PadCommand::Use),
+                PadButton::East => self.set_focus(false, commands),
+                PadButton::LeftShoulder => {
+                    commands.push(PadCommand::PrevWindow)
+                }
+                PadButton::RightShoulder => {
+                    commands.push(PadCommand::NextWindow)
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        let action = match button {
+            PadButton::South => Action::Attack,
+            PadButton::West => Action::PickUp,
+            PadButton::North => Action::OpenInventory,
+            PadButton::Select => Action::OpenProfile,
+            _ => return,
+        };
+
+        self.set_held(action, pressed, commands);
+    }
+
+    fn set_held(
+        &mut self,
+        action: Action,
+        pressed: bool,
+        commands: &mut Vec<PadCommand>,
+    ) {
+        let was_held = self.held.contains(&action);
+
+        if pressed && !was_held {
+            self.held.push(action);
+        } else if !pressed && was_held {
+            self.held.retain(|held| *held != action);
+        } else {
+            return;
+        }
+
+        commands.push(PadCommand::Action(action, pressed));
+    }
+
+    fn release_held(&mut self, commands: &mut Vec<PadCommand>) {
+        for action in std::mem::take(&mut self.held) {
+            commands.push(PadCommand::Action(action, false));
+        }
+    }
+
+    fn set_focus(&mut self, focus: bool, commands: &mut Vec<PadCommand>) {
+        if self.focus == focus {
+            return;
+        }
+
+        self.focus = focus;
+        self.release_held(commands);
+        commands.push(PadCommand::FocusMode(focus));
+
+        // A direction held while leaving focus mode starts moving.
+        if !focus && let Some(dir) = self.direction {
+            self.set_held(move_action(dir), true, commands);
+        }
+    }
+
+    fn update_direction(&mut self, commands: &mut Vec<PadCommand>) {
+        let direction = self
+            .dpad
+            .first()
+            .copied()
+            .or_else(|| stick_direction(self.dpad_axis.0, self.dpad_axis.1))
+            .or_else(|| stick_direction(self.stick.0, self.stick.1));
+
+        if direction == self.direction {
+            return;
+        }
+
+        if let Some(old) = self.direction {
+            self.set_held(move_action(old), false, commands);
+        }
+
+        self.direction = direction;
+
+        if let Some(dir) = direction {
+            if self.focus {
+                commands.push(PadCommand::Navigate(dir));
+            } else {
+                self.set_held(move_action(dir), true, commands);
+            }
+        }
+    }
+}
+
+/// The pad backend and its state. Without a source it never reports input.
+pub struct Gamepad {
+    source: Option<Box<dyn PadSource>>,
+    pub state: GamepadState,
+}
+
+impl Gamepad {
+    pub fn new(enabled: bool) -> Self {
+        let source = if enabled {
+            GilrsSource::new()
+                .map(|source| Box::new(source) as Box<dyn PadSource>)
+        } else {
+            None
+        };
+
+        Gamepad {
+            source,
+            state: GamepadState::default(),
+        }
+    }
+
+    pub fn with_source(source: Box<dyn PadSource>) -> Self {
+        Gamepad {
+            source: Some(source),
+            state: GamepadState::default(),
+        }
+    }
+
+    /// Drains the source and returns the commands for this frame.
+    pub fn poll(&mut self) -> Vec<PadCommand> {
+        let mut commands = Vec::new();
+
+        if let Some(source) = self.source.as_mut() {
+            while let Some(event) = source.next_event() {
+                commands.extend(self.state.handle(event));
+            }
+        }
+
+        commands
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn virtual_gamepad() -> (Gamepad, VirtualPadHandle) {
+        let (pad, handle) = VirtualPad::new();
+        (Gamepad::with_source(Box::new(pad)), handle)
+    }
+
+    #[test]
+    fn stick_and_dpad_drive_movement() {
+        let (mut gamepad, pad) = virtual_gamepad();
+
+        pad.stick(0.2, 0.3);
+        assert!(gamepad.poll().is_empty());
+
+        pad.stick(0.9, -0.4);
+        assert_eq!(
+            gamepad.poll(),
+            [PadCommand::Action(Action::MoveRight, true)]
+        );
+
+        pad.press(PadButton::DPadUp);
+        assert_eq!(
+            gamepad.poll(),
+            [
+                PadCommand::Action(Action::MoveRight, false),
+                PadCommand::Action(Action::MoveUp, true),
+            ]
+        );
+
+        pad.release(PadButton::DPadUp);
+        pad.stick(0.0, 0.0);
+        assert_eq!(
+            gamepad.poll(),
+            [
+                PadCommand::Action(Action::MoveUp, false),
+                PadCommand::Action(Action::MoveRight, true),
+                PadCommand::Action(Action::MoveRight, false),
+            ]
+        );
+
+        pad.press(PadButton::South);
+        pad.press(PadButton::West);
+        pad.release(PadButton::South);
+        assert_eq!(
+            gamepad.poll(),
+            [
+                PadCommand::Action(Action::Attack, true),
+                PadCommand::Action(Action::PickUp, true),
+                PadCommand::Action(Action::Attack, false),
+            ]
+        );
+
+        pad.push(PadEvent::Disconnected);
+        assert_eq!(gamepad.poll(), [PadCommand::Action(Action::PickUp, false)]);
+    }
+
+    #[test]
+    fn focus_mode_navigates_instead_of_moving() {
+        let (mut gamepad, pad) = virtual_gamepad();
+
+        pad.stick(-1.0, 0.0);
+        pad.press(PadButton::Start);
+        assert_eq!(
+            gamepad.poll(),
+            [
+                PadCommand::Action(Action::MoveLeft, true),
+                PadCommand::Action(Action::MoveLeft, false),
+                PadCommand::FocusMode(true),
+            ]
+        );
+
+        pad.stick(0.0, 0.0);
+        pad.press(PadButton::DPadDown);
+        pad.release(PadButton::DPadDown);
+        pad.press(PadButton::South);
+        pad.press(PadButton::North);
+        pad.press(PadButton::RightShoulder);
+        assert_eq!(
+            gamepad.poll(),
+            [
+                PadCommand::Navigate(Direction::Down),
+                PadCommand::Confirm,
+                PadCommand::Use,
+                PadCommand::NextWindow,
+            ]
+        );
+
+        pad.press(PadButton::DPadRight);
+        pad.press(PadButton::East);
+        assert_eq!(
+            gamepad.poll(),
+            [
+                PadCommand::Navigate(Direction::Right),
+                PadCommand::FocusMode(false),
+                PadCommand::Action(Action::MoveRight, true),
+            ]
+        );
+    }
+}
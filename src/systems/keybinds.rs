@@ -0,0 +1,463 @@
+use input::{Axis, Bindings, Key};
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs};
+use winit::keyboard::NamedKey;
+
+use crate::{Result, write_atomic};
+
+pub const KEYBINDS_FILE: &str = "keybinds.toml";
+pub const KEYBINDS_VERSION: u32 = 1;
+
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Hash,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+)]
+pub enum Action {
+    Quit,
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Attack,
+    PickUp,
+    OpenInventory,
+    OpenProfile,
+    OpenSetting,
+    FocusChat,
+    ToggleNetStats,
+}
+
+impl Action {
+    pub const ALL: [Action; 12] = [
+        Action::Quit,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Attack,
+        Action::PickUp,
+        Action::OpenInventory,
+        Action::OpenProfile,
+        Action::OpenSetting,
+        Action::FocusChat,
+        Action::ToggleNetStats,
+    ];
+
+    /// Actions listed on the key binding page of the Setting window. The
+    /// others are only rebound in keybinds.toml.
+    pub const GAMEPLAY: [Action; 10] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Attack,
+        Action::PickUp,
+        Action::OpenInventory,
+        Action::OpenProfile,
+        Action::OpenSetting,
+        Action::FocusChat,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::MoveUp => "Move Up",
+            Action::MoveDown => "Move Down",
+            Action::MoveLeft => "Move Left",
+            Action::MoveRight => "Move Right",
+            Action::Attack => "Attack",
+            Action::PickUp => "Pick Up",
+            Action::OpenInventory => "Inventory",
+            Action::OpenProfile => "Profile",
+            Action::OpenSetting => "Setting",
+            Action::FocusChat => "Chat",
+            Action::ToggleNetStats => "Network Stats",
+        }
+    }
+
+    fn default_keys(&self) -> &'static [&'static str] {
+        match self {
+            Action::Quit => &[],
+            Action::MoveUp => &["W"],
+            Action::MoveDown => &["S"],
+            Action::MoveLeft => &["A"],
+            Action::MoveRight => &["D"],
+            Action::Attack => &["Control"],
+            Action::PickUp => &["Space"],
+            Action::OpenInventory => &["I"],
+            Action::OpenProfile => &["P"],
+            Action::OpenSetting => &["O"],
+            Action::FocusChat => &["Enter"],
+            Action::ToggleNetStats => &["F5"],
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Action> {
+        Action::ALL
+            .into_iter()
+            .find(|action| format!("{:?}", action) == name)
+    }
+}
+
+/// Named keys that can be bound, with the name keybinds.toml uses for them.
+const NAMED_KEYS: [(&str, NamedKey); 32] = [
+    ("Control", NamedKey::Control),
+    ("Shift", NamedKey::Shift),
+    ("Alt", NamedKey::Alt),
+    ("Space", NamedKey::Space),
+    ("Enter", NamedKey::Enter),
+    ("Tab", NamedKey::Tab),
+    ("Backspace", NamedKey::Backspace),
+    ("Escape", NamedKey::Escape),
+    ("ArrowUp", NamedKey::ArrowUp),
+    ("ArrowDown", NamedKey::ArrowDown),
+    ("ArrowLeft", NamedKey::ArrowLeft),
+    ("ArrowRight", NamedKey::ArrowRight),
+    ("Home", NamedKey::Home),
+    ("End", NamedKey::End),
+    ("PageUp", NamedKey::PageUp),
+    ("PageDown", NamedKey::PageDown),
+    ("Insert", NamedKey::Insert),
+    ("Delete", NamedKey::Delete),
+    ("CapsLock", NamedKey::CapsLock),
+    ("AltGraph", NamedKey::AltGraph),
+    ("F1", NamedKey::F1),
+    ("F2", NamedKey::F2),
+    ("F3", NamedKey::F3),
+    ("F4", NamedKey::F4),
+    ("F5", NamedKey::F5),
+    ("F6", NamedKey::F6),
+    ("F7", NamedKey::F7),
+    ("F8", NamedKey::F8),
+    ("F9", NamedKey::F9),
+    ("F10", NamedKey::F10),
+    ("F11", NamedKey::F11),
+    ("F12", NamedKey::F12),
+];
+
+/// The name keybinds.toml uses for a key. Letters are stored upper case so
+/// a binding still matches while Shift or Caps Lock is held.
+pub fn key_name(key: &Key) -> Option<String> {
+    if let Key::Character(c) = key {
+        return Some(c.to_uppercase().collect());
+    }
+
+    if let Key::Named(named) = key {
+        return NAMED_KEYS
+            .iter()
+            .find(|(_, key)| key == named)
+            .map(|(name, _)| name.to_string());
+    }
+
+    None
+}
+
+/// Parses a key name back into a key. Returns None for unknown names.
+pub fn parse_key(name: &str) -> Option<Key> {
+    let mut chars = name.chars();
+
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(Key::Character(c.to_lowercase().next().unwrap_or(c)));
+    }
+
+    NAMED_KEYS
+        .iter()
+        .find(|(key_name, _)| key_name.eq_ignore_ascii_case(name))
+        .map(|(_, named)| Key::Named(*named))
+}
+
+fn normalize_key(name: &str) -> Option<String> {
+    parse_key(name).and_then(|key| key_name(&key))
+}
+
+#[derive(Serialize, Deserialize)]
+struct KeybindsFile {
+    version: u32,
+    keys: BTreeMap<String, Vec<String>>,
+}
+
+/// Which keys trigger each Action. Read from keybinds.toml and turned into
+/// the input crate's `Bindings` for the InputHandler.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keybinds {
+    keys: BTreeMap<Action, Vec<String>>,
+    changed: bool,
+}
+
+impl Default for Keybinds {
+    fn default() -> Self {
+        Keybinds {
+            keys: Action::ALL
+                .into_iter()
+                .map(|action| {
+                    let keys = action
+                        .default_keys()
+                        .iter()
+                        .map(|key| key.to_string())
+                        .collect();
+                    (action, keys)
+                })
+                .collect(),
+            changed: false,
+        }
+    }
+}
+
+impl Keybinds {
+    pub fn load(path: &str) -> Self {
+        let data = match fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(_) => {
+                let keybinds = Keybinds::default();
+                keybinds.save(path);
+                return keybinds;
+            }
+        };
+
+        match Keybinds::from_toml(&data) {
+            Ok(keybinds) => {
+                for (key, actions) in keybinds.conflicts() {
+                    warn!(
+                        "{} is bound to more than one action: {:?}",
+                        key, actions
+                    );
+                }
+
+                keybinds
+            }
+            Err(e) => {
+                error!("{} is not valid, using the defaults: {:?}", path, e);
+                Keybinds::default()
+            }
+        }
+    }
+
+    /// Actions missing from the file keep their default keys, unknown
+    /// actions and key names are logged and skipped.
+    pub fn from_toml(data: &str) -> Result<Self> {
+        let file: KeybindsFile = toml::from_str(data)?;
+        let mut keybinds = Keybinds::default();
+
+        for (name, keys) in file.keys {
+            let Some(action) = Action::from_name(&name) else {
+                warn!("Unknown action {} in keybinds", name);
+                continue;
+            };
+
+            let keys = keys
+                .iter()
+                .filter_map(|key| {
+                    let normalized = normalize_key(key);
+
+                    if normalized.is_none() {
+                        warn!("Unknown key {} for {} in keybinds", key, name);
+                    }
+
+                    normalized
+                })
+                .collect();
+
+            keybinds.keys.insert(action, keys);
+        }
+
+        Ok(keybinds)
+    }
+
+    pub fn to_toml(&self) -> std::result::Result<String, toml::ser::Error> {
+        let file = KeybindsFile {
+            version: KEYBINDS_VERSION,
+            keys: self
+                .keys
+                .iter()
+                .map(|(action, keys)| (format!("{:?}", action), keys.clone()))
+                .collect(),
+        };
+
+        toml::to_string(&file)
+    }
+
+    pub fn save(&self, path: &str) {
+        let data = match self.to_toml() {
+            Ok(data) => data,
+            Err(e) => {
+                error!("Failed to serialize {}: {}", path, e);
+                return;
+            }
+        };
+
+        if let Err(e) = write_atomic(path, data.as_bytes()) {
+            error!("Failed to save {}: {:?}", path, e);
+        }
+    }
+
+    pub fn keys(&self, action: Action) -> &[String] {
+        self.keys
+            .get(&action)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub fn action_for(&self, key: &Key) -> Option<Action> {
+        let name = key_name(key)?;
+
+        self.keys
+            .iter()
+            .find(|(_, keys)| keys.contains(&name))
+            .map(|(action, _)| *action)
+    }
+
+    /// Makes `key` the only key of `action`. Fails with the action that
+    /// already uses it, leaving the bindings unchanged.
+    pub fn rebind(
+        &mut self,
+        action: Action,
+        key: &Key,
+    ) -> std::result::Result<(), Option<Action>> {
+        let name = key_name(key).ok_or(None)?;
+
+        match self.action_for(key) {
+            Some(other) if other != action => return Err(Some(other)),
+            _ => {}
+        }
+
+        self.keys.insert(action, vec![name]);
+        self.changed = true;
+        Ok(())
+    }
+
+    pub fn reset(&mut self) {
+        *self = Keybinds {
+            changed: true,
+            ..Keybinds::default()
+        };
+    }
+
+    /// Keys bound to more than one action.
+    pub fn conflicts(&self) -> Vec<(String, Vec<Action>)> {
+        let mut users: BTreeMap<&str, Vec<Action>> = BTreeMap::new();
+
+        for (action, keys) in &self.keys {
+            for key in keys {
+                users.entry(key).or_default().push(*action);
+            }
+        }
+
+        users
+            .into_iter()
+            .filter(|(_, actions)| actions.len() > 1)
+            .map(|(key, actions)| (key.to_string(), actions))
+            .collect()
+    }
+
+    /// True once after the bindings changed, so the InputHandler can be
+    /// rebuilt.
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    pub fn to_bindings(&self) -> Bindings<Action, Axis> {
+        let mut bindings = Bindings::<Action, Axis>::new();
+
+        for (action, keys) in &self.keys {
+            let keys: Vec<_> = keys
+                .iter()
+                .filter_map(|key| parse_key(key))
+                .map(Into::into)
+                .collect();
+
+            if !keys.is_empty() {
+                bindings.insert_action(*action, keys);
+            }
+        }
+
+        bindings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_names_round_trip() {
+        for (name, _) in NAMED_KEYS {
+            assert_eq!(normalize_key(name).as_deref(), Some(name));
+        }
+
+        assert_eq!(key_name(&Key::Character('w')).as_deref(), Some("W"));
+        assert_eq!(key_name(&Key::Character('W')).as_deref(), Some("W"));
+        assert_eq!(normalize_key("arrowup").as_deref(), Some("ArrowUp"));
+        assert_eq!(normalize_key("NotAKey"), None);
+    }
+
+    #[test]
+    fn file_overrides_defaults_and_skips_unknown_entries() {
+        let keybinds = Keybinds::from_toml(
+            r#"
+            version = 1
+
+            [keys]
+            MoveUp = ["z", "ArrowUp"]
+            MoveLeft = ["Q"]
+            Dance = ["X"]
+            Attack = ["Hyper"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(keybinds.keys(Action::MoveUp), ["Z", "ArrowUp"]);
+        assert_eq!(
+            keybinds.action_for(&Key::Character('q')),
+            Some(Action::MoveLeft)
+        );
+        assert_eq!(keybinds.keys(Action::MoveDown), ["S"]);
+        assert!(keybinds.keys(Action::Attack).is_empty());
+
+        let saved = Keybinds::from_toml(&keybinds.to_toml().unwrap()).unwrap();
+        assert_eq!(saved, keybinds);
+    }
+
+    #[test]
+    fn rebind_refuses_keys_in_use() {
+        let mut keybinds = Keybinds::default();
+        assert!(keybinds.conflicts().is_empty());
+        assert_eq!(
+            keybinds.action_for(&Key::Named(NamedKey::F5)),
+            Some(Action::ToggleNetStats)
+        );
+
+        assert_eq!(
+            keybinds.rebind(Action::MoveUp, &Key::Character('a')),
+            Err(Some(Action::MoveLeft))
+        );
+        assert_eq!(keybinds.keys(Action::MoveUp), ["W"]);
+        assert!(!keybinds.take_changed());
+
+        keybinds
+            .rebind(Action::MoveUp, &Key::Character('z'))
+            .unwrap();
+        keybinds
+            .rebind(Action::MoveUp, &Key::Character('Z'))
+            .unwrap();
+        assert_eq!(keybinds.keys(Action::MoveUp), ["Z"]);
+        assert!(keybinds.take_changed());
+        assert!(!keybinds.take_changed());
+
+        let conflicting =
+            Keybinds::from_toml("version = 1\n[keys]\nAttack = [\"W\"]\n")
+                .unwrap();
+        assert_eq!(
+            conflicting.conflicts(),
+            [("W".to_string(), vec![Action::MoveUp, Action::Attack])]
+        );
+    }
+}
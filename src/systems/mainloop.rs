@@ -40,6 +40,7 @@ pub fn game_loop(
                     buffer,
                     seconds,
                 )?;
+                content.game_content.update_path(world, systems, socket)?;
                 update_npc(
                     world,
                     systems,
@@ -70,7 +71,9 @@ pub fn game_loop(
                 loop_timer.ping_tmr = seconds + 1.0;
             }
         }
-        ContentType::Menu => {}
+        ContentType::Menu => {
+            content.menu_content.update(systems);
+        }
     }
     Ok(())
 }
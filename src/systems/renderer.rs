@@ -1,7 +1,10 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    ops::{Deref, DerefMut},
+};
 
 use graphics::{
-    wgpu::{InstanceFlags, PresentMode},
+    wgpu::{Backends, InstanceFlags, PresentMode},
     *,
 };
 use lru::LruCache;
@@ -14,10 +17,38 @@ pub mod fade;
 pub use fade::*;
 
 use crate::{
-    Audio, Config, ItemData, MapData, MapPosition, MapSlotData, NpcData,
-    ShopData, TextureAllocation, data_types::*, game_content::*,
+    Audio, Config, ContentTable, DataFiles, ItemData, Keybinds, MapData,
+    MapPosition, MapSlotData, NpcData, SettingsService, ShopData,
+    TextureAllocation, data_types::*, game_content::*,
 };
 
+/// Graphics API the renderer may pick, see Config::graphic_backend.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ClientBackend {
+    OpenGL,
+    DX12,
+    Vulkan,
+    Metal,
+}
+
+impl ClientBackend {
+    pub const ALL: [ClientBackend; 4] = [
+        ClientBackend::OpenGL,
+        ClientBackend::DX12,
+        ClientBackend::Vulkan,
+        ClientBackend::Metal,
+    ];
+
+    pub fn to_backends(self) -> Backends {
+        match self {
+            ClientBackend::OpenGL => Backends::GL,
+            ClientBackend::DX12 => Backends::DX12,
+            ClientBackend::Vulkan => Backends::VULKAN,
+            ClientBackend::Metal => Backends::METAL,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub enum ClientAdapterPowerSettings {
     LowPower,
@@ -37,7 +68,7 @@ impl ClientAdapterPowerSettings {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
 pub enum ClientPresentMode {
     AutoVsync,
     AutoNoVsync,
@@ -58,6 +89,54 @@ impl ClientPresentMode {
             ClientPresentMode::Mailbox => PresentMode::Mailbox,
         }
     }
+
+    /// True for the modes that wait for vertical blank.
+    pub fn is_vsync(&self) -> bool {
+        matches!(
+            self,
+            ClientPresentMode::AutoVsync
+                | ClientPresentMode::Fifo
+                | ClientPresentMode::FifoRelaxed
+        )
+    }
+}
+
+/// Reconfigures the surface with the present mode from the config, so the
+/// VSync option applies without a restart. The renderer configures the
+/// surface with the mode it was created with whenever the window resizes,
+/// so the runner calls this again after a resize.
+pub fn apply_present_mode(systems: &mut SystemHolder) {
+    let renderer = &systems.renderer;
+    let size = renderer.window().inner_size();
+
+    if size.width == 0 || size.height == 0 {
+        return;
+    }
+
+    renderer.surface().configure(
+        renderer.device(),
+        &wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: renderer.surface_format(),
+            width: size.width,
+            height: size.height,
+            present_mode: systems.config.present_mode.parse_enum(),
+            desired_maximum_frame_latency: 2,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            view_formats: vec![],
+        },
+    );
+}
+
+/// The scale the UI is laid out with, the monitor's scale factor times the
+/// `ui_scale` from settings.toml.
+pub fn ui_scale_factor(renderer: &GpuRenderer, ui_scale: f32) -> f64 {
+    let monitor_scale = renderer
+        .window()
+        .current_monitor()
+        .map_or(1.0, |monitor| monitor.scale_factor());
+
+    monitor_scale.clamp(1.0, 1.5) * ui_scale as f64
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -86,17 +165,65 @@ pub struct TextCaret {
 }
 
 pub struct DatabaseHolder {
-    pub item: Vec<ItemData>,
-    pub shop: Vec<ShopData>,
-    pub npc: Vec<NpcData>,
+    pub item: ContentTable<ItemData>,
+    pub shop: ContentTable<ShopData>,
+    pub npc: ContentTable<NpcData>,
     pub mapdata: SlotMap<Index, MapSlotData>,
     pub mappos_key: HashMap<MapPosition, Index, ahash::RandomState>,
     pub map_cache: LruCache<Index, Index>,
+    pub data_files: DataFiles,
+}
+
+impl DatabaseHolder {
+    /// The item with this id, or the "???" placeholder when its data file
+    /// is missing, so a bad id never indexes out of range.
+    pub fn get_item(&self, id: usize) -> &ItemData {
+        self.item.get_or_placeholder(id)
+    }
+
+    pub fn get_npc(&self, id: usize) -> &NpcData {
+        self.npc.get_or_placeholder(id)
+    }
+
+    pub fn get_shop(&self, id: usize) -> &ShopData {
+        self.shop.get_or_placeholder(id)
+    }
+}
+
+/// The GpuRenderer, or none for headless systems. Headless systems drive
+/// the packet handlers in tests and build no graphics, so every widget
+/// index in them is GfxType::None and GfxCollection never reaches for the
+/// renderer. Anything else that does panics.
+pub struct ClientRenderer(Option<GpuRenderer>);
+
+impl ClientRenderer {
+    pub fn new(renderer: GpuRenderer) -> Self {
+        ClientRenderer(Some(renderer))
+    }
+
+    #[cfg(test)]
+    pub fn headless() -> Self {
+        ClientRenderer(None)
+    }
+}
+
+impl Deref for ClientRenderer {
+    type Target = GpuRenderer;
+
+    fn deref(&self) -> &GpuRenderer {
+        self.0.as_ref().expect("headless systems have no renderer")
+    }
+}
+
+impl DerefMut for ClientRenderer {
+    fn deref_mut(&mut self) -> &mut GpuRenderer {
+        self.0.as_mut().expect("headless systems have no renderer")
+    }
 }
 
 pub struct SystemHolder {
     pub gfx: GfxCollection,
-    pub renderer: GpuRenderer,
+    pub renderer: ClientRenderer,
     pub size: PhysicalSize<f32>,
     pub scale: f64,
     pub resource: TextureAllocation,
@@ -108,6 +235,51 @@ pub struct SystemHolder {
     pub caret: TextCaret,
     pub try_once: bool,
     pub fps: GfxType,
+    pub settings: SettingsService,
+    pub keybinds: Keybinds,
+    pub gamepad: Gamepad,
+}
+
+impl SystemHolder {
+    /// Systems without a window, GPU or audio device, for running packet
+    /// handlers in tests. The data tables load from `data_root`.
+    #[cfg(test)]
+    pub fn headless(data_root: &std::path::Path) -> Self {
+        let mut data_files = DataFiles::new(data_root);
+
+        SystemHolder {
+            gfx: GfxCollection::new(),
+            renderer: ClientRenderer::headless(),
+            size: PhysicalSize::new(800.0, 600.0),
+            scale: 1.0,
+            resource: TextureAllocation::default(),
+            fade: Fade::new(),
+            map_fade: MapFade::new(),
+            config: Config::default(),
+            base: DatabaseHolder {
+                item: crate::load_items(&mut data_files),
+                shop: crate::load_shops(&mut data_files),
+                npc: crate::load_npcs(&mut data_files),
+                mapdata: SlotMap::with_key(),
+                mappos_key: HashMap::default(),
+                map_cache: LruCache::new(
+                    std::num::NonZeroUsize::new(64).unwrap(),
+                ),
+                data_files,
+            },
+            audio: Audio::muted(),
+            caret: TextCaret {
+                visible: false,
+                index: None,
+                timer: 0.0,
+            },
+            try_once: true,
+            fps: GfxType::None,
+            settings: SettingsService::new(),
+            keybinds: Keybinds::default(),
+            gamepad: Gamepad::new(false),
+        }
+    }
 }
 
 pub struct State<Controls>
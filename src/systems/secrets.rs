@@ -0,0 +1,420 @@
+use ring::{
+    aead::{Aad, CHACHA20_POLY1305, LessSafeKey, NONCE_LEN, Nonce, UnboundKey},
+    hkdf::{HKDF_SHA256, Salt},
+    rand::{SecureRandom, SystemRandom},
+};
+use serde::{Deserialize, Deserializer, Serializer};
+use std::{
+    fmt::Write as _,
+    fs,
+    io::{self, Write},
+    path::Path,
+    sync::OnceLock,
+};
+
+use crate::Result;
+
+/// Random per install salt mixed into the machine key. Without it a copied
+/// settings.toml can not be opened, even on a machine with the same id.
+pub const LOCAL_KEY_FILE: &str = "keys/local.key";
+const LOCAL_KEY_LEN: usize = 32;
+
+/// Writes a file only the current user can read, for private keys and
+/// credentials.
+pub fn write_private_file(
+    path: impl AsRef<Path>,
+    data: &[u8],
+) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(path)?;
+
+    // Locked down before anything is written to it.
+    #[cfg(windows)]
+    restrict_to_owner(path)?;
+
+    file.write_all(data)?;
+    file.sync_all()
+}
+
+/// Makes an existing private file owner only, for files written before
+/// write_private_file was used for them.
+pub fn restrict_private_file(path: impl AsRef<Path>) -> io::Result<()> {
+    let path = path.as_ref();
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mut permissions = fs::metadata(path)?.permissions();
+
+        if permissions.mode() & 0o077 != 0 {
+            permissions.set_mode(0o600);
+            fs::set_permissions(path, permissions)?;
+        }
+    }
+
+    #[cfg(windows)]
+    restrict_to_owner(path)?;
+
+    Ok(())
+}
+
+/// Replaces the file's permissions with full access for its owner only,
+/// what mode 0o600 does on unix.
+#[cfg(windows)]
+fn restrict_to_owner(path: &Path) -> io::Result<()> {
+    use std::{ffi::OsStr, os::windows::ffi::OsStrExt, ptr};
+    use windows_sys::Win32::{
+        Foundation::LocalFree,
+        Security::{
+            Authorization::{
+                ConvertStringSecurityDescriptorToSecurityDescriptorW,
+                SDDL_REVISION_1,
+            },
+            DACL_SECURITY_INFORMATION, PROTECTED_DACL_SECURITY_INFORMATION,
+            PSECURITY_DESCRIPTOR, SetFileSecurityW,
+        },
+    };
+
+    let wide = |text: &OsStr| -> Vec<u16> {
+        text.encode_wide().chain(Some(0)).collect()
+    };
+
+    // A protected DACL, so nothing is inherited from the folder, with a
+    // single entry giving the owner full access.
+    let sddl = wide(OsStr::new("D:P(A;;FA;;;OW)"));
+    let path = wide(path.as_os_str());
+    let mut descriptor: PSECURITY_DESCRIPTOR = ptr::null_mut();
+
+    // SAFETY: both strings are NUL terminated, and the descriptor the first
+    // call allocates is freed with LocalFree as it requires.
+    unsafe {
+        if ConvertStringSecurityDescriptorToSecurityDescriptorW(
+            sddl.as_ptr(),
+            SDDL_REVISION_1,
+            &mut descriptor,
+            ptr::null_mut(),
+        ) == 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+
+        let set = SetFileSecurityW(
+            path.as_ptr(),
+            DACL_SECURITY_INFORMATION | PROTECTED_DACL_SECURITY_INFORMATION,
+            descriptor,
+        );
+        let error = io::Error::last_os_error();
+
+        LocalFree(descriptor);
+
+        if set == 0 {
+            return Err(error);
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes to a private temporary file and renames it over `path`, so a
+/// crash never leaves a half written file behind.
+pub fn write_atomic(path: impl AsRef<Path>, data: &[u8]) -> Result<()> {
+    let path = path.as_ref();
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+
+    write_private_file(&tmp, data)?;
+    fs::rename(&tmp, path)?;
+
+    Ok(())
+}
+
+fn to_hex(data: &[u8]) -> String {
+    let mut hex = String::with_capacity(data.len() * 2);
+
+    for byte in data {
+        let _ = write!(hex, "{:02x}", byte);
+    }
+
+    hex
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// The id the OS gives this install, if it can be read.
+#[cfg(not(windows))]
+fn platform_machine_id() -> Option<Vec<u8>> {
+    ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+        .iter()
+        .find_map(|path| fs::read(path).ok())
+}
+
+/// The id the OS gives this install, if it can be read.
+#[cfg(windows)]
+fn platform_machine_id() -> Option<Vec<u8>> {
+    use winreg::{
+        RegKey,
+        enums::{HKEY_LOCAL_MACHINE, KEY_READ, KEY_WOW64_64KEY},
+    };
+
+    // The 64 bit view, a 32 bit build would otherwise see another key.
+    let key = RegKey::predef(HKEY_LOCAL_MACHINE)
+        .open_subkey_with_flags(
+            r"SOFTWARE\Microsoft\Cryptography",
+            KEY_READ | KEY_WOW64_64KEY,
+        )
+        .ok()?;
+    let guid: String = key.get_value("MachineGuid").ok()?;
+
+    Some(guid.into_bytes())
+}
+
+/// The account the client runs as, so other users of the machine get
+/// another key.
+#[cfg(unix)]
+fn user_id() -> Option<Vec<u8>> {
+    // SAFETY: getuid has no preconditions and always succeeds.
+    let uid = unsafe { libc::getuid() };
+
+    Some(uid.to_le_bytes().to_vec())
+}
+
+/// The SID of the account the client runs as.
+#[cfg(windows)]
+fn user_id() -> Option<Vec<u8>> {
+    use std::{ptr, slice};
+    use windows_sys::Win32::{
+        Foundation::{CloseHandle, HANDLE},
+        Security::{
+            GetLengthSid, GetTokenInformation, TOKEN_QUERY, TOKEN_USER,
+            TokenUser,
+        },
+        System::Threading::{GetCurrentProcess, OpenProcessToken},
+    };
+
+    let mut token: HANDLE = ptr::null_mut();
+    let mut len = 0u32;
+
+    // SAFETY: the token is closed before the SID is read. The buffer is the
+    // size the first call asked for, in u64s so the TOKEN_USER at its start
+    // is aligned, and the SID it points to lies inside it.
+    unsafe {
+        if OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) == 0 {
+            return None;
+        }
+
+        GetTokenInformation(token, TokenUser, ptr::null_mut(), 0, &mut len);
+
+        let mut buffer = vec![0u64; (len as usize).div_ceil(8)];
+        let read = GetTokenInformation(
+            token,
+            TokenUser,
+            buffer.as_mut_ptr().cast(),
+            len,
+            &mut len,
+        );
+
+        CloseHandle(token);
+
+        if read == 0 {
+            return None;
+        }
+
+        let sid = (*buffer.as_ptr().cast::<TOKEN_USER>()).User.Sid;
+        let sid_len = GetLengthSid(sid) as usize;
+
+        Some(slice::from_raw_parts(sid.cast::<u8>(), sid_len).to_vec())
+    }
+}
+
+/// Something that identifies this machine and user. Only read from the OS,
+/// so it does not change with the environment the client is started from.
+/// Not secret on its own, it only ties the sealed values to where they were
+/// written.
+fn machine_id() -> Vec<u8> {
+    let mut id = platform_machine_id().unwrap_or_default();
+
+    id.extend(user_id().unwrap_or_default());
+    id
+}
+
+fn load_local_key(path: &str) -> io::Result<Vec<u8>> {
+    match fs::read(path) {
+        Ok(key) if key.len() == LOCAL_KEY_LEN => Ok(key),
+        _ => {
+            let mut key = vec![0u8; LOCAL_KEY_LEN];
+
+            SystemRandom::new().fill(&mut key).map_err(|_| {
+                io::Error::other("no random source for the local key")
+            })?;
+
+            if let Some(parent) = Path::new(path).parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            write_private_file(path, &key)?;
+            Ok(key)
+        }
+    }
+}
+
+/// Encrypts small secrets such as login tokens before they are written to
+/// settings.toml.
+pub struct SecretKey {
+    key: LessSafeKey,
+    rng: SystemRandom,
+}
+
+impl SecretKey {
+    pub fn derive(local_key: &[u8], machine_id: &[u8]) -> Option<Self> {
+        let okm = Salt::new(HKDF_SHA256, local_key)
+            .extract(machine_id)
+            .expand(&[b"ascending settings secret"], &CHACHA20_POLY1305)
+            .ok()?;
+
+        Some(SecretKey {
+            key: LessSafeKey::new(UnboundKey::from(okm)),
+            rng: SystemRandom::new(),
+        })
+    }
+
+    /// The key of this machine, created on first use.
+    pub fn machine() -> Option<&'static SecretKey> {
+        static KEY: OnceLock<Option<SecretKey>> = OnceLock::new();
+
+        KEY.get_or_init(|| match load_local_key(LOCAL_KEY_FILE) {
+            Ok(local_key) => SecretKey::derive(&local_key, &machine_id()),
+            Err(e) => {
+                log::error!("Could not load {}: {}", LOCAL_KEY_FILE, e);
+                None
+            }
+        })
+        .as_ref()
+    }
+
+    /// Returns hex of the nonce followed by the ciphertext and tag.
+    pub fn seal(&self, plain: &str) -> Option<String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng.fill(&mut nonce).ok()?;
+
+        let mut data = plain.as_bytes().to_vec();
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::empty(),
+                &mut data,
+            )
+            .ok()?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&data);
+        Some(to_hex(&sealed))
+    }
+
+    /// None when the value was sealed by another key or was changed.
+    pub fn open(&self, sealed: &str) -> Option<String> {
+        let data = from_hex(sealed)?;
+
+        if data.len() < NONCE_LEN {
+            return None;
+        }
+
+        let (nonce, data) = data.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).ok()?;
+        let mut data = data.to_vec();
+        let plain = self
+            .key
+            .open_in_place(nonce, Aad::empty(), &mut data)
+            .ok()?;
+
+        String::from_utf8(plain.to_vec()).ok()
+    }
+}
+
+/// Serde adapter for `#[serde(with = "sealed")]` string fields. Values are
+/// sealed with the machine key on save and opened on load. A value that can
+/// not be opened loads as empty, so the player just signs in again.
+pub mod sealed {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        value: &str,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        if value.is_empty() {
+            return serializer.serialize_str("");
+        }
+
+        match SecretKey::machine().and_then(|key| key.seal(value)) {
+            Some(sealed) => serializer.serialize_str(&sealed),
+            None => {
+                log::warn!("Could not seal a secret, it will not be saved");
+                serializer.serialize_str("")
+            }
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<String, D::Error> {
+        let sealed = String::deserialize(deserializer)?;
+
+        if sealed.is_empty() {
+            return Ok(sealed);
+        }
+
+        match SecretKey::machine().and_then(|key| key.open(&sealed)) {
+            Some(value) => Ok(value),
+            None => {
+                log::warn!("A saved secret could not be opened, dropping it");
+                Ok(String::new())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sealed_values_open_only_with_the_same_key() {
+        let key = SecretKey::derive(&[7; LOCAL_KEY_LEN], b"machine").unwrap();
+        let sealed = key.seal("login-token").unwrap();
+
+        assert!(!sealed.contains("login-token"));
+        assert_eq!(key.open(&sealed).as_deref(), Some("login-token"));
+        assert_ne!(key.seal("login-token").unwrap(), sealed);
+
+        let other = SecretKey::derive(&[8; LOCAL_KEY_LEN], b"machine").unwrap();
+        assert_eq!(other.open(&sealed), None);
+
+        let other = SecretKey::derive(&[7; LOCAL_KEY_LEN], b"laptop").unwrap();
+        assert_eq!(other.open(&sealed), None);
+
+        let mut tampered = sealed.into_bytes();
+        let last = tampered.len() - 1;
+        tampered[last] = if tampered[last] == b'0' { b'1' } else { b'0' };
+        let tampered = String::from_utf8(tampered).unwrap();
+        assert_eq!(key.open(&tampered), None);
+        assert_eq!(key.open("zz"), None);
+    }
+}
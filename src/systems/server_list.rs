@@ -0,0 +1,72 @@
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::config::*;
+
+/// A single named realm the player can connect to.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RealmEntry {
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    pub tls_port: u16,
+    #[serde(default)]
+    pub server_name: Option<String>,
+}
+
+impl RealmEntry {
+    pub fn endpoint(&self, trust: &TrustSettings) -> ServerEndpoint {
+        ServerEndpoint {
+            host: self.host.clone(),
+            port: self.port,
+            tls_port: self.tls_port,
+            server_name: self
+                .server_name
+                .clone()
+                .unwrap_or_else(|| self.host.clone()),
+            trust: trust.clone(),
+        }
+    }
+
+    /// Stores this realm as the selected one within the Config. It replaces
+    /// any endpoint given on the command line, since the player chose it.
+    pub fn apply_to(&self, config: &mut Config) {
+        config.launch.clear_endpoint();
+        config.realm = Some(self.name.clone());
+        config.host = Some(self.host.clone());
+        config.port = Some(self.port);
+        config.tls_port = Some(self.tls_port);
+        config.server_name.clone_from(&self.server_name);
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ServerList {
+    #[serde(default)]
+    pub server: Vec<RealmEntry>,
+}
+
+impl ServerList {
+    /// Reads the realm list. A missing or broken file gives an empty list so
+    /// the client falls back to the endpoint within settings.toml.
+    pub fn read_list(path: &str) -> Self {
+        match fs::read_to_string(path) {
+            Ok(data) => match toml::from_str(&data) {
+                Ok(list) => list,
+                Err(e) => {
+                    error!("Failed to parse {}: {}", path, e);
+                    ServerList::default()
+                }
+            },
+            Err(e) => {
+                warn!("No server list loaded from {}: {}", path, e);
+                ServerList::default()
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.server.is_empty()
+    }
+}
@@ -0,0 +1,235 @@
+use std::time::Duration;
+
+use crate::{
+    ClientPresentMode, Config, MyInstant, SystemHolder, apply_present_mode,
+};
+
+pub const SETTINGS_FILE: &str = "settings.toml";
+
+/// How long settings.toml waits after the last change before it is written,
+/// so dragging a volume slider does not rewrite the file every frame.
+pub const SETTINGS_SAVE_DELAY: Duration = Duration::from_millis(750);
+
+pub const UI_SCALE_MIN: f32 = 0.75;
+pub const UI_SCALE_MAX: f32 = 1.5;
+pub const UI_SCALE_STEP: f32 = 0.25;
+
+/// The UI scale after `scale`, wrapping back to UI_SCALE_MIN past the
+/// largest step.
+pub fn next_ui_scale(scale: f32) -> f32 {
+    let next = scale + UI_SCALE_STEP;
+
+    if next > UI_SCALE_MAX + f32::EPSILON {
+        UI_SCALE_MIN
+    } else {
+        next
+    }
+}
+
+/// One option of the Setting window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SettingChange {
+    BgmVolume(u8),
+    SfxVolume(u8),
+    ShowFps(bool),
+    ShowPing(bool),
+    ShowAveragePing(bool),
+    ShowFrameLoop(bool),
+    PresentMode(ClientPresentMode),
+    UiScale(f32),
+}
+
+impl SettingChange {
+    /// Every option of the Setting window with the value it has in `config`.
+    pub fn all_from(config: &Config) -> [SettingChange; 8] {
+        [
+            SettingChange::BgmVolume(config.bgm_volume),
+            SettingChange::SfxVolume(config.sfx_volume),
+            SettingChange::ShowFps(config.show_fps),
+            SettingChange::ShowPing(config.show_ping),
+            SettingChange::ShowAveragePing(config.show_average_ping),
+            SettingChange::ShowFrameLoop(config.show_frame_loop),
+            SettingChange::PresentMode(config.present_mode),
+            SettingChange::UiScale(config.ui_scale),
+        ]
+    }
+
+    /// Writes the value into `config`, clamped to its valid range. Returns
+    /// false when the config already held it.
+    pub fn write(self, config: &mut Config) -> bool {
+        fn set<T: PartialEq>(field: &mut T, value: T) -> bool {
+            let changed = *field != value;
+            *field = value;
+            changed
+        }
+
+        match self {
+            SettingChange::BgmVolume(volume) => {
+                set(&mut config.bgm_volume, volume.min(100))
+            }
+            SettingChange::SfxVolume(volume) => {
+                set(&mut config.sfx_volume, volume.min(100))
+            }
+            SettingChange::ShowFps(show) => set(&mut config.show_fps, show),
+            SettingChange::ShowPing(show) => set(&mut config.show_ping, show),
+            SettingChange::ShowAveragePing(show) => {
+                set(&mut config.show_average_ping, show)
+            }
+            SettingChange::ShowFrameLoop(show) => {
+                set(&mut config.show_frame_loop, show)
+            }
+            SettingChange::PresentMode(mode) => {
+                set(&mut config.present_mode, mode)
+            }
+            SettingChange::UiScale(scale) => set(
+                &mut config.ui_scale,
+                scale.clamp(UI_SCALE_MIN, UI_SCALE_MAX),
+            ),
+        }
+    }
+}
+
+/// Tracks unsaved settings. Every change from the Setting window goes
+/// through `apply_setting`, and the runner calls `flush_settings` each frame.
+#[derive(Debug, Default)]
+pub struct SettingsService {
+    save_at: Option<MyInstant>,
+    layout_changed: bool,
+}
+
+impl SettingsService {
+    pub fn new() -> Self {
+        SettingsService::default()
+    }
+
+    /// Pushes the save back to SETTINGS_SAVE_DELAY after `now`.
+    pub fn mark_dirty(&mut self, now: MyInstant) {
+        self.save_at = Some(now + SETTINGS_SAVE_DELAY);
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.save_at.is_some()
+    }
+
+    pub fn is_due(&self, now: MyInstant) -> bool {
+        self.save_at.is_some_and(|save_at| save_at <= now)
+    }
+
+    /// True after the UI scale changed until `layout_rebuilt` is called.
+    /// The old layout keeps the old scale until then, so its buttons still
+    /// line up with the mouse.
+    pub fn layout_changed(&self) -> bool {
+        self.layout_changed
+    }
+
+    pub fn layout_rebuilt(&mut self) {
+        self.layout_changed = false;
+    }
+}
+
+/// Stores the change and applies it. Overlay labels owned by the interface
+/// are updated by `Interface::change_setting`, and a new UI scale is laid
+/// out by `Content::update_layout` once the input that changed it is done.
+/// Returns false when nothing changed.
+pub fn apply_setting(
+    systems: &mut SystemHolder,
+    change: SettingChange,
+) -> bool {
+    if !change.write(&mut systems.config) {
+        return false;
+    }
+
+    match change {
+        SettingChange::BgmVolume(_) => systems
+            .audio
+            .set_music_volume(systems.config.bgm_volume as f32 * 0.01),
+        SettingChange::SfxVolume(_) => systems
+            .audio
+            .set_effect_volume(systems.config.sfx_volume as f32 * 0.01),
+        SettingChange::ShowFps(show) => {
+            systems.gfx.set_visible(&systems.fps, show)
+        }
+        SettingChange::PresentMode(_) => apply_present_mode(systems),
+        SettingChange::UiScale(_) => systems.settings.layout_changed = true,
+        _ => {}
+    }
+
+    systems.settings.mark_dirty(MyInstant::now());
+    true
+}
+
+/// Puts every option of the Setting window back to its default. Returns the
+/// options that changed.
+pub fn reset_settings(systems: &mut SystemHolder) -> Vec<SettingChange> {
+    SettingChange::all_from(&Config::default())
+        .into_iter()
+        .filter(|change| apply_setting(systems, *change))
+        .collect()
+}
+
+/// Writes settings.toml once the save delay has passed, or right away when
+/// `force` is set, such as on exit.
+pub fn flush_settings(systems: &mut SystemHolder, force: bool) {
+    let settings = &mut systems.settings;
+
+    if !settings.is_dirty() || !(force || settings.is_due(MyInstant::now())) {
+        return;
+    }
+
+    settings.save_at = None;
+    systems.config.save_config(SETTINGS_FILE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_waits_for_the_last_change() {
+        let mut settings = SettingsService::new();
+        let start = MyInstant::now();
+        assert!(!settings.is_due(start + SETTINGS_SAVE_DELAY));
+
+        settings.mark_dirty(start);
+        assert!(!settings.is_due(start));
+
+        let later = start + SETTINGS_SAVE_DELAY / 2;
+        settings.mark_dirty(later);
+        assert!(!settings.is_due(start + SETTINGS_SAVE_DELAY));
+        assert!(settings.is_due(later + SETTINGS_SAVE_DELAY));
+    }
+
+    #[test]
+    fn changes_are_clamped_and_reset_to_defaults() {
+        let mut config = Config::default();
+
+        assert!(SettingChange::BgmVolume(250).write(&mut config));
+        assert_eq!(config.bgm_volume, 100);
+        assert!(!SettingChange::BgmVolume(100).write(&mut config));
+
+        assert!(SettingChange::UiScale(9.0).write(&mut config));
+        assert_eq!(config.ui_scale, UI_SCALE_MAX);
+        assert_eq!(next_ui_scale(config.ui_scale), UI_SCALE_MIN);
+        assert_eq!(next_ui_scale(1.0), 1.25);
+
+        assert!(SettingChange::ShowPing(true).write(&mut config));
+        assert!(
+            SettingChange::PresentMode(ClientPresentMode::Immediate)
+                .write(&mut config)
+        );
+
+        let changed: Vec<_> = SettingChange::all_from(&Config::default())
+            .into_iter()
+            .filter(|change| change.write(&mut config))
+            .collect();
+
+        assert_eq!(changed.len(), 4);
+        assert!(changed.contains(&SettingChange::PresentMode(
+            ClientPresentMode::AutoVsync
+        )));
+        assert_eq!(
+            SettingChange::all_from(&config),
+            SettingChange::all_from(&Config::default())
+        );
+    }
+}
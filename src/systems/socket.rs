@@ -1,6 +1,6 @@
 use crate::{
-    Alert, BufferTask, ClientError, Content, Result, SERVER_ID, SERVER_PORT,
-    SystemHolder, World, config::*,
+    Alert, BufferTask, ClientError, Content, MyInstant, Result, SystemHolder,
+    World, config::*,
 };
 pub use bytey::{ByteBuffer, ByteBufferError, ByteBufferRead, ByteBufferWrite};
 
@@ -13,6 +13,7 @@ pub use mmap_bytey::{
 use notls_socket::Socket;
 use pki_types::ServerName;
 use serde::{Deserialize, Serialize};
+use snafu::Backtrace;
 use states::ClientState;
 use std::io::ErrorKind;
 use std::os;
@@ -33,95 +34,323 @@ pub use handledata::*;
 pub use sends::*;
 
 pub mod bufer_ext;
+pub mod content_sync;
+pub mod frame;
+pub mod loopback;
+pub mod metrics;
 pub mod notls_socket;
+pub mod probe;
+pub mod protocol;
+pub mod reconnect;
+pub mod send_queue;
+pub mod session;
 pub mod states;
 pub mod tls_socket;
+pub mod transport;
+pub mod trust;
+
+pub use content_sync::*;
+pub use frame::*;
+pub use metrics::*;
+pub use protocol::*;
+pub use reconnect::*;
+pub use send_queue::*;
+pub use session::*;
+pub use transport::*;
+pub use trust::*;
 
 pub struct Poller {
-    pub tls_socket: TlsSocket,
-    pub socket: Socket,
+    pub channels: Vec<Box<dyn Transport>>,
     pub poll: mio::Poll,
+    pub endpoint: Option<ServerEndpoint>,
+    /// Optional packets the server enabled for this connection.
+    pub capabilities: Capabilities,
+    pub content_sync: ContentSync,
+    pub reconnector: Reconnector,
+    pub compression: CompressionStats,
+    pub metrics: NetMetrics,
+    pub send_queue: SendQueue,
+    pub recorder: Option<SessionRecorder>,
+    pub replay: Option<SessionReplay>,
 }
 
 impl Poller {
+    /// Creates an unconnected Poller. Call `connect` once a realm is known.
     pub fn new() -> Result<Self> {
-        let poll = Poll::new()?;
-        let tls_socket = TlsSocket::new(&poll)?;
-        let socket = Socket::new(&poll)?;
-
         Ok(Poller {
-            tls_socket,
-            socket,
-            poll,
+            channels: Vec::with_capacity(2),
+            poll: Poll::new()?,
+            endpoint: None,
+            capabilities: Capabilities::NONE,
+            content_sync: ContentSync::default(),
+            reconnector: Reconnector::default(),
+            compression: CompressionStats::default(),
+            metrics: NetMetrics::default(),
+            send_queue: SendQueue::default(),
+            recorder: None,
+            replay: None,
         })
     }
 
-    pub fn reconnect(&mut self, is_tls: bool) -> Result<()> {
-        if is_tls {
-            self.tls_socket.reconnect(&self.poll)
-        } else {
-            self.socket.reconnect(&self.poll)
+    /// Builds a Poller that plays a recorded session back instead of
+    /// talking to a server. Sends go to loopback channels and are dropped.
+    pub fn replay(path: &str) -> Result<Self> {
+        let (mut poller, _, _) = Poller::loopback()?;
+
+        info!("Replaying session {}", path);
+        poller.replay = Some(SessionReplay::load(path)?);
+
+        Ok(poller)
+    }
+
+    /// Starts writing every inbound frame to a session file.
+    pub fn start_recording(&mut self, path: &str) -> Result<()> {
+        info!("Recording session to {}", path);
+        self.recorder = Some(SessionRecorder::create(path)?);
+
+        Ok(())
+    }
+
+    /// Connects the TLS and plain channels to the endpoint resolved from the
+    /// Config, dropping any existing connection first.
+    pub fn connect(&mut self, config: &Config) -> Result<()> {
+        self.disconnect();
+
+        let endpoint = config.server_endpoint();
+
+        info!(
+            "Connecting to {} (port {}, tls port {}, server name {})",
+            endpoint.host,
+            endpoint.port,
+            endpoint.tls_port,
+            endpoint.server_name
+        );
+
+        let tls_socket = TlsSocket::new(&self.poll, &endpoint, CHANNEL_TLS)?;
+        self.add_channel(Box::new(tls_socket));
+
+        let socket = Socket::new(&self.poll, &endpoint, CHANNEL_PLAIN)?;
+        self.add_channel(Box::new(socket));
+
+        self.endpoint = Some(endpoint);
+
+        Ok(())
+    }
+
+    /// Adds a transport and returns its channel id. Transports that register
+    /// with mio must use the returned id as their Token.
+    pub fn add_channel(&mut self, transport: Box<dyn Transport>) -> ChannelId {
+        self.channels.push(transport);
+        self.channels.len() - 1
+    }
+
+    /// Closes every channel and forgets the endpoint.
+    pub fn disconnect(&mut self) {
+        if self.compression.total() > 0 {
+            info!(
+                "Compression saved {} bytes in, {} bytes out",
+                self.compression.saved_in, self.compression.saved_out
+            );
+        }
+
+        for mut channel in self.channels.drain(..) {
+            if channel.state() != ClientState::Closed {
+                let _ = channel.shutdown(&self.poll);
+            }
         }
+
+        self.endpoint = None;
+        self.capabilities = Capabilities::NONE;
+        self.content_sync.reset();
+        self.reconnector.reset();
+    }
+
+    #[inline]
+    pub fn is_connected(&self) -> bool {
+        !self.channels.is_empty()
+    }
+
+    /// Returns true when the channel exists and its connection was lost.
+    pub fn is_closed(&self, channel: ChannelId) -> bool {
+        self.channels
+            .get(channel)
+            .is_some_and(|transport| transport.state() == ClientState::Closed)
+    }
+
+    /// Returns why a channel refused the server certificate, if one did.
+    pub fn take_trust_error(&mut self) -> Option<&'static str> {
+        self.channels
+            .iter_mut()
+            .find_map(|transport| transport.take_trust_error())
+    }
+
+    pub fn clear_sends(&mut self, channel: ChannelId) {
+        self.send_queue.clear_channel(channel);
+
+        if let Some(transport) = self.channels.get_mut(channel) {
+            transport.clear_sends();
+        }
+    }
+
+    pub fn take_sends(&mut self, channel: ChannelId) -> VecDeque<MByteBuffer> {
+        match self.channels.get_mut(channel) {
+            Some(transport) => transport.take_sends(),
+            None => VecDeque::new(),
+        }
+    }
+
+    /// Moves everything still waiting to go out on a lost channel into the
+    /// reconnect hold, oldest first, so it is sent once the channel is back.
+    pub fn hold_sends(&mut self, channel: ChannelId) {
+        for buf in self.take_sends(channel) {
+            self.reconnector.hold(channel, buf, true);
+        }
+
+        for buf in self.send_queue.take_channel(channel) {
+            self.reconnector.hold(channel, buf, false);
+        }
+    }
+
+    pub fn reconnect(&mut self, channel: ChannelId) -> Result<()> {
+        let endpoint = match &self.endpoint {
+            Some(endpoint) => endpoint,
+            None => return Ok(()),
+        };
+
+        if let Some(transport) = self.channels.get_mut(channel) {
+            transport.reconnect(&self.poll, endpoint)?;
+        }
+
+        Ok(())
     }
 
     #[inline]
     #[allow(dead_code)]
     /// this should properly Close the socket.
-    pub fn set_to_closing(&mut self, is_tls: bool) {
-        if is_tls {
-            self.tls_socket.set_to_closing(&self.poll);
-        } else {
-            self.socket.set_to_closing(&self.poll);
+    pub fn set_to_closing(&mut self, channel: ChannelId) {
+        if let Some(transport) = self.channels.get_mut(channel) {
+            transport.set_to_closing(&self.poll);
         }
     }
 
-    pub fn shutdown(&mut self, is_tls: bool) -> Result<()> {
-        if is_tls {
-            self.tls_socket.shutdown(&self.poll)
-        } else {
-            self.socket.shutdown(&self.poll)
+    pub fn shutdown(&mut self, channel: ChannelId) -> Result<()> {
+        if let Some(transport) = self.channels.get_mut(channel) {
+            transport.shutdown(&self.poll)?;
         }
+
+        Ok(())
     }
 
-    fn process(
+    /// Queues a packet for the next flush_sends, or holds it while a
+    /// reconnect is in progress so it can go out once the session resumed.
+    #[inline]
+    #[allow(dead_code)]
+    pub fn send(
         &mut self,
-        event: &mio::event::Event,
-        is_tls: bool,
+        mut buf: MByteBuffer,
+        channel: ChannelId,
     ) -> Result<()> {
-        if is_tls {
-            self.tls_socket.process(event, &self.poll)
-        } else {
-            self.socket.process(event, &self.poll)
+        if channel >= self.channels.len() {
+            return Err(ClientError::InvalidSocket {
+                backtrace: Backtrace::new(),
+            });
         }
+
+        if self.reconnector.is_reconnecting() {
+            self.reconnector.hold(channel, buf, false);
+            return Ok(());
+        }
+
+        let id = packet_id(&mut buf)?;
+
+        self.send_queue.push(channel, id, buf);
+        Ok(())
     }
 
-    #[inline]
-    #[allow(dead_code)]
-    pub fn send(&mut self, buf: MByteBuffer, is_tls: bool) -> Result<()> {
-        if is_tls {
-            self.tls_socket.send(buf, &self.poll)
-        } else {
-            self.socket.send(buf, &self.poll)
+    /// Hands every queued packet that passed its rate limit to the
+    /// transports. Packets for a lost channel are held for the reconnect
+    /// instead. Called once per frame.
+    pub fn flush_sends(&mut self) -> Result<()> {
+        if self.send_queue.is_empty() {
+            return Ok(());
+        }
+
+        for channel in 0..self.channels.len() {
+            if self.is_closed(channel) {
+                self.hold_sends(channel);
+            }
+        }
+
+        for queued in self.send_queue.take_ready() {
+            self.send_direct(queued.buf, queued.channel)?;
+        }
+
+        Ok(())
+    }
+
+    /// Sends a packet right away, skipping the queue even while
+    /// reconnecting. Used for the packets that resume the session.
+    pub fn send_direct(
+        &mut self,
+        mut buf: MByteBuffer,
+        channel: ChannelId,
+    ) -> Result<()> {
+        let id = packet_id(&mut buf)?;
+
+        if self.capabilities.contains(Capabilities::COMPRESSION) {
+            let length = buf.length();
+
+            if let Some(compressed) = compress_frame(&mut buf)? {
+                self.compression.saved_out +=
+                    (length - compressed.length()) as u64;
+                buf = compressed;
+            }
+        }
+
+        if let Some(id) = id {
+            self.metrics.record_out(id, buf.length());
+        }
+
+        match self.channels.get_mut(channel) {
+            Some(transport) => transport.send(buf, &self.poll),
+            None => Err(ClientError::InvalidSocket {
+                backtrace: Backtrace::new(),
+            }),
+        }
+    }
+
+    /// Writes a frame send_direct already compressed and counted again,
+    /// skipping the queue, compression and metrics. A compressed frame is
+    /// expanded when the resumed session did not enable compression.
+    fn resend_frame(
+        &mut self,
+        mut buf: MByteBuffer,
+        channel: ChannelId,
+    ) -> Result<()> {
+        if !self.capabilities.contains(Capabilities::COMPRESSION) {
+            buf = expand_frame(buf)?;
+        }
+
+        match self.channels.get_mut(channel) {
+            Some(transport) => transport.send(buf, &self.poll),
+            None => Err(ClientError::InvalidSocket {
+                backtrace: Backtrace::new(),
+            }),
         }
     }
 
-    /// Returns (is_tls, is_dead). Will return is_tls false if both succeed.
     pub fn poll_events(&mut self) -> Result<()> {
         let mut events = Events::with_capacity(32);
         self.poll.poll(&mut events, Some(Duration::new(0, 0)))?;
 
         for event in events.into_iter() {
-            if event.token() == Token(0) {
-                self.tls_socket.process(event, &self.poll)?;
+            let channel = event.token().0;
 
-                if let ClientState::Closed = self.tls_socket.state {
-                    warn!("Disconnected TLS");
-                }
-            } else {
-                self.socket.process(event, &self.poll)?;
+            if let Some(transport) = self.channels.get_mut(channel) {
+                transport.process(event, &self.poll)?;
 
-                if let ClientState::Closed = self.socket.state {
-                    warn!("Disconnected");
+                if let ClientState::Closed = transport.state() {
+                    warn!("Disconnected channel {}", channel);
                 }
             }
         }
@@ -141,139 +370,152 @@ impl Poller {
     ) -> Result<()> {
         let mut packet = MByteBuffer::new()?;
 
-        //check TLS first
-        loop {
-            packet.move_cursor_to_start();
-            let length = match self.tls_socket.get_length(&self.poll) {
-                Some(n) => n,
-                None => break,
-            };
-
-            if length == 0 {
-                log::error!("Length was Zero. Bad or malformed packet.");
-                self.tls_socket.set_to_closing(&self.poll);
-                break;
-            }
-
-            if length
-                <= (self.tls_socket.buffer.length()
-                    - self.tls_socket.buffer.cursor()) as u64
-            {
-                let mut errored = false;
-
-                if let Ok(bytes) =
-                    self.tls_socket.buffer.read_slice(length as usize)
-                {
-                    if packet.write_slice(bytes).is_err() {
-                        errored = true;
-                    }
-
-                    packet.move_cursor_to_start();
-                } else {
-                    errored = true;
-                }
-
-                if errored {
-                    log::error!("Disconnected on packet read to buffer");
-                    self.tls_socket.set_to_closing(&self.poll);
-                    break;
-                }
+        if let Some(mut replay) = self.replay.take() {
+            while let Some(frame) = replay.next_due() {
+                packet.move_cursor_to_start();
+                packet.write_slice(&frame.data)?;
+                packet.move_cursor_to_start();
 
-                if handle_data(
-                    self,
+                if let Err(e) = self.handle_frame(
                     router,
                     world,
                     systems,
                     content,
                     alert,
                     &mut packet,
+                    frame.data.len() + FRAME_HEADER_SIZE,
                     seconds,
                     buffertask,
-                )
-                .is_err()
-                {
-                    log::error!("Disconnected on handle_data");
-                    self.tls_socket.set_to_closing(&self.poll);
-                    break;
+                ) {
+                    log::error!(
+                        "Replay frame at {}ms on channel {} failed: {}",
+                        frame.time,
+                        frame.channel,
+                        e
+                    );
                 }
+            }
+
+            if replay.is_finished() {
+                info!("Session replay finished");
             } else {
-                self.tls_socket
-                    .buffer
-                    .move_cursor(self.tls_socket.buffer.cursor() - 8)?;
-                break;
+                self.replay = Some(replay);
             }
-        }
 
-        if self.tls_socket.buffer.cursor() == self.tls_socket.buffer.length() {
-            self.tls_socket.buffer.truncate(0)?;
+            return Ok(());
         }
 
-        loop {
-            packet.move_cursor_to_start();
-            let length = match self.socket.get_length(&self.poll) {
-                Some(n) => n,
-                None => break,
-            };
-
-            if length == 0 {
-                log::error!("Length was Zero. Bad or malformed packet.");
-                self.socket.set_to_closing(&self.poll);
-                break;
-            }
-
-            if length
-                <= (self.socket.buffer.length() - self.socket.buffer.cursor())
-                    as u64
-            {
-                let mut errored = false;
+        // Channels are handled in order so TLS packets are handled first.
+        for channel in 0..self.channels.len() {
+            loop {
+                let (status, read) = match self.channels.get_mut(channel) {
+                    Some(transport) => {
+                        let buffer = transport.buffer();
+                        let start = buffer.cursor();
+                        let status = decode_frame(buffer, &mut packet)?;
 
-                if let Ok(bytes) =
-                    self.socket.buffer.read_slice(length as usize)
-                {
-                    if packet.write_slice(bytes).is_err() {
-                        errored = true;
+                        (status, buffer.cursor() - start)
                     }
+                    None => break,
+                };
 
-                    packet.move_cursor_to_start();
-                } else {
-                    errored = true;
-                }
+                match status {
+                    FrameStatus::Ready(length) => {
+                        let wire = read - FRAME_HEADER_SIZE;
 
-                if errored {
-                    log::error!("Disconnected on packet read to buffer");
-                    self.socket.set_to_closing(&self.poll);
-                    break;
+                        if length > wire {
+                            self.compression.saved_in += (length - wire) as u64;
+                        }
+
+                        let frame = &packet.as_slice()[..length];
+
+                        if let Some(recorder) = &mut self.recorder {
+                            if let Err(e) = recorder.record(channel, frame) {
+                                log::error!("Stopped recording: {:?}", e);
+                                self.recorder = None;
+                            }
+                        }
+                    }
+                    FrameStatus::Incomplete => break,
+                    FrameStatus::Malformed => {
+                        self.set_to_closing(channel);
+                        break;
+                    }
                 }
 
-                if handle_data(
-                    self,
-                    router,
-                    world,
-                    systems,
-                    content,
-                    alert,
-                    &mut packet,
-                    seconds,
-                    buffertask,
-                )
-                .is_err()
+                if self
+                    .handle_frame(
+                        router,
+                        world,
+                        systems,
+                        content,
+                        alert,
+                        &mut packet,
+                        read,
+                        seconds,
+                        buffertask,
+                    )
+                    .is_err()
                 {
                     log::error!("Disconnected on handle_data");
-                    self.socket.set_to_closing(&self.poll);
+                    self.set_to_closing(channel);
                     break;
                 }
-            } else {
-                self.socket
-                    .buffer
-                    .move_cursor(self.socket.buffer.cursor() - 8)?;
-                break;
+            }
+
+            if let Some(transport) = self.channels.get_mut(channel) {
+                compact_buffer(transport.buffer())?;
             }
         }
 
-        if self.socket.buffer.cursor() == self.socket.buffer.length() {
-            self.socket.buffer.truncate(0)?;
+        // Flushed every frame so a crash loses at most this frame.
+        if let Some(recorder) = &mut self.recorder
+            && let Err(e) = recorder.flush()
+        {
+            log::error!("Stopped recording: {:?}", e);
+            self.recorder = None;
         }
 
         Ok(())
     }
+
+    /// Runs handle_data on a decoded frame and records its size and
+    /// handler time.
+    #[allow(clippy::too_many_arguments)]
+    fn handle_frame(
+        &mut self,
+        router: &PacketRouter,
+        world: &mut World,
+        systems: &mut SystemHolder,
+        content: &mut Content,
+        alert: &mut Alert,
+        packet: &mut MByteBuffer,
+        bytes: usize,
+        seconds: f32,
+        buffertask: &mut BufferTask,
+    ) -> Result<()> {
+        let id = packet.read::<ServerPackets>();
+        packet.move_cursor_to_start();
+
+        let start = MyInstant::now();
+        let result = handle_data(
+            self, router, world, systems, content, alert, packet, seconds,
+            buffertask,
+        );
+
+        if let Ok(id) = id {
+            self.metrics.record_in(id, bytes, start.elapsed());
+        }
+
+        result
+    }
+}
+
+/// Reads the ClientPacket id of a finished packet without moving its cursor.
+fn packet_id(buf: &mut MByteBuffer) -> Result<Option<ClientPacket>> {
+    buf.move_cursor(FRAME_HEADER_SIZE)?;
+    let id = buf.read::<ClientPacket>().ok();
+    buf.move_cursor(0)?;
+
+    Ok(id)
 }
@@ -1,6 +1,5 @@
 use crate::{
-    Alert, BufferTask, ClientError, Content, Result, SERVER_ID, SERVER_PORT,
-    SystemHolder, config::*,
+    Alert, BufferTask, ClientError, Content, Result, SystemHolder, config::*,
 };
 pub use bytey::{ByteBuffer, ByteBufferError, ByteBufferRead, ByteBufferWrite};
 pub use mmap_bytey::{
@@ -0,0 +1,308 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{MyInstant, is_data_path, sha256_hex};
+
+/// Paths asked for in one ClientPacket::RequestContent, to keep the
+/// packet under MAX_FRAME_LENGTH.
+pub const CONTENT_REQUEST_BATCH: usize = 64;
+/// How long the server may go quiet during a check or download before the
+/// client gives up on it.
+pub const CONTENT_SYNC_TIMEOUT_MS: u128 = 15000;
+
+/// One file in the server's content manifest.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContentEntry {
+    pub sha256: String,
+    pub size: u32,
+}
+
+/// How far the download has come, for the progress screen.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SyncProgress {
+    pub files_done: usize,
+    pub files_total: usize,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+}
+
+/// Brings the local data files in line with the server's. The server answers
+/// the content hash from the handshake with ServerPackets::ContentCurrent
+/// when it matches, or else sends its manifest in pages. The client asks
+/// for the files that differ and the server sends each one in chunks.
+#[derive(Debug, Default)]
+pub struct ContentSync {
+    manifest: BTreeMap<String, ContentEntry>,
+    manifest_total: usize,
+    wanted: BTreeMap<String, ContentEntry>,
+    partial: HashMap<String, Vec<u8>>,
+    progress: SyncProgress,
+    failed: Vec<String>,
+    /// Paths written to the patch folder or removed since the check began.
+    changed: Vec<String>,
+    active: bool,
+    last_heard: Option<MyInstant>,
+}
+
+impl ContentSync {
+    /// True from the handshake until the server said the content is
+    /// current or the last file arrived. Logging in waits for this.
+    pub fn is_busy(&self) -> bool {
+        self.active
+    }
+
+    /// Waits for the server's answer to the content hash that was just
+    /// sent.
+    pub fn check(&mut self) {
+        self.reset();
+        self.active = true;
+        self.last_heard = Some(MyInstant::now());
+    }
+
+    /// True once the server sent nothing for the check or download in
+    /// CONTENT_SYNC_TIMEOUT_MS.
+    pub fn timed_out(&self) -> bool {
+        self.active
+            && self.last_heard.is_some_and(|at| {
+                at.elapsed().as_millis() > CONTENT_SYNC_TIMEOUT_MS
+            })
+    }
+
+    pub fn progress(&self) -> Option<SyncProgress> {
+        self.active.then_some(self.progress)
+    }
+
+    /// Adds a page of the server's manifest. Returns the whole manifest
+    /// once `total` entries arrived.
+    pub fn add_manifest(
+        &mut self,
+        total: usize,
+        entries: Vec<(String, ContentEntry)>,
+    ) -> Option<BTreeMap<String, ContentEntry>> {
+        self.active = true;
+        self.last_heard = Some(MyInstant::now());
+        self.manifest_total = total;
+        self.manifest.extend(entries);
+
+        if self.manifest.len() < self.manifest_total {
+            return None;
+        }
+
+        Some(std::mem::take(&mut self.manifest))
+    }
+
+    /// Starts downloading the entries that differ from the local files and
+    /// returns their paths to request. Paths that are not data files are
+    /// dropped so the server can not write outside the data folder. With
+    /// nothing to download, take_finished ends the sync right away.
+    pub fn start(
+        &mut self,
+        wanted: BTreeMap<String, ContentEntry>,
+    ) -> Vec<String> {
+        self.partial.clear();
+        self.failed.clear();
+        self.wanted = wanted
+            .into_iter()
+            .filter(|(path, _)| is_data_path(path))
+            .collect();
+        self.progress = SyncProgress {
+            files_total: self.wanted.len(),
+            bytes_total: self.wanted.values().map(|e| e.size as u64).sum(),
+            ..Default::default()
+        };
+        self.active = true;
+
+        self.wanted.keys().cloned().collect()
+    }
+
+    /// Adds a chunk of a file. Returns the file once its last chunk arrived
+    /// and it matched the checksum from the manifest.
+    pub fn add_chunk(
+        &mut self,
+        path: &str,
+        offset: u32,
+        bytes: &[u8],
+    ) -> Result<Option<Vec<u8>>, String> {
+        let Some(entry) = self.wanted.get(path) else {
+            return Err(format!("{path} was not requested"));
+        };
+        let (size, expected) = (entry.size as usize, entry.sha256.clone());
+        self.last_heard = Some(MyInstant::now());
+        let data = self.partial.entry(path.to_string()).or_default();
+
+        if offset as usize != data.len() || data.len() + bytes.len() > size {
+            self.fail(path);
+            return Err(format!("{path} chunk at {offset} is out of order"));
+        }
+
+        data.extend_from_slice(bytes);
+        self.progress.bytes_done += bytes.len() as u64;
+
+        if data.len() < size {
+            return Ok(None);
+        }
+
+        let data = self.partial.remove(path).unwrap_or_default();
+        let found = sha256_hex(&data);
+
+        if found != expected {
+            self.fail(path);
+            return Err(format!(
+                "{path} checksum {found} does not match {expected}"
+            ));
+        }
+
+        self.finish_file(path);
+        Ok(Some(data))
+    }
+
+    /// Gives up on a file, the rest of the download carries on.
+    pub fn fail(&mut self, path: &str) {
+        if let Some(entry) = self.wanted.get(path) {
+            let received =
+                self.partial.remove(path).map_or(0, |data| data.len());
+
+            self.progress.bytes_done +=
+                (entry.size as u64).saturating_sub(received as u64);
+            self.failed.push(path.to_string());
+            self.finish_file(path);
+        }
+    }
+
+    fn finish_file(&mut self, path: &str) {
+        self.wanted.remove(path);
+        self.progress.files_done += 1;
+    }
+
+    /// Once every file arrived or failed, ends the download and returns
+    /// the paths that failed.
+    pub fn take_finished(&mut self) -> Option<Vec<String>> {
+        if !self.active || !self.wanted.is_empty() {
+            return None;
+        }
+
+        self.active = false;
+        Some(std::mem::take(&mut self.failed))
+    }
+
+    /// Notes a data file the sync wrote or removed, so whatever was built
+    /// from it can be loaded again once the sync ends.
+    pub fn mark_changed(&mut self, path: &str) {
+        self.changed.push(path.to_string());
+    }
+
+    pub fn take_changed(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.changed)
+    }
+
+    /// Drops a check or download that was cut off by a lost channel or a
+    /// disconnect.
+    pub fn reset(&mut self) {
+        *self = ContentSync::default();
+    }
+}
+
+/// The server entries whose checksum differs from `local` or that are
+/// missing locally.
+pub fn changed_entries(
+    server: BTreeMap<String, ContentEntry>,
+    local: &BTreeMap<String, String>,
+) -> BTreeMap<String, ContentEntry> {
+    server
+        .into_iter()
+        .filter(|(path, entry)| local.get(path) != Some(&entry.sha256))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(bytes: &[u8]) -> ContentEntry {
+        ContentEntry {
+            sha256: sha256_hex(bytes),
+            size: bytes.len() as u32,
+        }
+    }
+
+    #[test]
+    fn changed_files_are_downloaded_in_chunks_and_checked() {
+        let mut sync = ContentSync::default();
+        let local = BTreeMap::from([
+            ("items/0.bin".to_string(), sha256_hex(b"sword")),
+            ("items/1.bin".to_string(), sha256_hex(b"old shield")),
+        ]);
+
+        assert!(
+            sync.add_manifest(
+                4,
+                vec![
+                    ("items/0.bin".into(), entry(b"sword")),
+                    ("items/1.bin".into(), entry(b"shield")),
+                ],
+            )
+            .is_none()
+        );
+        assert!(sync.is_busy());
+
+        let server = sync
+            .add_manifest(
+                4,
+                vec![
+                    ("shops/2.bin".into(), entry(b"general store")),
+                    ("../settings.toml".into(), entry(b"evil")),
+                ],
+            )
+            .unwrap();
+        let paths = sync.start(changed_entries(server, &local));
+        assert_eq!(paths, vec!["items/1.bin", "shops/2.bin"]);
+        assert_eq!(sync.progress().unwrap().bytes_total, 19);
+
+        assert_eq!(sync.add_chunk("items/1.bin", 0, b"shi"), Ok(None));
+        assert!(sync.add_chunk("items/1.bin", 0, b"eld").is_err());
+        assert!(sync.add_chunk("items/0.bin", 0, b"sword").is_err());
+        assert_eq!(sync.take_finished(), None);
+
+        assert_eq!(
+            sync.add_chunk("shops/2.bin", 0, b"general store"),
+            Ok(Some(b"general store".to_vec()))
+        );
+
+        sync.mark_changed("shops/2.bin");
+
+        let progress = sync.progress().unwrap();
+        assert_eq!((progress.files_done, progress.bytes_done), (2, 19));
+        assert_eq!(sync.take_finished(), Some(vec!["items/1.bin".into()]));
+        assert_eq!(sync.take_changed(), vec!["shops/2.bin"]);
+        assert!(!sync.is_busy());
+
+        // A new download does not inherit the last one's leftovers.
+        sync.mark_changed("maps/0_0_0.bin");
+        sync.check();
+        assert!(sync.is_busy());
+        assert!(sync.take_changed().is_empty());
+        sync.failed.push("items/1.bin".into());
+        sync.partial.insert("items/1.bin".into(), b"shi".to_vec());
+        assert!(sync.start(BTreeMap::new()).is_empty());
+        assert_eq!(sync.take_finished(), Some(vec![]));
+        assert!(sync.partial.is_empty());
+        assert!(!sync.is_busy());
+    }
+
+    #[test]
+    fn quiet_server_times_out_the_check() {
+        let mut sync = ContentSync::default();
+        assert!(!sync.timed_out());
+
+        sync.check();
+        assert!(!sync.timed_out());
+
+        let quiet = std::time::Duration::from_millis(
+            CONTENT_SYNC_TIMEOUT_MS as u64 + 1000,
+        );
+        sync.last_heard = Some(MyInstant(std::time::Instant::now() - quiet));
+        assert!(sync.timed_out());
+
+        sync.reset();
+        assert!(!sync.timed_out());
+    }
+}
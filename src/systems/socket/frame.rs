@@ -0,0 +1,168 @@
+use crate::{ClientError, Result};
+use log::trace;
+use snafu::Backtrace;
+
+use super::{
+    ByteBuffer, ByteBufferRead, ByteBufferWrite, MByteBuffer, MByteBufferRead,
+    MByteBufferWrite, bufer_ext::MByteBufferExt,
+};
+
+/// Largest frame body the client accepts.
+pub const MAX_FRAME_LENGTH: u64 = 8192;
+/// Size of the u64 length prefix in front of every frame.
+pub const FRAME_HEADER_SIZE: usize = 8;
+/// Set on the length prefix when the body is LZ4 compressed. Only used
+/// once both sides enabled Capabilities::COMPRESSION.
+pub const FRAME_COMPRESSED: u64 = 1 << 63;
+/// Bodies smaller than this are not worth compressing.
+pub const COMPRESS_THRESHOLD: usize = 512;
+/// Largest body a compressed frame may expand to.
+pub const MAX_DECOMPRESSED_LENGTH: usize = 65536;
+
+/// Bytes compression kept off the wire.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct CompressionStats {
+    pub saved_in: u64,
+    pub saved_out: u64,
+}
+
+impl CompressionStats {
+    #[inline]
+    pub fn total(&self) -> u64 {
+        self.saved_in + self.saved_out
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FrameStatus {
+    /// A full frame was copied into the packet buffer. Holds the body
+    /// length, the packet may have stale bytes past it.
+    Ready(usize),
+    /// Not enough bytes yet, the cursor was left at the frame start.
+    Incomplete,
+    /// The length prefix was out of range. The connection should be closed.
+    Malformed,
+}
+
+/// Reads the next length prefixed frame out of `buffer` into `packet`.
+/// `packet` cursor is left at the start of the frame body.
+pub fn decode_frame(
+    buffer: &mut ByteBuffer,
+    packet: &mut MByteBuffer,
+) -> Result<FrameStatus> {
+    if buffer.length() - buffer.cursor() < FRAME_HEADER_SIZE {
+        return Ok(FrameStatus::Incomplete);
+    }
+
+    let prefix = buffer.read::<u64>()?;
+    let compressed = prefix & FRAME_COMPRESSED != 0;
+    let length = prefix & !FRAME_COMPRESSED;
+
+    trace!("Length is {}, compressed {}", length, compressed);
+    if !(2..=MAX_FRAME_LENGTH).contains(&length) {
+        log::error!("Bad or malformed packet length {}", length);
+        return Ok(FrameStatus::Malformed);
+    }
+
+    if length > (buffer.length() - buffer.cursor()) as u64 {
+        buffer.move_cursor(buffer.cursor() - FRAME_HEADER_SIZE)?;
+        return Ok(FrameStatus::Incomplete);
+    }
+
+    let body = buffer.read_slice(length as usize)?;
+
+    packet.move_cursor_to_start();
+
+    if compressed {
+        let data = match decompress_body(body) {
+            Some(data) => data,
+            None => return Ok(FrameStatus::Malformed),
+        };
+
+        packet.write_slice(&data)?;
+        packet.move_cursor_to_start();
+        return Ok(FrameStatus::Ready(data.len()));
+    }
+
+    packet.write_slice(body)?;
+    packet.move_cursor_to_start();
+
+    Ok(FrameStatus::Ready(length as usize))
+}
+
+/// Body layout is the u32 little endian uncompressed size followed by the
+/// LZ4 block.
+fn decompress_body(body: &[u8]) -> Option<Vec<u8>> {
+    let size = u32::from_le_bytes(body.get(..4)?.try_into().ok()?) as usize;
+
+    if size > MAX_DECOMPRESSED_LENGTH {
+        log::error!("Compressed frame expands to {} bytes", size);
+        return None;
+    }
+
+    match lz4_flex::block::decompress(&body[4..], size) {
+        Ok(data) => Some(data),
+        Err(e) => {
+            log::error!("Failed to decompress frame: {}", e);
+            None
+        }
+    }
+}
+
+/// Compresses a finished packet when that makes it smaller. Returns None
+/// when the packet should be sent as it is.
+pub fn compress_frame(buf: &mut MByteBuffer) -> Result<Option<MByteBuffer>> {
+    let data = buf.as_slice();
+
+    if data.len() < FRAME_HEADER_SIZE + COMPRESS_THRESHOLD {
+        return Ok(None);
+    }
+
+    let body = &data[FRAME_HEADER_SIZE..];
+    let compressed = lz4_flex::block::compress_prepend_size(body);
+
+    if compressed.len() >= body.len() {
+        return Ok(None);
+    }
+
+    let mut out = MByteBuffer::new()?;
+    out.write(compressed.len() as u64 | FRAME_COMPRESSED)?;
+    out.write_slice(&compressed)?;
+    out.move_cursor(0)?;
+
+    Ok(Some(out))
+}
+
+/// Undoes compress_frame on a finished packet. An uncompressed packet is
+/// returned as it is.
+pub fn expand_frame(mut buf: MByteBuffer) -> Result<MByteBuffer> {
+    buf.move_cursor(0)?;
+    let prefix = buf.read::<u64>()?;
+    buf.move_cursor(0)?;
+
+    if prefix & FRAME_COMPRESSED == 0 {
+        return Ok(buf);
+    }
+
+    let Some(data) = decompress_body(&buf.as_slice()[FRAME_HEADER_SIZE..])
+    else {
+        return Err(ClientError::InvalidPacket {
+            backtrace: Backtrace::new(),
+        });
+    };
+
+    let mut out = MByteBuffer::new_packet()?;
+    out.write_slice(&data)?;
+    out.finish()?;
+
+    Ok(out)
+}
+
+/// Drops the consumed bytes once every frame in the buffer was read.
+pub fn compact_buffer(buffer: &mut ByteBuffer) -> Result<()> {
+    if buffer.cursor() == buffer.length() {
+        buffer.truncate(0)?;
+    }
+
+    Ok(())
+}
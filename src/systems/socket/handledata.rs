@@ -1,3 +1,4 @@
+pub mod handle_content;
 pub mod handle_entity;
 pub mod handle_general;
 pub mod handle_interface;
@@ -6,5 +7,6 @@ pub mod handle_trade;
 pub mod mapper;
 pub mod router;
 
+pub use handle_content::update_content_sync;
 pub use mapper::{PacketRouter, ServerPackets};
 pub use router::handle_data;
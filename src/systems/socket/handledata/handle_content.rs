@@ -0,0 +1,194 @@
+use mmap_bytey::MByteBuffer;
+
+use crate::{
+    Alert, AlertIndex, AlertType, Result, World,
+    content::Content,
+    load_items, load_npcs, load_shops, reload_map_data,
+    systems::{
+        BufferTask, ContentEntry, Poller, SystemHolder, changed_entries,
+        send_request_content,
+    },
+};
+
+pub fn handle_content_manifest(
+    socket: &mut Poller,
+    _world: &mut World,
+    systems: &mut SystemHolder,
+    content: &mut Content,
+    alert: &mut Alert,
+    data: &mut MByteBuffer,
+    _seconds: f32,
+    buffer: &mut BufferTask,
+) -> Result<()> {
+    let total = data.read::<u32>()? as usize;
+    let count = data.read::<u16>()?;
+    let mut entries = Vec::with_capacity(count as usize);
+
+    for _ in 0..count {
+        let path = data.read::<String>()?;
+        let entry = ContentEntry {
+            sha256: data.read::<String>()?,
+            size: data.read::<u32>()?,
+        };
+
+        entries.push((path, entry));
+    }
+
+    let Some(server) = socket.content_sync.add_manifest(total, entries) else {
+        return Ok(());
+    };
+
+    let local = systems.base.data_files.content_hashes();
+    let local_only: Vec<String> = local
+        .keys()
+        .filter(|path| !server.contains_key(*path))
+        .cloned()
+        .collect();
+    let paths = socket.content_sync.start(changed_entries(server, local));
+
+    // Left in place they would change the content hash on every connect.
+    if !local_only.is_empty() {
+        log::info!(
+            "Removing {} data files the server does not have",
+            local_only.len()
+        );
+
+        if let Err(e) = systems.base.data_files.remove(&local_only) {
+            log::warn!("Could not remove old data files: {}", e);
+        }
+
+        for path in &local_only {
+            socket.content_sync.mark_changed(path);
+        }
+    }
+
+    if paths.is_empty() {
+        return finish_sync(socket, systems, content, alert, buffer);
+    }
+
+    log::info!("Downloading {} changed data files", paths.len());
+    send_request_content(socket, &paths)
+}
+
+pub fn handle_content_current(
+    socket: &mut Poller,
+    _world: &mut World,
+    _systems: &mut SystemHolder,
+    _content: &mut Content,
+    _alert: &mut Alert,
+    _data: &mut MByteBuffer,
+    _seconds: f32,
+    _buffer: &mut BufferTask,
+) -> Result<()> {
+    log::info!("Game data matches the server");
+    socket.content_sync.reset();
+    Ok(())
+}
+
+pub fn handle_content_chunk(
+    socket: &mut Poller,
+    _world: &mut World,
+    systems: &mut SystemHolder,
+    content: &mut Content,
+    alert: &mut Alert,
+    data: &mut MByteBuffer,
+    _seconds: f32,
+    buffer: &mut BufferTask,
+) -> Result<()> {
+    let path = data.read::<String>()?;
+    let offset = data.read::<u32>()?;
+    let len = data.read::<u32>()?;
+    let bytes = data.read_slice(len as usize)?;
+
+    match socket.content_sync.add_chunk(&path, offset, bytes) {
+        Ok(Some(file)) => {
+            match systems.base.data_files.write_patch(&path, &file) {
+                Ok(()) => socket.content_sync.mark_changed(&path),
+                Err(e) => {
+                    log::warn!("Could not save {}: {}", path, e);
+                    socket.content_sync.fail(&path);
+                }
+            }
+        }
+        Ok(None) => {}
+        Err(e) => log::warn!("Dropping data file download, {}", e),
+    }
+
+    finish_sync(socket, systems, content, alert, buffer)
+}
+
+/// Reloads the tables once the last file arrived, and the maps when any
+/// map file changed. A download with files that failed can not be played
+/// with, so the client goes back to the title screen and tries again on
+/// the next connect.
+fn finish_sync(
+    socket: &mut Poller,
+    systems: &mut SystemHolder,
+    content: &mut Content,
+    alert: &mut Alert,
+    buffer: &mut BufferTask,
+) -> Result<()> {
+    let Some(failed) = socket.content_sync.take_finished() else {
+        return Ok(());
+    };
+
+    let files = &mut systems.base.data_files;
+    systems.base.item = load_items(files);
+    systems.base.npc = load_npcs(files);
+    systems.base.shop = load_shops(files);
+
+    if socket
+        .content_sync
+        .take_changed()
+        .iter()
+        .any(|path| path.starts_with("maps/"))
+    {
+        reload_map_data(systems, &content.game_content.map.mapindex, buffer);
+    }
+
+    if failed.is_empty() {
+        return Ok(());
+    }
+
+    log::error!("Game data update failed for {}", failed.join(", "));
+    socket.disconnect();
+    alert.show_alert(
+        systems,
+        AlertType::Inform,
+        format!(
+            "{} game data files could not be updated. Please try again.",
+            failed.len()
+        ),
+        "Update Failed".into(),
+        250,
+        AlertIndex::UpdateRequired,
+        false,
+    );
+
+    Ok(())
+}
+
+/// Gives up on a check or download the server stopped answering, the same
+/// way as on a failed file.
+pub fn update_content_sync(
+    socket: &mut Poller,
+    systems: &mut SystemHolder,
+    alert: &mut Alert,
+) {
+    if !socket.content_sync.timed_out() {
+        return;
+    }
+
+    log::error!("Server stopped answering the game data check");
+    socket.content_sync.reset();
+    socket.disconnect();
+    alert.show_alert(
+        systems,
+        AlertType::Inform,
+        "The server did not finish sending game data. Please try again.".into(),
+        "Update Failed".into(),
+        250,
+        AlertIndex::UpdateRequired,
+        false,
+    );
+}
@@ -5,15 +5,17 @@ use crate::{
     Alert, DeathType, Entity, EntityKind, Equipment, GlobalKey, Item,
     MovementData, NpcMode, Position, Result, UserAccess, VITALS_MAX, World,
     content::{
-        Content, MapItem, NPC_SPRITE_FRAME_X, PLAYER_SPRITE_FRAME_X, add_npc,
-        add_player, create_npc_light, create_player_light, finalize_entity,
-        init_npc_attack, init_player_attack, is_map_connected, npc_finalized,
-        player_finalized, set_npc_frame, set_player_frame, unload_mapitems,
-        unload_npc, unload_player, update_camera, update_mapitem_position,
-        update_npc_camera, update_player_camera,
+        Content, MapItem, NPC_SPRITE_FRAME_X, PLAYER_SPRITE_FRAME_X, Reconcile,
+        add_npc, add_player, create_npc_light, create_player_light,
+        finalize_entity, init_npc_attack, init_player_attack, is_map_connected,
+        npc_finalized, player_finalized, rollback_player, set_npc_frame,
+        set_player_frame, unload_mapitems, unload_npc, unload_player,
+        update_camera, update_mapitem_position, update_npc_camera,
+        update_player_camera,
     },
     systems::{
-        BufferTask, FadeData, FadeType, Poller, SystemHolder, get_percent,
+        BufferTask, Capabilities, FadeData, FadeType, Poller, SystemHolder,
+        get_percent,
     },
 };
 
@@ -176,13 +178,11 @@ pub fn handle_npcdata(
                 if let Some(Entity::Npc(n_data)) =
                     world.entities.get_mut(entity)
                 {
-                    if let Some(npc_data) = systems.base.npc.get(num as usize) {
-                        systems.gfx.set_text(
-                            &mut systems.renderer,
-                            &n_data.name_map.0,
-                            &npc_data.name,
-                        );
-                    }
+                    systems.gfx.set_text(
+                        &mut systems.renderer,
+                        &n_data.name_map.0,
+                        &systems.base.get_npc(num as usize).name,
+                    );
 
                     n_data.dir = dir;
                     n_data.level = level;
@@ -244,13 +244,8 @@ pub fn handle_mapitems(
                     Position::default()
                 };
 
-                let sprite = if let Some(itemdata) =
-                    systems.base.item.get(item.num as usize)
-                {
-                    itemdata.sprite as usize
-                } else {
-                    0
-                };
+                let sprite =
+                    systems.base.get_item(item.num as usize).sprite as usize;
                 let mapitem = MapItem::create(
                     world,
                     systems,
@@ -285,23 +280,69 @@ pub fn handle_mapitems(
 }
 
 pub fn handle_move(
-    _socket: &mut Poller,
+    socket: &mut Poller,
     world: &mut World,
     systems: &mut SystemHolder,
     content: &mut Content,
     _alert: &mut Alert,
     data: &mut MByteBuffer,
-    _seconds: f32,
+    seconds: f32,
     _buffer: &mut BufferTask,
 ) -> Result<()> {
     let count = data.read::<u32>()?;
+    let move_time = socket.capabilities.contains(Capabilities::MOVE_TIME);
+    let move_seq = socket.capabilities.contains(Capabilities::MOVE_SEQ);
 
     for _ in 0..count {
         let entity = data.read::<GlobalKey>()?;
         let pos = data.read::<Position>()?;
-        let _warp = data.read::<bool>()?;
+        let warp = data.read::<bool>()?;
         let _switch = data.read::<bool>()?;
         let dir = data.read::<u8>()?;
+        let server_ms = if move_time {
+            Some(data.read::<u64>()?)
+        } else {
+            None
+        };
+        let seq = if move_seq {
+            data.read::<Option<u32>>()?
+        } else {
+            None
+        };
+        let time = content.game_content.server_clock.stamp(server_ms, seconds);
+
+        // Warps onto a map we can not see fall through to be unloaded.
+        if warp
+            && Some(entity) != content.game_content.myentity
+            && content
+                .game_content
+                .my_position(world)
+                .map(|p| p.map)
+                .is_some_and(|map| is_map_connected(map, pos.map))
+        {
+            snap_entity(world, systems, socket, content, entity, pos, dir)?;
+            continue;
+        }
+
+        if warp {
+            content.game_content.prediction.clear();
+        } else if Some(entity) == content.game_content.myentity {
+            // The server moved us on its own, so the pending steps were
+            // taken from the wrong tile.
+            if move_seq && seq.is_none() {
+                content.game_content.prediction.clear();
+            }
+
+            match content.game_content.prediction.reconcile(pos, seq) {
+                // Already walked when it was predicted.
+                Reconcile::Confirmed(_) => continue,
+                Reconcile::Mispredicted(_) => {
+                    rollback_player(world, entity, pos);
+                    continue;
+                }
+                Reconcile::Untracked => {}
+            }
+        }
 
         if let Some(myentity) = content.game_content.myentity {
             if world.entities.contains_key(entity) {
@@ -315,37 +356,38 @@ pub fn handle_move(
 
                 if is_map_connected(player_pos.map, pos.map) {
                     if let Some(entity_data) = world.entities.get_mut(entity) {
-                        let movement_data = MovementData { end_pos: pos, dir };
+                        let movement_data = MovementData {
+                            end_pos: pos,
+                            dir,
+                            time,
+                        };
+                        // Resent moves carry a newer time, so only the
+                        // tile and direction tell them apart.
+                        let is_new = |data: &MovementData| {
+                            data.end_pos != pos || data.dir != dir
+                        };
 
                         match entity_data {
                             Entity::Player(p_data) => {
-                                if p_data.movement_buffer.is_empty() {
+                                if p_data
+                                    .movement_buffer
+                                    .back()
+                                    .is_none_or(is_new)
+                                {
                                     p_data
                                         .movement_buffer
                                         .push_back(movement_data);
-                                } else if let Some(data) =
-                                    p_data.movement_buffer.back()
-                                {
-                                    if *data != movement_data {
-                                        p_data
-                                            .movement_buffer
-                                            .push_back(movement_data);
-                                    }
                                 }
                             }
                             Entity::Npc(n_data) => {
-                                if n_data.movement_buffer.is_empty() {
+                                if n_data
+                                    .movement_buffer
+                                    .back()
+                                    .is_none_or(is_new)
+                                {
                                     n_data
                                         .movement_buffer
                                         .push_back(movement_data);
-                                } else if let Some(data) =
-                                    n_data.movement_buffer.back()
-                                {
-                                    if *data != movement_data {
-                                        n_data
-                                            .movement_buffer
-                                            .push_back(movement_data);
-                                    }
                                 }
                             }
                             _ => {}
@@ -391,6 +433,58 @@ pub fn handle_move(
     Ok(())
 }
 
+/// Moves a remote player or npc straight to `pos` for a teleport, without
+/// walking there or waiting for the moves buffered before it.
+fn snap_entity(
+    world: &mut World,
+    systems: &mut SystemHolder,
+    socket: &mut Poller,
+    content: &mut Content,
+    entity: GlobalKey,
+    pos: Position,
+    dir: u8,
+) -> Result<()> {
+    match world.entities.get_mut(entity) {
+        Some(Entity::Player(p_data)) => {
+            p_data.movement_buffer.clear();
+            p_data.movement.is_moving = false;
+            p_data.movement.move_offset = 0.0;
+            p_data.pos = pos;
+            p_data.pos_offset = Vec2::new(0.0, 0.0);
+            p_data.dir = dir;
+
+            let frame = dir * PLAYER_SPRITE_FRAME_X as u8;
+            set_player_frame(world, systems, entity, frame as usize)?;
+            update_player_camera(
+                world,
+                systems,
+                socket,
+                entity,
+                &mut content.game_content,
+            )
+        }
+        Some(Entity::Npc(n_data)) => {
+            n_data.movement_buffer.clear();
+            n_data.movement.is_moving = false;
+            n_data.movement.move_offset = 0.0;
+            n_data.pos = pos;
+            n_data.pos_offset = Vec2::new(0.0, 0.0);
+            n_data.dir = dir;
+
+            let frame = dir * NPC_SPRITE_FRAME_X as u8;
+            set_npc_frame(world, systems, entity, frame as usize)?;
+            update_npc_camera(
+                world,
+                systems,
+                entity,
+                socket,
+                &mut content.game_content,
+            )
+        }
+        _ => Ok(()),
+    }
+}
+
 pub fn handle_warp(
     socket: &mut Poller,
     world: &mut World,
@@ -411,6 +505,10 @@ pub fn handle_warp(
             continue;
         }
 
+        if Some(entity) == content.game_content.myentity {
+            content.game_content.prediction.clear();
+        }
+
         let (old_pos, dir) =
             if let Some(entity_data) = world.entities.get_mut(entity) {
                 match entity_data {
@@ -2,11 +2,15 @@ use graphics::*;
 use mmap_bytey::MByteBuffer;
 
 use crate::{
-    Alert, GlobalKey, MyInstant, Result, World,
+    APP_MAJOR, APP_MINOR, APP_REV, Alert, AlertIndex, AlertType, GlobalKey,
+    MyInstant, Result, World,
     content::Content,
+    content_hash,
     systems::{
-        BufferTask, FADE_SWITCH_TO_GAME, FadeData, FadeType, Poller,
-        SystemHolder, send_handshake, send_login_ok, send_tls_handshake,
+        BufferTask, Capabilities, FADE_SWITCH_TO_GAME, FadeData, FadeType,
+        PROTOCOL_VERSION, Poller, ProtocolInfo, SystemHolder, reject_reconnect,
+        send_handshake, send_login_ok, send_request_login_token,
+        send_revoke_login_token, send_tls_handshake,
     },
 };
 
@@ -67,11 +71,19 @@ pub fn handle_ping(
     Ok(())
 }
 
+/// Both handshakes carry the same code, only save when it changed.
+fn store_reconnect_code(systems: &mut SystemHolder, code: String) {
+    if systems.config.reconnect_code != code {
+        systems.config.reconnect_code = code;
+        systems.config.save_config("settings.toml");
+    }
+}
+
 pub fn handle_handshake(
     socket: &mut Poller,
     _world: &mut World,
     systems: &mut SystemHolder,
-    content: &mut Content,
+    _content: &mut Content,
     _alert: &mut Alert,
     data: &mut MByteBuffer,
     _seconds: f32,
@@ -79,10 +91,9 @@ pub fn handle_handshake(
 ) -> Result<()> {
     let code = data.read::<String>()?;
     let handshake = data.read::<String>()?;
-    systems.config.reconnect_code = code;
-    systems.config.save_config("settings.toml");
-    content.game_content.reconnect_count = 0;
-    send_handshake(socket, handshake)
+    store_reconnect_code(systems, code);
+    send_handshake(socket, handshake)?;
+    socket.resume_session()
 }
 
 pub fn handle_loginok(
@@ -105,7 +116,23 @@ pub fn handle_loginok(
         FadeData::None,
     );
 
-    send_login_ok(socket, &systems.config.reconnect_code)
+    send_login_ok(socket, &systems.config.reconnect_code)?;
+
+    if !socket.capabilities.contains(Capabilities::LOGIN_TOKEN) {
+        return Ok(());
+    }
+
+    if systems.config.remember_login {
+        if systems.config.login_token.is_empty() {
+            send_request_login_token(socket)?;
+        }
+    } else if !systems.config.login_token.is_empty() {
+        let token = std::mem::take(&mut systems.config.login_token);
+        systems.config.save_config("settings.toml");
+        send_revoke_login_token(socket, &token)?;
+    }
+
+    Ok(())
 }
 
 pub fn handle_myindex(
@@ -127,7 +154,7 @@ pub fn handle_tls_handshake(
     socket: &mut Poller,
     _world: &mut World,
     systems: &mut SystemHolder,
-    content: &mut Content,
+    _content: &mut Content,
     _alert: &mut Alert,
     data: &mut MByteBuffer,
     _seconds: f32,
@@ -135,10 +162,10 @@ pub fn handle_tls_handshake(
 ) -> Result<()> {
     let code = data.read::<String>()?;
     let handshake = data.read::<String>()?;
-    systems.config.reconnect_code = code;
-    systems.config.save_config("settings.toml");
-    content.game_content.reconnect_count = 0;
-    send_tls_handshake(socket, handshake)
+    store_reconnect_code(systems, code);
+
+    let content_hash = content_hash(systems.base.data_files.content_hashes());
+    send_tls_handshake(socket, handshake, content_hash)
 }
 
 pub fn handle_clear_data(
@@ -175,7 +202,7 @@ pub fn handle_playitemsfx(
 ) -> Result<()> {
     let index = data.read::<u16>()?;
 
-    if let Some(sfx_name) = &systems.base.item[index as usize].sound_index {
+    if let Some(sfx_name) = &systems.base.get_item(index as usize).sound_index {
         let volume = systems.config.sfx_volume as f32 * 0.01;
         systems
             .audio
@@ -184,3 +211,127 @@ pub fn handle_playitemsfx(
 
     Ok(())
 }
+
+pub fn handle_protocol_info(
+    socket: &mut Poller,
+    _world: &mut World,
+    systems: &mut SystemHolder,
+    _content: &mut Content,
+    alert: &mut Alert,
+    data: &mut MByteBuffer,
+    _seconds: f32,
+    _buffer: &mut BufferTask,
+) -> Result<()> {
+    let info = ProtocolInfo {
+        server_version: data.read::<u16>()?,
+        min_version: data.read::<u16>()?,
+        enabled: data.read::<Capabilities>()?,
+    };
+
+    if !info.is_compatible() {
+        log::error!(
+            "Protocol mismatch, client {} server {} (min {})",
+            PROTOCOL_VERSION,
+            info.server_version,
+            info.min_version
+        );
+
+        let msg = if PROTOCOL_VERSION < info.min_version {
+            format!(
+                "Your client {}.{}.{} is too old for this server. Please update the game.",
+                APP_MAJOR, APP_MINOR, APP_REV
+            )
+        } else {
+            "This server runs an older version than your client. Please try again later.".to_string()
+        };
+
+        socket.disconnect();
+        alert.show_alert(
+            systems,
+            AlertType::Inform,
+            msg,
+            "Update Required".into(),
+            250,
+            AlertIndex::UpdateRequired,
+            false,
+        );
+        return Ok(());
+    }
+
+    socket.capabilities = info.enabled.intersection(Capabilities::client());
+
+    // Logging in waits until the server answered the content hash. An
+    // older server never answers it.
+    if socket.capabilities.contains(Capabilities::CONTENT_SYNC) {
+        socket.content_sync.check();
+    }
+
+    log::info!(
+        "Protocol {} accepted, capabilities {:#x}",
+        info.server_version,
+        socket.capabilities.0
+    );
+
+    Ok(())
+}
+
+pub fn handle_reconnect_fail(
+    socket: &mut Poller,
+    _world: &mut World,
+    systems: &mut SystemHolder,
+    _content: &mut Content,
+    alert: &mut Alert,
+    _data: &mut MByteBuffer,
+    _seconds: f32,
+    _buffer: &mut BufferTask,
+) -> Result<()> {
+    reject_reconnect(socket, systems, alert);
+    Ok(())
+}
+
+pub fn handle_login_token(
+    _socket: &mut Poller,
+    _world: &mut World,
+    systems: &mut SystemHolder,
+    _content: &mut Content,
+    _alert: &mut Alert,
+    data: &mut MByteBuffer,
+    _seconds: f32,
+    _buffer: &mut BufferTask,
+) -> Result<()> {
+    let token = data.read::<String>()?;
+
+    if systems.config.remember_login {
+        systems.config.login_token = token;
+        systems.config.save_config("settings.toml");
+    }
+
+    Ok(())
+}
+
+/// The saved token expired or was revoked on the server.
+pub fn handle_login_token_rejected(
+    _socket: &mut Poller,
+    _world: &mut World,
+    systems: &mut SystemHolder,
+    _content: &mut Content,
+    alert: &mut Alert,
+    _data: &mut MByteBuffer,
+    _seconds: f32,
+    _buffer: &mut BufferTask,
+) -> Result<()> {
+    systems.config.login_token.clear();
+    systems.config.save_config("settings.toml");
+
+    alert.show_alert(
+        systems,
+        AlertType::Inform,
+        "Your saved login has expired. Please enter your password.".into(),
+        "Alert Message".into(),
+        250,
+        AlertIndex::None,
+        false,
+    );
+
+    Ok(())
+}
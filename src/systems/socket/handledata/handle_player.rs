@@ -6,14 +6,14 @@ use crate::{
     content::{
         Content, ProfileLabel, Window, add_player, close_interface,
         create_player_light, player_get_armor_defense, player_get_next_lvl_exp,
-        player_get_weapon_damage,
+        player_get_weapon_damage, rollback_player,
     },
-    systems::{BufferTask, Poller, SystemHolder},
+    systems::{BufferTask, Capabilities, Poller, SystemHolder},
 };
 
 pub fn handle_move_ok(
-    _socket: &mut Poller,
-    _world: &mut World,
+    socket: &mut Poller,
+    world: &mut World,
     _systems: &mut SystemHolder,
     content: &mut Content,
     _alert: &mut Alert,
@@ -22,8 +22,25 @@ pub fn handle_move_ok(
     _buffer: &mut BufferTask,
 ) -> Result<()> {
     // TODO use this to reset players position, reload stuff etc.
-    let _move_ok = data.read::<bool>()?;
+    let move_ok = data.read::<bool>()?;
+    let seq = if socket.capabilities.contains(Capabilities::MOVE_SEQ) {
+        Some(data.read::<u32>()?)
+    } else {
+        None
+    };
     content.game_content.can_move = true;
+
+    // The server refused the step and corrected our position.
+    if !move_ok {
+        content.game_content.cancel_path(world, socket)?;
+
+        if let (Some(step), Some(entity)) = (
+            content.game_content.prediction.reject(seq),
+            content.game_content.myentity,
+        ) {
+            rollback_player(world, entity, step.from);
+        }
+    }
     //info!("move allowed: {move_ok}");
     //content.game_content.myentity = Some(entity);
     Ok(())
@@ -3,8 +3,8 @@ use ahash::AHashMap;
 use serde::{Deserialize, Serialize};
 
 use super::{
-    handle_entity::*, handle_general::*, handle_interface::*, handle_player::*,
-    handle_trade::*,
+    handle_content::*, handle_entity::*, handle_general::*,
+    handle_interface::*, handle_player::*, handle_trade::*,
 };
 
 type PacketFunction = fn(
@@ -73,6 +73,13 @@ pub enum ServerPackets {
     Ping,
     TlsHandShake,
     ClearData,
+    ProtocolInfo,
+    ReconnectFail,
+    LoginToken,
+    LoginTokenRejected,
+    ContentManifest,
+    ContentChunk,
+    ContentCurrent,
 }
 
 pub struct PacketRouter(pub AHashMap<ServerPackets, PacketFunction>);
@@ -173,6 +180,34 @@ impl PacketRouter {
                 ServerPackets::ClearData,
                 handle_clear_data as PacketFunction,
             ),
+            (
+                ServerPackets::ProtocolInfo,
+                handle_protocol_info as PacketFunction,
+            ),
+            (
+                ServerPackets::ReconnectFail,
+                handle_reconnect_fail as PacketFunction,
+            ),
+            (
+                ServerPackets::LoginToken,
+                handle_login_token as PacketFunction,
+            ),
+            (
+                ServerPackets::LoginTokenRejected,
+                handle_login_token_rejected as PacketFunction,
+            ),
+            (
+                ServerPackets::ContentManifest,
+                handle_content_manifest as PacketFunction,
+            ),
+            (
+                ServerPackets::ContentChunk,
+                handle_content_chunk as PacketFunction,
+            ),
+            (
+                ServerPackets::ContentCurrent,
+                handle_content_current as PacketFunction,
+            ),
         ]))
     }
 }
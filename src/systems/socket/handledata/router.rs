@@ -1,4 +1,4 @@
-use log::{error, trace};
+use log::{error, trace, warn};
 use snafu::Backtrace;
 
 use crate::{BufferTask, data_types::*, fade::*, socket::*};
@@ -21,6 +21,11 @@ pub fn handle_data(
         return Ok(());
     }
 
+    if !socket.capabilities.allows(id) {
+        warn!("Ignoring {:?}, it was not enabled by the server", id);
+        return Ok(());
+    }
+
     let fun = match router.0.get(&id) {
         Some(fun) => fun,
         None => {
@@ -40,3 +45,232 @@ pub fn handle_data(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Entity, EntityKind, GlobalKey, IsUsingType, Item, MapPosition,
+        PlayerEntity, Position,
+        socket::{
+            bufer_ext::MByteBufferExt,
+            loopback::{LoopbackHandle, read_sent},
+        },
+    };
+    use slotmap::KeyData;
+
+    /// A logged in client with headless systems, fed through the same
+    /// process_packets call the game loop makes.
+    struct Client {
+        socket: Poller,
+        tls: LoopbackHandle,
+        plain: LoopbackHandle,
+        router: PacketRouter,
+        world: World,
+        systems: SystemHolder,
+        content: Content,
+        alert: Alert,
+        buffer: BufferTask,
+    }
+
+    impl Client {
+        fn new() -> Self {
+            let (mut socket, tls, plain) = Poller::loopback().unwrap();
+            socket.capabilities = Capabilities::client();
+
+            Client {
+                socket,
+                tls,
+                plain,
+                router: PacketRouter::init(),
+                world: World::default(),
+                systems: SystemHolder::headless(
+                    &std::env::temp_dir().join("ascending_headless_data"),
+                ),
+                content: Content::headless(),
+                alert: Alert::new(),
+                buffer: BufferTask::new(),
+            }
+        }
+
+        fn receive(&mut self, mut packet: MByteBuffer) {
+            packet.finish().unwrap();
+            self.tls.push_frame(&mut packet);
+            self.socket
+                .process_packets(
+                    &self.router,
+                    &mut self.world,
+                    &mut self.systems,
+                    &mut self.content,
+                    &mut self.alert,
+                    0.0,
+                    &mut self.buffer,
+                )
+                .unwrap();
+            self.socket.flush_sends().unwrap();
+
+            // A handler error closes the channel.
+            assert!(!self.tls.is_closed());
+        }
+
+        fn add_player(&mut self, key: GlobalKey, pos: Position) {
+            let _ = self.world.kinds.insert(key, EntityKind::Player);
+            let _ = self.world.entities.insert(
+                key,
+                Entity::Player(Box::new(PlayerEntity {
+                    pos,
+                    ..Default::default()
+                })),
+            );
+            self.content.game_content.players.borrow_mut().insert(key);
+        }
+    }
+
+    fn packet(id: ServerPackets) -> MByteBuffer {
+        let mut packet = MByteBuffer::new_packet().unwrap();
+        packet.write(id).unwrap();
+        packet
+    }
+
+    fn sent_ids(handle: &LoopbackHandle) -> Vec<ClientPacket> {
+        handle
+            .take_sent()
+            .iter()
+            .map(|frame| read_sent(frame).read::<ClientPacket>().unwrap())
+            .collect()
+    }
+
+    fn key(index: u64) -> GlobalKey {
+        GlobalKey::from(KeyData::from_ffi(index))
+    }
+
+    #[test]
+    fn login_ok_fades_in_and_asks_for_a_token() {
+        let mut client = Client::new();
+        client.systems.config.remember_login = true;
+
+        let mut login = packet(ServerPackets::LoginOk);
+        login.write(12u32).unwrap();
+        login.write(30u32).unwrap();
+        client.receive(login);
+
+        assert!(client.systems.fade.show);
+        assert_eq!(sent_ids(&client.plain), vec![ClientPacket::LoginOk]);
+        assert_eq!(
+            sent_ids(&client.tls),
+            vec![ClientPacket::RequestLoginToken]
+        );
+    }
+
+    #[test]
+    fn warp_moves_me_and_drops_players_left_behind() {
+        let mut client = Client::new();
+        let (me, other) = (key(1), key(2));
+        let start = Position::default();
+
+        client.add_player(me, start);
+        client.add_player(other, start);
+        client.content.game_content.myentity = Some(me);
+        client.content.game_content.can_move = false;
+        client.content.game_content.prediction.push(
+            0,
+            start,
+            Position { x: 1, ..start },
+            Direction::Right,
+        );
+
+        let here = Position {
+            x: 5,
+            y: 7,
+            ..start
+        };
+        let away = Position {
+            map: MapPosition { x: 4, ..start.map },
+            ..start
+        };
+        let mut warp = packet(ServerPackets::Warp);
+        warp.write(2u32).unwrap();
+        warp.write(me).unwrap();
+        warp.write(here).unwrap();
+        warp.write(other).unwrap();
+        warp.write(away).unwrap();
+        client.receive(warp);
+
+        let Some(Entity::Player(p_data)) = client.world.entities.get(me) else {
+            panic!("my player was removed");
+        };
+        assert_eq!(p_data.pos, here);
+        assert!(!client.content.game_content.prediction.is_pending());
+        assert!(client.content.game_content.can_move);
+
+        assert!(!client.world.entities.contains_key(other));
+        assert!(
+            !client
+                .content
+                .game_content
+                .players
+                .borrow()
+                .contains(&other)
+        );
+    }
+
+    #[test]
+    fn storage_opens_and_fills_the_bank() {
+        let mut client = Client::new();
+
+        let mut open = packet(ServerPackets::OpenStorage);
+        open.write(0u32).unwrap();
+        client.receive(open);
+
+        let game_content = &client.content.game_content;
+        assert_eq!(game_content.player_data.is_using_type, IsUsingType::Bank);
+        assert!(game_content.interface.storage.visible);
+
+        let items = vec![
+            Item {
+                num: 3,
+                val: 10,
+                ..Default::default()
+            },
+            Item {
+                num: 8,
+                val: 1,
+                ..Default::default()
+            },
+        ];
+        let mut storage = packet(ServerPackets::PlayerStorage);
+        storage.write(0usize).unwrap();
+        storage.write(2usize).unwrap();
+        storage.write(items.clone()).unwrap();
+        client.receive(storage);
+
+        assert_eq!(client.content.game_content.player_data.storage[..2], items);
+    }
+
+    #[test]
+    fn trade_opens_and_tracks_my_status() {
+        let mut client = Client::new();
+        let partner = key(7);
+
+        let mut init = packet(ServerPackets::InitTrade);
+        init.write(partner).unwrap();
+        client.receive(init);
+
+        let game_content = &client.content.game_content;
+        assert_eq!(
+            game_content.player_data.is_using_type,
+            IsUsingType::Trading(partner)
+        );
+        assert!(game_content.interface.trade.visible);
+
+        let mut status = packet(ServerPackets::TradeStatus);
+        status.write(TradeStatus::Accepted).unwrap();
+        status.write(TradeStatus::None).unwrap();
+        client.receive(status);
+
+        assert_eq!(
+            client.content.game_content.interface.trade.trade_status,
+            TradeStatus::Accepted
+        );
+    }
+}
@@ -0,0 +1,431 @@
+use crate::{Result, config::*};
+use mio::Poll;
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+use super::{
+    ByteBuffer, ClientState, MByteBuffer, Poller, Transport,
+    bufer_ext::ByteBufferExt,
+};
+
+#[derive(Default)]
+pub struct LoopbackState {
+    /// Frames waiting to be handed to the client, length prefix included.
+    pub inbound: VecDeque<Vec<u8>>,
+    /// Frames the client sent, length prefix included.
+    pub sent: Vec<Vec<u8>>,
+    pub closed: bool,
+}
+
+/// Test side of a loopback channel. Scripts server frames and inspects what
+/// the client sent back.
+#[derive(Clone, Default)]
+pub struct LoopbackHandle(pub Rc<RefCell<LoopbackState>>);
+
+impl LoopbackHandle {
+    /// Queues a finished packet as if the server had sent it.
+    pub fn push_frame(&self, packet: &mut MByteBuffer) {
+        self.0
+            .borrow_mut()
+            .inbound
+            .push_back(packet.as_slice().to_vec());
+    }
+
+    /// Returns and clears every frame the client sent so far.
+    pub fn take_sent(&self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.0.borrow_mut().sent)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.0.borrow().closed
+    }
+}
+
+/// In memory transport. Nothing touches the network or mio, so the Poller,
+/// the frame decoder and the send functions can run without a server.
+pub struct LoopbackTransport {
+    pub state: ClientState,
+    pub buffer: ByteBuffer,
+    pub shared: LoopbackHandle,
+}
+
+impl LoopbackTransport {
+    pub fn new() -> Result<(Self, LoopbackHandle)> {
+        let shared = LoopbackHandle::default();
+
+        Ok((
+            LoopbackTransport {
+                state: ClientState::Open,
+                buffer: ByteBuffer::new_packet_with(8192)?,
+                shared: shared.clone(),
+            },
+            shared,
+        ))
+    }
+
+    /// Moves scripted frames into the receive buffer, like a socket read.
+    fn fill_buffer(&mut self) -> Result<()> {
+        let mut shared = self.shared.0.borrow_mut();
+
+        if shared.inbound.is_empty() {
+            return Ok(());
+        }
+
+        let pos = self.buffer.cursor();
+        self.buffer.move_cursor_to_end();
+
+        while let Some(frame) = shared.inbound.pop_front() {
+            self.buffer.write_slice(&frame)?;
+        }
+
+        self.buffer.move_cursor(pos)?;
+        Ok(())
+    }
+}
+
+impl Transport for LoopbackTransport {
+    fn state(&self) -> ClientState {
+        self.state
+    }
+
+    fn process(
+        &mut self,
+        _event: &mio::event::Event,
+        _poll: &Poll,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    fn send(&mut self, mut buf: MByteBuffer, _poll: &Poll) -> Result<()> {
+        self.shared
+            .0
+            .borrow_mut()
+            .sent
+            .push(buf.as_slice().to_vec());
+        Ok(())
+    }
+
+    fn set_to_closing(&mut self, _poll: &Poll) {
+        self.state = ClientState::Closed;
+        self.shared.0.borrow_mut().closed = true;
+    }
+
+    fn shutdown(&mut self, _poll: &Poll) -> Result<()> {
+        self.state = ClientState::Closed;
+        self.shared.0.borrow_mut().closed = true;
+        Ok(())
+    }
+
+    fn reconnect(
+        &mut self,
+        _poll: &Poll,
+        _endpoint: &ServerEndpoint,
+    ) -> Result<()> {
+        self.state = ClientState::Open;
+        self.shared.0.borrow_mut().closed = false;
+        self.buffer.truncate(0)?;
+        Ok(())
+    }
+
+    fn clear_sends(&mut self) {}
+
+    fn take_sends(&mut self) -> VecDeque<MByteBuffer> {
+        VecDeque::new()
+    }
+
+    fn buffer(&mut self) -> &mut ByteBuffer {
+        if let Err(e) = self.fill_buffer() {
+            log::error!("Loopback buffer fill failed: {:?}", e);
+        }
+
+        &mut self.buffer
+    }
+}
+
+impl Poller {
+    /// Builds a Poller whose TLS and plain channels are loopbacks.
+    /// Returns the handles as (tls, plain).
+    pub fn loopback() -> Result<(Self, LoopbackHandle, LoopbackHandle)> {
+        let mut poller = Poller::new()?;
+        let (tls, tls_handle) = LoopbackTransport::new()?;
+        let (plain, plain_handle) = LoopbackTransport::new()?;
+
+        poller.add_channel(Box::new(tls));
+        poller.add_channel(Box::new(plain));
+
+        Ok((poller, tls_handle, plain_handle))
+    }
+}
+
+/// Decodes a frame from LoopbackHandle::take_sent, with the cursor at the
+/// packet id.
+#[cfg(test)]
+pub fn read_sent(frame: &[u8]) -> MByteBuffer {
+    let mut buffer = ByteBuffer::new_packet_with(frame.len()).unwrap();
+    let mut packet = MByteBuffer::new().unwrap();
+
+    buffer.truncate(0).unwrap();
+    buffer.write_slice(frame).unwrap();
+    buffer.move_cursor(0).unwrap();
+
+    assert!(matches!(
+        super::decode_frame(&mut buffer, &mut packet).unwrap(),
+        super::FrameStatus::Ready(_)
+    ));
+    packet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        GlobalKey,
+        socket::{
+            Capabilities, ClientPacket, FRAME_HEADER_SIZE, FrameStatus,
+            MByteBufferRead, MByteBufferWrite, ReconnectPhase, ServerPackets,
+            bufer_ext::MByteBufferExt, compress_frame, decode_frame,
+            send_attack, send_login, send_move, send_ping,
+        },
+    };
+
+    #[test]
+    fn scripted_frames_decode_in_order() {
+        let (mut poller, tls, _plain) = Poller::loopback().unwrap();
+
+        for id in [ServerPackets::Ping, ServerPackets::ClearData] {
+            let mut buf = MByteBuffer::new_packet().unwrap();
+            buf.write(id).unwrap();
+            buf.write(0u32).unwrap();
+            buf.finish().unwrap();
+            tls.push_frame(&mut buf);
+        }
+
+        let mut packet = MByteBuffer::new().unwrap();
+        let channel = &mut poller.channels[CHANNEL_TLS];
+
+        for expected in [ServerPackets::Ping, ServerPackets::ClearData] {
+            assert!(matches!(
+                decode_frame(channel.buffer(), &mut packet).unwrap(),
+                FrameStatus::Ready(_)
+            ));
+            assert_eq!(packet.read::<ServerPackets>().unwrap(), expected);
+        }
+
+        assert_eq!(
+            decode_frame(channel.buffer(), &mut packet).unwrap(),
+            FrameStatus::Incomplete
+        );
+    }
+
+    #[test]
+    fn partial_frame_waits_for_rest() {
+        let (mut poller, tls, _plain) = Poller::loopback().unwrap();
+        let mut buf = MByteBuffer::new_packet().unwrap();
+        buf.write(ServerPackets::Ping).unwrap();
+        buf.write(0u64).unwrap();
+        buf.finish().unwrap();
+
+        let bytes = buf.as_slice().to_vec();
+        tls.0.borrow_mut().inbound.push_back(bytes[..10].to_vec());
+
+        let mut packet = MByteBuffer::new().unwrap();
+        let channel = &mut poller.channels[CHANNEL_TLS];
+
+        assert_eq!(
+            decode_frame(channel.buffer(), &mut packet).unwrap(),
+            FrameStatus::Incomplete
+        );
+
+        tls.0.borrow_mut().inbound.push_back(bytes[10..].to_vec());
+
+        assert_eq!(
+            decode_frame(channel.buffer(), &mut packet).unwrap(),
+            FrameStatus::Ready(bytes.len() - FRAME_HEADER_SIZE)
+        );
+        assert_eq!(
+            packet.read::<ServerPackets>().unwrap(),
+            ServerPackets::Ping
+        );
+    }
+
+    #[test]
+    fn malformed_length_is_rejected() {
+        let (mut poller, tls, _plain) = Poller::loopback().unwrap();
+        tls.0
+            .borrow_mut()
+            .inbound
+            .push_back(0u64.to_le_bytes().to_vec());
+
+        let mut packet = MByteBuffer::new().unwrap();
+
+        assert_eq!(
+            decode_frame(poller.channels[CHANNEL_TLS].buffer(), &mut packet)
+                .unwrap(),
+            FrameStatus::Malformed
+        );
+    }
+
+    #[test]
+    fn login_goes_out_on_tls() {
+        let (mut poller, tls, plain) = Poller::loopback().unwrap();
+
+        send_login(
+            &mut poller,
+            "tester".into(),
+            "secret".into(),
+            (1, 2, 3),
+            "code",
+        )
+        .unwrap();
+        poller.flush_sends().unwrap();
+
+        assert!(plain.take_sent().is_empty());
+
+        let sent = tls.take_sent();
+        assert_eq!(sent.len(), 1);
+
+        let mut packet = read_sent(&sent[0]);
+        assert_eq!(packet.read::<ClientPacket>().unwrap(), ClientPacket::Login);
+        assert_eq!(packet.read::<String>().unwrap(), "tester");
+        assert_eq!(packet.read::<String>().unwrap(), "secret");
+        assert_eq!(packet.read::<u16>().unwrap(), 1);
+        assert_eq!(packet.read::<u16>().unwrap(), 2);
+        assert_eq!(packet.read::<u16>().unwrap(), 3);
+    }
+
+    #[test]
+    fn movement_goes_out_on_plain() {
+        let (mut poller, tls, plain) = Poller::loopback().unwrap();
+
+        send_move(&mut poller, Some(2), 7).unwrap();
+        send_attack(&mut poller, 2, None::<GlobalKey>).unwrap();
+        send_ping(&mut poller).unwrap();
+        poller.flush_sends().unwrap();
+
+        let sent = plain.take_sent();
+        assert_eq!(sent.len(), 2);
+
+        let mut packet = read_sent(&sent[0]);
+        assert_eq!(packet.read::<ClientPacket>().unwrap(), ClientPacket::Move);
+        assert_eq!(packet.read::<Option<u8>>().unwrap(), Some(2));
+        assert_eq!(packet.cursor(), packet.length());
+
+        // With MOVE_SEQ the step's sequence number follows.
+        poller.capabilities = Capabilities::MOVE_SEQ;
+        send_move(&mut poller, None, 8).unwrap();
+        poller.flush_sends().unwrap();
+
+        let mut packet = read_sent(&plain.take_sent()[0]);
+        assert_eq!(packet.read::<ClientPacket>().unwrap(), ClientPacket::Move);
+        assert_eq!(packet.read::<Option<u8>>().unwrap(), None);
+        assert_eq!(packet.read::<u32>().unwrap(), 8);
+
+        let mut packet = read_sent(&sent[1]);
+        assert_eq!(
+            packet.read::<ClientPacket>().unwrap(),
+            ClientPacket::Attack
+        );
+
+        let sent = tls.take_sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(
+            read_sent(&sent[0]).read::<ClientPacket>().unwrap(),
+            ClientPacket::OnlineCheck
+        );
+    }
+
+    #[test]
+    fn closing_marks_handle() {
+        let (mut poller, tls, _plain) = Poller::loopback().unwrap();
+
+        poller.set_to_closing(CHANNEL_TLS);
+
+        assert!(tls.is_closed());
+        assert!(poller.is_closed(CHANNEL_TLS));
+        assert!(!poller.is_closed(CHANNEL_PLAIN));
+    }
+
+    #[test]
+    fn compressed_frame_round_trips() {
+        let (mut poller, tls, _plain) = Poller::loopback().unwrap();
+        let mut buf = MByteBuffer::new_packet().unwrap();
+        buf.write(ServerPackets::ChatMsg).unwrap();
+        buf.write_slice(&[42u8; 2048]).unwrap();
+        buf.finish().unwrap();
+
+        let body_len = buf.length() - FRAME_HEADER_SIZE;
+        let mut compressed = compress_frame(&mut buf).unwrap().unwrap();
+        assert!(compressed.length() < buf.length());
+        tls.push_frame(&mut compressed);
+
+        let mut packet = MByteBuffer::new().unwrap();
+
+        assert_eq!(
+            decode_frame(poller.channels[CHANNEL_TLS].buffer(), &mut packet)
+                .unwrap(),
+            FrameStatus::Ready(body_len)
+        );
+        assert_eq!(
+            packet.read::<ServerPackets>().unwrap(),
+            ServerPackets::ChatMsg
+        );
+        assert_eq!(packet.read_slice(2048).unwrap(), &[42u8; 2048][..]);
+    }
+
+    #[test]
+    fn metrics_count_compressed_bytes_as_sent() {
+        let (mut poller, tls, _plain) = Poller::loopback().unwrap();
+        let mut message = MByteBuffer::new_packet().unwrap();
+        message.write(ClientPacket::Message).unwrap();
+        message.write_slice(&[42u8; 2048]).unwrap();
+        message.finish().unwrap();
+        let length = message.length();
+
+        poller.capabilities = Capabilities::COMPRESSION;
+        poller.send_direct(message, CHANNEL_TLS).unwrap();
+
+        let sent = tls.take_sent();
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].len() < length);
+        assert_eq!(
+            poller.metrics.outbound[&ClientPacket::Message].bytes,
+            sent[0].len() as u64
+        );
+    }
+
+    #[test]
+    fn held_frames_resend_without_being_counted_again() {
+        let (mut poller, tls, plain) = Poller::loopback().unwrap();
+        let mut message = MByteBuffer::new_packet().unwrap();
+        message.write(ClientPacket::Message).unwrap();
+        message.write_slice(&[42u8; 2048]).unwrap();
+        message.finish().unwrap();
+
+        // Taken back from the transport after compression, while the
+        // resumed session did not enable it.
+        let wire = compress_frame(&mut message).unwrap().unwrap();
+        poller.reconnector.hold(CHANNEL_TLS, wire, true);
+        poller.reconnector.phase = ReconnectPhase::Waiting {
+            attempt: 1,
+            retry_at: 0.0,
+        };
+        send_move(&mut poller, Some(2), 7).unwrap();
+
+        poller.resume_session().unwrap();
+        poller.flush_sends().unwrap();
+
+        let sent = tls.take_sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].as_slice(), message.as_slice());
+
+        let sent = plain.take_sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(
+            read_sent(&sent[0]).read::<ClientPacket>().unwrap(),
+            ClientPacket::Move
+        );
+
+        assert_eq!(poller.metrics.outbound.len(), 1);
+        assert!(poller.metrics.outbound.contains_key(&ClientPacket::Move));
+        assert_eq!(poller.compression.saved_out, 0);
+    }
+}
@@ -0,0 +1,215 @@
+use crate::{MyInstant, Result};
+use ahash::AHashMap;
+use std::{
+    fmt::Debug,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    time::Duration,
+};
+
+use super::{ClientPacket, ServerPackets};
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct PacketStat {
+    pub frames: u64,
+    /// Bytes on the wire, length prefix included.
+    pub bytes: u64,
+    /// Time spent in handle_data. Always zero for outbound packets.
+    pub handler_time: Duration,
+}
+
+impl PacketStat {
+    fn add(&mut self, bytes: usize, handler_time: Duration) {
+        self.frames += 1;
+        self.bytes += bytes as u64;
+        self.handler_time += handler_time;
+    }
+}
+
+/// Per packet type traffic counters for the current run.
+pub struct NetMetrics {
+    pub inbound: AHashMap<ServerPackets, PacketStat>,
+    pub outbound: AHashMap<ClientPacket, PacketStat>,
+    pub start: MyInstant,
+}
+
+impl Default for NetMetrics {
+    fn default() -> Self {
+        NetMetrics {
+            inbound: AHashMap::default(),
+            outbound: AHashMap::default(),
+            start: MyInstant::now(),
+        }
+    }
+}
+
+fn total<K>(stats: &AHashMap<K, PacketStat>) -> PacketStat {
+    stats.values().fold(PacketStat::default(), |mut sum, stat| {
+        sum.frames += stat.frames;
+        sum.bytes += stat.bytes;
+        sum.handler_time += stat.handler_time;
+        sum
+    })
+}
+
+/// Sorted by bytes, largest first.
+fn sorted<K: Copy>(stats: &AHashMap<K, PacketStat>) -> Vec<(K, PacketStat)> {
+    let mut list: Vec<(K, PacketStat)> =
+        stats.iter().map(|(id, stat)| (*id, *stat)).collect();
+
+    list.sort_by(|a, b| b.1.bytes.cmp(&a.1.bytes));
+    list
+}
+
+impl NetMetrics {
+    pub fn record_in(
+        &mut self,
+        id: ServerPackets,
+        bytes: usize,
+        handler_time: Duration,
+    ) {
+        self.inbound.entry(id).or_default().add(bytes, handler_time);
+    }
+
+    pub fn record_out(&mut self, id: ClientPacket, bytes: usize) {
+        self.outbound
+            .entry(id)
+            .or_default()
+            .add(bytes, Duration::ZERO);
+    }
+
+    pub fn total_in(&self) -> PacketStat {
+        total(&self.inbound)
+    }
+
+    pub fn total_out(&self) -> PacketStat {
+        total(&self.outbound)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inbound.is_empty() && self.outbound.is_empty()
+    }
+
+    /// Inbound packet types, largest byte count first.
+    pub fn top_inbound(&self) -> Vec<(ServerPackets, PacketStat)> {
+        sorted(&self.inbound)
+    }
+
+    pub fn top_outbound(&self) -> Vec<(ClientPacket, PacketStat)> {
+        sorted(&self.outbound)
+    }
+
+    /// Average bytes per second since the counters started.
+    pub fn rates(&self) -> (f64, f64) {
+        let secs = self.start.elapsed().as_secs_f64().max(1.0);
+
+        (
+            self.total_in().bytes as f64 / secs,
+            self.total_out().bytes as f64 / secs,
+        )
+    }
+
+    pub fn export_csv(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+
+        writeln!(file, "direction,packet,frames,bytes,handler_us")?;
+        write_rows(&mut file, "in", &self.top_inbound())?;
+        write_rows(&mut file, "out", &self.top_outbound())?;
+        file.flush()?;
+
+        Ok(())
+    }
+}
+
+fn write_rows<K: Debug>(
+    file: &mut impl Write,
+    direction: &str,
+    rows: &[(K, PacketStat)],
+) -> Result<()> {
+    for (id, stat) in rows {
+        writeln!(
+            file,
+            "{},{:?},{},{},{}",
+            direction,
+            id,
+            stat.frames,
+            stat.bytes,
+            stat.handler_time.as_micros()
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn records_add_up_per_packet_and_in_total() {
+        let mut metrics = NetMetrics::default();
+        assert!(metrics.is_empty());
+
+        let ms = Duration::from_millis;
+        metrics.record_in(ServerPackets::Move, 40, ms(1));
+        metrics.record_in(ServerPackets::Move, 40, ms(2));
+        metrics.record_in(ServerPackets::ChatMsg, 300, ms(1));
+        metrics.record_out(ClientPacket::Move, 20);
+        metrics.record_out(ClientPacket::Move, 20);
+
+        assert_eq!(
+            metrics.inbound[&ServerPackets::Move],
+            PacketStat {
+                frames: 2,
+                bytes: 80,
+                handler_time: ms(3),
+            }
+        );
+        assert_eq!(
+            metrics.total_in(),
+            PacketStat {
+                frames: 3,
+                bytes: 380,
+                handler_time: ms(4),
+            }
+        );
+        assert_eq!(
+            metrics.total_out(),
+            PacketStat {
+                frames: 2,
+                bytes: 40,
+                handler_time: Duration::ZERO,
+            }
+        );
+
+        let top: Vec<ServerPackets> =
+            metrics.top_inbound().iter().map(|(id, _)| *id).collect();
+        assert_eq!(top, vec![ServerPackets::ChatMsg, ServerPackets::Move]);
+    }
+
+    #[test]
+    fn csv_has_a_header_and_a_row_per_packet() {
+        let mut metrics = NetMetrics::default();
+        metrics.record_in(ServerPackets::Move, 80, Duration::from_micros(7));
+        metrics.record_in(ServerPackets::ChatMsg, 300, Duration::ZERO);
+        metrics.record_out(ClientPacket::Move, 20);
+
+        let path = std::env::temp_dir()
+            .join(format!("ascending_metrics_{}.csv", std::process::id()));
+        metrics.export_csv(&path).unwrap();
+        let csv = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(
+            csv.lines().collect::<Vec<_>>(),
+            vec![
+                "direction,packet,frames,bytes,handler_us",
+                "in,ChatMsg,1,300,0",
+                "in,Move,1,80,7",
+                "out,Move,1,20,0",
+            ]
+        );
+    }
+}
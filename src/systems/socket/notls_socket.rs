@@ -1,6 +1,5 @@
 use crate::{
-    Alert, BufferTask, ClientError, Content, Result, SERVER_ID, SERVER_PORT,
-    SystemHolder, config::*,
+    Alert, BufferTask, ClientError, Content, Result, SystemHolder, config::*,
 };
 pub use bytey::{ByteBuffer, ByteBufferError, ByteBufferRead, ByteBufferWrite};
 
@@ -27,6 +26,7 @@ use std::{
 
 use super::PacketRouter;
 use super::states::PollState;
+use super::transport::{ChannelId, Transport};
 use super::{ClientState, bufer_ext::ByteBufferExt};
 
 pub struct Socket {
@@ -39,13 +39,16 @@ pub struct Socket {
 }
 
 impl Socket {
-    pub fn new(poll: &mio::Poll) -> Result<Self> {
-        let host = SERVER_ID;
-        let socket = connect(host, SERVER_PORT)?;
+    pub fn new(
+        poll: &mio::Poll,
+        endpoint: &ServerEndpoint,
+        channel: ChannelId,
+    ) -> Result<Self> {
+        let socket = connect(&endpoint.host, endpoint.port)?;
 
         let mut socket = Socket {
             socket,
-            token: mio::Token(1),
+            token: mio::Token(channel),
             state: ClientState::New,
             sends: VecDeque::with_capacity(32),
             buffer: ByteBuffer::new_packet_with(8192)?,
@@ -57,110 +60,11 @@ impl Socket {
         Ok(socket)
     }
 
-    pub fn clear_sends(&mut self) {
-        self.sends.clear();
-    }
-
     pub fn clear_buffer(&mut self) -> Result<()> {
         self.buffer.truncate(0)?;
         Ok(())
     }
 
-    pub fn reconnect(&mut self, poll: &mio::Poll) -> Result<()> {
-        let host = SERVER_ID;
-        let socket = connect(host, SERVER_PORT)?;
-
-        self.socket = socket;
-        self.state = ClientState::New;
-        self.poll_state = PollState::ReadWrite;
-        self.clear_sends();
-        self.clear_buffer()?;
-        self.register(poll)?;
-
-        Ok(())
-    }
-
-    #[inline]
-    #[allow(dead_code)]
-    /// this should properly Close the socket.
-    pub fn set_to_closing(&mut self, poll: &mio::Poll) {
-        self.state = ClientState::Closing;
-        self.reregister(poll).unwrap();
-    }
-
-    pub fn shutdown(&mut self, poll: &mio::Poll) -> Result<()> {
-        poll.registry().deregister(&mut self.socket)?;
-        self.state = ClientState::Closed;
-
-        Ok(())
-    }
-
-    pub fn process(
-        &mut self,
-        event: &mio::event::Event,
-        poll: &mio::Poll,
-    ) -> Result<()> {
-        if self.state == ClientState::New
-            && (event.is_writable() || event.is_readable())
-        {
-            match self.socket.take_error() {
-                Ok(None) => match self.socket.peer_addr() {
-                    Ok(_) => {
-                        self.state = ClientState::Open;
-                        self.socket.set_nodelay(true)?;
-                    }
-                    Err(err) => {
-                        if let Some(os_err) = err.raw_os_error() {
-                            if os_err == 115 {
-                                self.reregister(poll)?;
-                                return Ok(());
-                            } else {
-                                log::error!(
-                                    "Connection OS Error id: {}",
-                                    os_err
-                                );
-                                return self.shutdown(poll);
-                            }
-                        }
-
-                        if err.kind() == ErrorKind::NotConnected {
-                            self.reregister(poll)?;
-                            return Ok(());
-                        } else {
-                            log::error!(
-                                "Connection Peer Address Error Kind: {:?}",
-                                err.kind()
-                            );
-                            return self.shutdown(poll);
-                        }
-                    }
-                },
-                Ok(Some(err)) | Err(err) => {
-                    log::error!("Connection Error: {:?}", err);
-                    return self.shutdown(poll);
-                }
-            }
-        }
-
-        if event.is_readable() {
-            self.read()?;
-        }
-
-        if event.is_writable() {
-            self.write();
-        }
-
-        match self.state {
-            ClientState::Closing => {
-                poll.registry().deregister(&mut self.socket)?;
-                self.state = ClientState::Closed;
-            }
-            _ => self.reregister(poll)?,
-        }
-
-        Ok(())
-    }
-
     fn read(&mut self) -> Result<()> {
         let pos = self.buffer.cursor();
         self.buffer.move_cursor_to_end();
@@ -248,29 +152,124 @@ impl Socket {
 
         Ok(())
     }
+}
+
+impl Transport for Socket {
+    fn state(&self) -> ClientState {
+        self.state
+    }
 
-    #[inline]
-    #[allow(dead_code)]
-    pub fn send(&mut self, buf: MByteBuffer, poll: &mio::Poll) -> Result<()> {
+    fn process(
+        &mut self,
+        event: &mio::event::Event,
+        poll: &Poll,
+    ) -> Result<()> {
+        if self.state == ClientState::New
+            && (event.is_writable() || event.is_readable())
+        {
+            match self.socket.take_error() {
+                Ok(None) => match self.socket.peer_addr() {
+                    Ok(_) => {
+                        self.state = ClientState::Open;
+                        self.socket.set_nodelay(true)?;
+                    }
+                    Err(err) => {
+                        if let Some(os_err) = err.raw_os_error() {
+                            if os_err == 115 {
+                                self.reregister(poll)?;
+                                return Ok(());
+                            } else {
+                                log::error!(
+                                    "Connection OS Error id: {}",
+                                    os_err
+                                );
+                                return self.shutdown(poll);
+                            }
+                        }
+
+                        if err.kind() == ErrorKind::NotConnected {
+                            self.reregister(poll)?;
+                            return Ok(());
+                        } else {
+                            log::error!(
+                                "Connection Peer Address Error Kind: {:?}",
+                                err.kind()
+                            );
+                            return self.shutdown(poll);
+                        }
+                    }
+                },
+                Ok(Some(err)) | Err(err) => {
+                    log::error!("Connection Error: {:?}", err);
+                    return self.shutdown(poll);
+                }
+            }
+        }
+
+        if event.is_readable() {
+            self.read()?;
+        }
+
+        if event.is_writable() {
+            self.write();
+        }
+
+        match self.state {
+            ClientState::Closing => {
+                poll.registry().deregister(&mut self.socket)?;
+                self.state = ClientState::Closed;
+            }
+            _ => self.reregister(poll)?,
+        }
+
+        Ok(())
+    }
+
+    fn send(&mut self, buf: MByteBuffer, poll: &Poll) -> Result<()> {
         self.sends.push_back(buf);
         self.poll_state.add(PollState::Write);
         self.reregister(poll)
     }
 
-    pub fn get_length(&mut self, poll: &Poll) -> Option<u64> {
-        if self.buffer.length() - self.buffer.cursor() >= 8 {
-            let length = self.buffer.read::<u64>().ok()?;
+    fn set_to_closing(&mut self, poll: &Poll) {
+        self.state = ClientState::Closing;
+        self.reregister(poll).unwrap();
+    }
 
-            trace!("Length is {}", length);
-            if !(2..=8192).contains(&length) {
-                log::error!("Disconnected on packet get_length");
-                self.set_to_closing(poll);
-            }
+    fn shutdown(&mut self, poll: &Poll) -> Result<()> {
+        poll.registry().deregister(&mut self.socket)?;
+        self.state = ClientState::Closed;
 
-            Some(length)
-        } else {
-            None
-        }
+        Ok(())
+    }
+
+    fn reconnect(
+        &mut self,
+        poll: &Poll,
+        endpoint: &ServerEndpoint,
+    ) -> Result<()> {
+        let socket = connect(&endpoint.host, endpoint.port)?;
+
+        self.socket = socket;
+        self.state = ClientState::New;
+        self.poll_state = PollState::ReadWrite;
+        self.clear_sends();
+        self.clear_buffer()?;
+        self.register(poll)?;
+
+        Ok(())
+    }
+
+    fn clear_sends(&mut self) {
+        self.sends.clear();
+    }
+
+    fn take_sends(&mut self) -> VecDeque<MByteBuffer> {
+        std::mem::take(&mut self.sends)
+    }
+
+    fn buffer(&mut self) -> &mut ByteBuffer {
+        &mut self.buffer
     }
 }
 
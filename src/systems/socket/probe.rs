@@ -0,0 +1,218 @@
+use crate::{ClientError, MyInstant, Result, config::*};
+use mio::{Events, Poll};
+use rustls::ClientConfig;
+use snafu::Backtrace;
+use std::{
+    sync::{
+        Arc,
+        mpsc::{self, Receiver, TryRecvError},
+    },
+    thread,
+    time::Duration,
+};
+
+use super::{
+    ByteBuffer, CHANNEL_TLS, FrameStatus, MByteBuffer, MByteBufferRead,
+    ServerPackets, Transport, decode_frame, online_check_packet,
+    states::ClientState, tls_socket::TlsSocket,
+};
+
+/// How long a realm has to answer the OnlineCheck before it shows offline.
+pub const PROBE_TIMEOUT_MS: u128 = 3000;
+/// How long the probe thread waits on its socket between checks.
+const PROBE_POLL_MS: u64 = 10;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RealmStatus {
+    Checking,
+    /// Round trip time in milliseconds.
+    Online(u64),
+    Offline,
+}
+
+/// Sends a single OnlineCheck to a realm over its own TLS connection and
+/// measures how long the reply takes. The lookup, connect and wait run on
+/// their own thread so a slow realm never stalls the menu. The TLS config
+/// is built by the caller, so the CA bundle and client identity are read
+/// once for all realms.
+pub struct RealmProbe {
+    result: Option<Receiver<RealmStatus>>,
+    pub status: RealmStatus,
+}
+
+impl RealmProbe {
+    pub fn new(
+        endpoint: &ServerEndpoint,
+        tls_config: Arc<ClientConfig>,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let endpoint = endpoint.clone();
+        let spawned = thread::Builder::new()
+            .name(format!("probe {}", endpoint.host))
+            .spawn(move || {
+                // The menu may have dropped the probe in the meantime.
+                let _ = sender.send(run_probe(&endpoint, tls_config));
+            });
+
+        match spawned {
+            Ok(_) => RealmProbe {
+                result: Some(receiver),
+                status: RealmStatus::Checking,
+            },
+            Err(e) => {
+                log::warn!("Could not start realm probe thread: {}", e);
+                RealmProbe::offline()
+            }
+        }
+    }
+
+    /// A probe that was never sent, for when there is no TLS config.
+    pub fn offline() -> Self {
+        RealmProbe {
+            result: None,
+            status: RealmStatus::Offline,
+        }
+    }
+
+    /// Checks whether the probe thread is done. Returns true when the
+    /// status changed.
+    pub fn update(&mut self) -> bool {
+        let Some(receiver) = &self.result else {
+            return false;
+        };
+
+        self.status = match receiver.try_recv() {
+            Ok(status) => status,
+            Err(TryRecvError::Empty) => return false,
+            Err(TryRecvError::Disconnected) => RealmStatus::Offline,
+        };
+        self.result = None;
+
+        true
+    }
+}
+
+fn open_probe(
+    endpoint: &ServerEndpoint,
+    tls_config: Arc<ClientConfig>,
+) -> Result<(Poll, TlsSocket)> {
+    let poll = Poll::new()?;
+    let mut socket =
+        TlsSocket::with_config(&poll, endpoint, CHANNEL_TLS, tls_config)?;
+
+    socket.send(online_check_packet()?, &poll)?;
+
+    Ok((poll, socket))
+}
+
+fn run_probe(
+    endpoint: &ServerEndpoint,
+    tls_config: Arc<ClientConfig>,
+) -> RealmStatus {
+    let start = MyInstant::now();
+    let (mut poll, mut socket) = match open_probe(endpoint, tls_config) {
+        Ok(connection) => connection,
+        Err(e) => {
+            log::warn!("Realm probe to {} failed: {}", endpoint.host, e);
+            return RealmStatus::Offline;
+        }
+    };
+
+    let status = wait_for_reply(&mut poll, &mut socket, start);
+
+    if socket.state != ClientState::Closed {
+        let _ = socket.shutdown(&poll);
+    }
+
+    status
+}
+
+fn wait_for_reply(
+    poll: &mut Poll,
+    socket: &mut TlsSocket,
+    start: MyInstant,
+) -> RealmStatus {
+    let mut events = Events::with_capacity(8);
+
+    loop {
+        let wait = Duration::from_millis(PROBE_POLL_MS);
+
+        if poll.poll(&mut events, Some(wait)).is_err() {
+            return RealmStatus::Offline;
+        }
+
+        for event in events.iter() {
+            if socket.process(event, poll).is_err() {
+                return RealmStatus::Offline;
+            }
+        }
+
+        let elapsed = start.elapsed().as_millis();
+        let Ok(answered) = read_reply(socket.buffer()) else {
+            return RealmStatus::Offline;
+        };
+
+        if socket.state == ClientState::Closed {
+            return RealmStatus::Offline;
+        } else if answered {
+            return RealmStatus::Online(elapsed as u64);
+        } else if elapsed > PROBE_TIMEOUT_MS {
+            return RealmStatus::Offline;
+        }
+    }
+}
+
+/// Reads every whole frame the realm sent so far. True once one of them is
+/// the OnlineCheck reply, anything the realm sent before it is skipped.
+fn read_reply(buffer: &mut ByteBuffer) -> Result<bool> {
+    let mut packet = MByteBuffer::new()?;
+
+    loop {
+        match decode_frame(buffer, &mut packet)? {
+            FrameStatus::Ready(_) => {
+                if packet.read::<ServerPackets>()? == ServerPackets::OnlineCheck
+                {
+                    return Ok(true);
+                }
+            }
+            FrameStatus::Incomplete => return Ok(false),
+            FrameStatus::Malformed => {
+                return Err(ClientError::InvalidPacket {
+                    backtrace: Backtrace::new(),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::socket::{MByteBufferWrite, bufer_ext::MByteBufferExt};
+
+    fn frame(id: ServerPackets) -> MByteBuffer {
+        let mut buf = MByteBuffer::new_packet().unwrap();
+        buf.write(id).unwrap();
+        buf.write(0u32).unwrap();
+        buf.finish().unwrap();
+        buf
+    }
+
+    #[test]
+    fn only_the_online_check_counts_as_the_reply() {
+        let mut buffer = ByteBuffer::with_capacity(64).unwrap();
+
+        for id in [ServerPackets::Ping, ServerPackets::AlertMsg] {
+            buffer.write_slice(frame(id).as_slice()).unwrap();
+        }
+        buffer.move_cursor(0).unwrap();
+        assert!(!read_reply(&mut buffer).unwrap());
+
+        let at = buffer.cursor();
+        buffer
+            .write_slice(frame(ServerPackets::OnlineCheck).as_slice())
+            .unwrap();
+        buffer.move_cursor(at).unwrap();
+        assert!(read_reply(&mut buffer).unwrap());
+    }
+}
@@ -0,0 +1,118 @@
+use super::{MByteBufferRead, MByteBufferWrite, ServerPackets};
+
+/// Bumped whenever a packet layout changes in a way older clients or
+/// servers can not read.
+pub const PROTOCOL_VERSION: u16 = 1;
+
+/// Optional packets and features, one bit each. The client sends what it
+/// supports in the TLS handshake and the server answers with the subset it
+/// enabled for this connection.
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    MByteBufferRead,
+    MByteBufferWrite,
+)]
+pub struct Capabilities(pub u32);
+
+impl Capabilities {
+    pub const NONE: Capabilities = Capabilities(0);
+    /// ServerPackets::PlayItemSfx. Servers from before the handshake send it
+    /// anyway, so it is accepted without the bit.
+    pub const ITEM_SFX: Capabilities = Capabilities(1);
+    /// ServerPackets::Damage, accepted without the bit like ITEM_SFX.
+    pub const DAMAGE_TEXT: Capabilities = Capabilities(1 << 1);
+    /// LZ4 compressed frames, see FRAME_COMPRESSED.
+    pub const COMPRESSION: Capabilities = Capabilities(1 << 2);
+    /// Revocable "remember me" login tokens, see ServerPackets::LoginToken.
+    pub const LOGIN_TOKEN: Capabilities = Capabilities(1 << 3);
+    /// ServerPackets::Move entries end with the server time in
+    /// milliseconds.
+    pub const MOVE_TIME: Capabilities = Capabilities(1 << 4);
+    /// The TLS handshake ends with the client's content hash. The server
+    /// answers with ServerPackets::ContentCurrent when it matches, or else
+    /// with ServerPackets::ContentManifest.
+    pub const CONTENT_SYNC: Capabilities = Capabilities(1 << 5);
+    /// ClientPacket::Move ends with a sequence number. The server echoes it
+    /// at the end of ServerPackets::MoveOk and of the Move entry answering
+    /// it, after the MOVE_TIME stamp, while other entries carry None.
+    pub const MOVE_SEQ: Capabilities = Capabilities(1 << 6);
+
+    /// Everything this client build understands.
+    pub const fn client() -> Capabilities {
+        Capabilities(
+            Self::ITEM_SFX.0
+                | Self::DAMAGE_TEXT.0
+                | Self::COMPRESSION.0
+                | Self::LOGIN_TOKEN.0
+                | Self::MOVE_TIME.0
+                | Self::CONTENT_SYNC.0
+                | Self::MOVE_SEQ.0,
+        )
+    }
+
+    #[inline]
+    pub fn contains(self, other: Capabilities) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    #[inline]
+    pub fn intersection(self, other: Capabilities) -> Capabilities {
+        Capabilities(self.0 & other.0)
+    }
+
+    /// Returns the capability a packet needs, if it is optional. Only
+    /// packets added after the handshake are gated, older ones must keep
+    /// working with servers that never enable anything.
+    pub fn required_for(id: ServerPackets) -> Option<Capabilities> {
+        match id {
+            ServerPackets::LoginToken | ServerPackets::LoginTokenRejected => {
+                Some(Self::LOGIN_TOKEN)
+            }
+            ServerPackets::ContentManifest
+            | ServerPackets::ContentChunk
+            | ServerPackets::ContentCurrent => Some(Self::CONTENT_SYNC),
+            _ => None,
+        }
+    }
+
+    /// True when the packet is not optional or was enabled by the server.
+    pub fn allows(self, id: ServerPackets) -> bool {
+        Self::required_for(id).is_none_or(|needed| self.contains(needed))
+    }
+}
+
+/// What the server told us about its protocol after the TLS handshake.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ProtocolInfo {
+    pub server_version: u16,
+    /// Oldest client protocol the server still accepts.
+    pub min_version: u16,
+    pub enabled: Capabilities,
+}
+
+impl ProtocolInfo {
+    pub fn is_compatible(&self) -> bool {
+        (self.min_version..=self.server_version).contains(&PROTOCOL_VERSION)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_new_packets_need_a_capability() {
+        let none = Capabilities::NONE;
+
+        assert!(none.allows(ServerPackets::PlayItemSfx));
+        assert!(none.allows(ServerPackets::Damage));
+        assert!(!none.allows(ServerPackets::LoginToken));
+        assert!(!none.allows(ServerPackets::ContentCurrent));
+        assert!(Capabilities::client().allows(ServerPackets::ContentChunk));
+    }
+}
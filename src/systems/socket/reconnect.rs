@@ -0,0 +1,369 @@
+use crate::{
+    Alert, AlertIndex, AlertType, ClientError, Content, ContentType,
+    ReconnectOverlay, Result, SystemHolder,
+};
+use rand::{Rng, thread_rng};
+use std::collections::VecDeque;
+
+use super::{
+    CHANNEL_PLAIN, CHANNEL_TLS, ChannelId, MByteBuffer, Poller, send_reconnect,
+    send_tls_reconnect,
+};
+
+/// Delay before the first retry, doubled on every failed attempt.
+pub const RECONNECT_BASE_DELAY: f32 = 0.5;
+/// Upper bound for the backoff delay.
+pub const RECONNECT_MAX_DELAY: f32 = 16.0;
+/// Attempts to resume a game session before falling back to login.
+pub const RECONNECT_MAX_ATTEMPTS: u32 = 6;
+/// How long the server has to answer a reconnect code.
+pub const RESUME_TIMEOUT: f32 = 5.0;
+/// Sends held while a channel is down, oldest dropped first.
+pub const MAX_HELD_SENDS: usize = 64;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReconnectPhase {
+    Connected,
+    /// Waiting out the backoff before the next attempt.
+    Waiting {
+        attempt: u32,
+        retry_at: f32,
+    },
+    /// Sockets are back up and the reconnect code was sent.
+    Resuming {
+        attempt: u32,
+        timeout_at: f32,
+    },
+}
+
+/// A send held while its channel was down.
+pub struct HeldSend {
+    pub channel: ChannelId,
+    pub buf: MByteBuffer,
+    /// Taken back from a transport, so send_direct already compressed and
+    /// counted it. Written again as it is instead of queued.
+    pub on_wire: bool,
+}
+
+/// Tracks lost connections and holds game sends made while a channel was
+/// down so they can be sent once the session was resumed.
+pub struct Reconnector {
+    pub phase: ReconnectPhase,
+    held: VecDeque<HeldSend>,
+}
+
+impl Default for Reconnector {
+    fn default() -> Self {
+        Reconnector {
+            phase: ReconnectPhase::Connected,
+            held: VecDeque::with_capacity(8),
+        }
+    }
+}
+
+/// Capped exponential backoff with up to 25% jitter so many clients do not
+/// hammer a restarted server at the same moment.
+pub fn backoff_delay(attempt: u32) -> f32 {
+    let delay = (RECONNECT_BASE_DELAY * 2f32.powi(attempt.min(16) as i32))
+        .min(RECONNECT_MAX_DELAY);
+
+    delay + thread_rng().gen_range(0.0..=delay * 0.25)
+}
+
+impl Reconnector {
+    #[inline]
+    pub fn is_reconnecting(&self) -> bool {
+        self.phase != ReconnectPhase::Connected
+    }
+
+    /// The attempt shown to the player, if a reconnect is in progress.
+    pub fn attempt(&self) -> Option<u32> {
+        match self.phase {
+            ReconnectPhase::Connected => None,
+            ReconnectPhase::Waiting { attempt, .. }
+            | ReconnectPhase::Resuming { attempt, .. } => Some(attempt),
+        }
+    }
+
+    pub fn hold(
+        &mut self,
+        channel: ChannelId,
+        buf: MByteBuffer,
+        on_wire: bool,
+    ) {
+        if self.held.len() >= MAX_HELD_SENDS {
+            self.held.pop_front();
+        }
+
+        self.held.push_back(HeldSend {
+            channel,
+            buf,
+            on_wire,
+        });
+    }
+
+    pub fn take_held(&mut self) -> VecDeque<HeldSend> {
+        std::mem::take(&mut self.held)
+    }
+
+    /// Forgets everything, used when falling back to the login menu.
+    pub fn reset(&mut self) {
+        self.phase = ReconnectPhase::Connected;
+        self.held.clear();
+    }
+
+    fn schedule(&mut self, attempt: u32, seconds: f32) {
+        self.phase = ReconnectPhase::Waiting {
+            attempt,
+            retry_at: seconds + backoff_delay(attempt.saturating_sub(1)),
+        };
+    }
+}
+
+impl Poller {
+    /// Called once the server accepted our reconnect code, or once the
+    /// channels are back up when there is no session to resume. Sends
+    /// whatever was held while the connection was down.
+    pub fn resume_session(&mut self) -> Result<()> {
+        if !self.reconnector.is_reconnecting() {
+            return Ok(());
+        }
+
+        self.reconnector.phase = ReconnectPhase::Connected;
+
+        let held = self.reconnector.take_held();
+
+        if !held.is_empty() {
+            log::info!("Resending {} held actions", held.len());
+        }
+
+        for held in held {
+            if held.on_wire {
+                self.resend_frame(held.buf, held.channel)?;
+            } else {
+                self.send(held.buf, held.channel)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn fall_back_to_login(
+    socket: &mut Poller,
+    systems: &mut SystemHolder,
+    alert: &mut Alert,
+) {
+    socket.reconnector.reset();
+    systems.config.reconnect_code.clear();
+    systems.config.save_config("settings.toml");
+
+    alert.show_alert(
+        systems,
+        AlertType::Inform,
+        "You have been disconnected".into(),
+        "Alert Message".into(),
+        250,
+        AlertIndex::Disconnect,
+        false,
+    );
+}
+
+/// Called by the server handler when the reconnect code was refused.
+pub fn reject_reconnect(
+    socket: &mut Poller,
+    systems: &mut SystemHolder,
+    alert: &mut Alert,
+) {
+    log::warn!("Server rejected the reconnect code");
+    fall_back_to_login(socket, systems, alert);
+}
+
+/// Retrying can not fix a refused certificate, so drop the connection and
+/// tell the player why.
+fn refuse_server(
+    socket: &mut Poller,
+    systems: &mut SystemHolder,
+    alert: &mut Alert,
+    reason: &str,
+) {
+    let host = socket
+        .endpoint
+        .as_ref()
+        .map(|endpoint| endpoint.server_name.clone())
+        .unwrap_or_default();
+
+    log::error!("Refusing {}, {}", host, reason);
+    socket.disconnect();
+
+    alert.show_alert(
+        systems,
+        AlertType::Inform,
+        format!("Could not verify the server, {reason}."),
+        "Connection Not Secure".into(),
+        250,
+        AlertIndex::Disconnect,
+        false,
+    );
+}
+
+/// Tells the player why connecting to `server` failed. A TLS trust setup
+/// error, such as an unreadable `ca_bundle`, is shown like a refused
+/// certificate.
+pub fn alert_connect_error(
+    systems: &mut SystemHolder,
+    alert: &mut Alert,
+    error: &ClientError,
+    server: &str,
+) {
+    let (msg, title) = match error {
+        ClientError::TlsTrust { message, .. } => (
+            format!("Could not set up a secure connection, {message}."),
+            "Connection Not Secure",
+        ),
+        _ => (format!("Unable to reach {server}"), "Alert Message"),
+    };
+
+    alert.show_alert(
+        systems,
+        AlertType::Inform,
+        msg,
+        title.into(),
+        250,
+        AlertIndex::None,
+        false,
+    );
+}
+
+/// Drives the reconnection state machine once per frame.
+pub fn update_reconnect(
+    socket: &mut Poller,
+    systems: &mut SystemHolder,
+    content: &mut Content,
+    alert: &mut Alert,
+    overlay: &mut ReconnectOverlay,
+    seconds: f32,
+) {
+    if let Some(reason) = socket.take_trust_error() {
+        refuse_server(socket, systems, alert, reason);
+        overlay.hide(systems);
+        return;
+    }
+
+    let lost = socket.is_closed(CHANNEL_TLS) || socket.is_closed(CHANNEL_PLAIN);
+    let in_game = content.content_type == ContentType::Game;
+
+    // The server forgets a check or download when the channel drops, and
+    // asks again after the next handshake.
+    if lost {
+        socket.content_sync.reset();
+    }
+
+    match socket.reconnector.phase {
+        ReconnectPhase::Connected => {
+            if lost {
+                log::warn!("Connection lost, reconnecting");
+                socket.reconnector.schedule(1, seconds);
+            }
+        }
+        ReconnectPhase::Waiting { attempt, retry_at } => {
+            if retry_at <= seconds {
+                try_reconnect(
+                    socket, systems, alert, attempt, in_game, seconds,
+                );
+            }
+        }
+        ReconnectPhase::Resuming {
+            attempt,
+            timeout_at,
+        } => {
+            if lost || timeout_at < seconds {
+                log::warn!("Resume attempt {} got no answer", attempt);
+                socket.reconnector.schedule(attempt + 1, seconds);
+            }
+        }
+    }
+
+    match socket.reconnector.attempt() {
+        Some(attempt) if in_game => overlay.show(systems, attempt),
+        _ => overlay.hide(systems),
+    }
+}
+
+fn try_reconnect(
+    socket: &mut Poller,
+    systems: &mut SystemHolder,
+    alert: &mut Alert,
+    attempt: u32,
+    in_game: bool,
+    seconds: f32,
+) {
+    if in_game && attempt > RECONNECT_MAX_ATTEMPTS {
+        fall_back_to_login(socket, systems, alert);
+        return;
+    }
+
+    if let Err(e) = reconnect_closed(socket) {
+        log::warn!("Reconnect attempt {} failed: {:?}", attempt, e);
+        socket.reconnector.schedule(attempt + 1, seconds);
+        return;
+    }
+
+    // Nothing to resume in the menu or without a code, the channels being
+    // up is enough. Sends such as a Login made while they were down still
+    // go out.
+    if !in_game || systems.config.reconnect_code.is_empty() {
+        if let Err(e) = socket.resume_session() {
+            log::warn!("Failed to resend held actions: {:?}", e);
+            socket.reconnector.reset();
+        }
+
+        return;
+    }
+
+    let code = systems.config.reconnect_code.clone();
+
+    match send_tls_reconnect(socket, &code)
+        .and_then(|_| send_reconnect(socket, &code))
+    {
+        Ok(_) => {
+            socket.reconnector.phase = ReconnectPhase::Resuming {
+                attempt,
+                timeout_at: seconds + RESUME_TIMEOUT,
+            };
+        }
+        Err(e) => {
+            log::warn!("Failed to send reconnect: {:?}", e);
+            socket.reconnector.schedule(attempt + 1, seconds);
+        }
+    }
+}
+
+fn reconnect_closed(socket: &mut Poller) -> Result<()> {
+    for channel in [CHANNEL_TLS, CHANNEL_PLAIN] {
+        if socket.is_closed(channel) {
+            // Anything still queued never reached the server.
+            socket.hold_sends(channel);
+
+            socket.reconnect(channel)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_grows_and_caps() {
+        for attempt in 0..20 {
+            let base = (RECONNECT_BASE_DELAY * 2f32.powi(attempt))
+                .min(RECONNECT_MAX_DELAY);
+            let delay = backoff_delay(attempt as u32);
+
+            assert!(delay >= base && delay <= base * 1.25);
+        }
+    }
+}
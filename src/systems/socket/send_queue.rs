@@ -0,0 +1,265 @@
+use crate::MyInstant;
+use ahash::{AHashMap, AHashSet};
+use std::{collections::VecDeque, time::Duration};
+
+use super::{ChannelId, ClientPacket, MByteBuffer};
+
+/// Rate limited packets of one kind allowed to wait in the queue. Newer
+/// ones are dropped so a stuck key can not build up a backlog.
+pub const MAX_QUEUED_PER_KIND: usize = 8;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SendPriority {
+    /// Account, session and trade packets. Sent first and never limited.
+    High,
+    Normal,
+}
+
+impl ClientPacket {
+    pub fn priority(self) -> SendPriority {
+        match self {
+            ClientPacket::Register
+            | ClientPacket::Login
+            | ClientPacket::LoginOk
+            | ClientPacket::TokenLogin
+            | ClientPacket::RequestLoginToken
+            | ClientPacket::RevokeLoginToken
+            | ClientPacket::HandShake
+            | ClientPacket::TlsHandShake
+            | ClientPacket::Reconnect
+            | ClientPacket::TlsReconnect
+            | ClientPacket::Disconnect
+            | ClientPacket::AddTradeItem
+            | ClientPacket::RemoveTradeItem
+            | ClientPacket::UpdateTradeMoney
+            | ClientPacket::SubmitTrade
+            | ClientPacket::AcceptTrade
+            | ClientPacket::DeclineTrade
+            | ClientPacket::CloseTrade => SendPriority::High,
+            _ => SendPriority::Normal,
+        }
+    }
+
+    /// Smallest gap between two packets of this kind.
+    pub fn rate_limit(self) -> Option<Duration> {
+        let ms = match self {
+            ClientPacket::Dir => 50,
+            ClientPacket::SetTarget => 100,
+            ClientPacket::Attack => 100,
+            ClientPacket::PickUp => 200,
+            ClientPacket::UseItem | ClientPacket::Unequip => 100,
+            ClientPacket::Message | ClientPacket::Command => 300,
+            ClientPacket::OnlineCheck => 1000,
+            _ => return None,
+        };
+
+        Some(Duration::from_millis(ms))
+    }
+
+    /// Only the newest of several of these in a row matters.
+    pub fn coalesces(self) -> bool {
+        matches!(self, ClientPacket::Dir | ClientPacket::SetTarget)
+    }
+}
+
+pub struct QueuedSend {
+    pub channel: ChannelId,
+    pub id: Option<ClientPacket>,
+    pub buf: MByteBuffer,
+}
+
+/// Sits between the send functions and the transports. Flushed once per
+/// frame by Poller::flush_sends.
+#[derive(Default)]
+pub struct SendQueue {
+    priority: VecDeque<QueuedSend>,
+    normal: VecDeque<QueuedSend>,
+    last_sent: AHashMap<ClientPacket, MyInstant>,
+}
+
+impl SendQueue {
+    pub fn push(
+        &mut self,
+        channel: ChannelId,
+        id: Option<ClientPacket>,
+        buf: MByteBuffer,
+    ) {
+        let item = QueuedSend { channel, id, buf };
+
+        let Some(kind) = id else {
+            self.normal.push_back(item);
+            return;
+        };
+
+        if kind.priority() == SendPriority::High {
+            self.priority.push_back(item);
+            return;
+        }
+
+        // Only the packet at the back is replaced, anything queued after an
+        // older one still has to follow it.
+        if kind.coalesces()
+            && let Some(last) = self.normal.back_mut()
+            && last.channel == channel
+            && last.id == Some(kind)
+        {
+            *last = item;
+            return;
+        }
+
+        if kind.rate_limit().is_some()
+            && self
+                .normal
+                .iter()
+                .filter(|queued| queued.id == Some(kind))
+                .count()
+                >= MAX_QUEUED_PER_KIND
+        {
+            log::warn!("Dropping {:?}, too many waiting to be sent", kind);
+            return;
+        }
+
+        self.normal.push_back(item);
+    }
+
+    pub fn clear_channel(&mut self, channel: ChannelId) {
+        self.priority.retain(|queued| queued.channel != channel);
+        self.normal.retain(|queued| queued.channel != channel);
+    }
+
+    /// Removes the packets waiting for a channel, in the order they would
+    /// have been sent.
+    pub fn take_channel(&mut self, channel: ChannelId) -> Vec<MByteBuffer> {
+        let mut taken = Vec::new();
+
+        for queue in [&mut self.priority, &mut self.normal] {
+            let (ours, rest): (VecDeque<_>, _) = queue
+                .drain(..)
+                .partition(|queued| queued.channel == channel);
+
+            *queue = rest;
+            taken.extend(ours.into_iter().map(|queued| queued.buf));
+        }
+
+        taken
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.priority.is_empty() && self.normal.is_empty()
+    }
+
+    /// Removes and returns every packet that may go out now, priority
+    /// packets first. A rate limited packet also holds back the packets
+    /// queued after it on its channel so their order is kept.
+    pub fn take_ready(&mut self) -> Vec<QueuedSend> {
+        let mut ready: Vec<QueuedSend> = self.priority.drain(..).collect();
+        let mut blocked = AHashSet::default();
+        let mut waiting = VecDeque::with_capacity(self.normal.len());
+
+        for item in self.normal.drain(..) {
+            if blocked.contains(&item.channel) {
+                waiting.push_back(item);
+                continue;
+            }
+
+            if let Some(kind) = item.id {
+                if let Some(limit) = kind.rate_limit() {
+                    if let Some(last) = self.last_sent.get(&kind) {
+                        if last.elapsed() < limit {
+                            blocked.insert(item.channel);
+                            waiting.push_back(item);
+                            continue;
+                        }
+                    }
+
+                    self.last_sent.insert(kind, MyInstant::now());
+                }
+            }
+
+            ready.push(item);
+        }
+
+        self.normal = waiting;
+        ready
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::socket::{
+        CHANNEL_PLAIN, CHANNEL_TLS, MByteBufferWrite, bufer_ext::MByteBufferExt,
+    };
+
+    fn packet(id: ClientPacket, value: u8) -> MByteBuffer {
+        let mut buf = MByteBuffer::new_packet().unwrap();
+        buf.write(id).unwrap();
+        buf.write(value).unwrap();
+        buf.finish().unwrap();
+        buf
+    }
+
+    fn push(queue: &mut SendQueue, id: ClientPacket, value: u8) {
+        queue.push(CHANNEL_PLAIN, Some(id), packet(id, value));
+    }
+
+    #[test]
+    fn dir_updates_coalesce_and_priority_goes_first() {
+        let mut queue = SendQueue::default();
+
+        push(&mut queue, ClientPacket::Dir, 1);
+        push(&mut queue, ClientPacket::Dir, 2);
+        push(&mut queue, ClientPacket::Move, 1);
+        push(&mut queue, ClientPacket::Dir, 3);
+        push(&mut queue, ClientPacket::AcceptTrade, 0);
+
+        let ids: Vec<_> =
+            queue.take_ready().iter().map(|item| item.id).collect();
+
+        // The first Dir is replaced, the last one stays behind the Move.
+        assert_eq!(
+            ids,
+            vec![
+                Some(ClientPacket::AcceptTrade),
+                Some(ClientPacket::Dir),
+                Some(ClientPacket::Move),
+                Some(ClientPacket::Dir),
+            ]
+        );
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn rate_limited_kinds_wait() {
+        let mut queue = SendQueue::default();
+
+        push(&mut queue, ClientPacket::Attack, 0);
+        push(&mut queue, ClientPacket::Attack, 1);
+        push(&mut queue, ClientPacket::Move, 0);
+        push(&mut queue, ClientPacket::Ping, 0);
+        queue.push(
+            CHANNEL_TLS,
+            Some(ClientPacket::Move),
+            packet(ClientPacket::Move, 0),
+        );
+
+        // The second Attack holds back the plain channel's Move and Ping.
+        let ready = queue.take_ready();
+        assert_eq!(ready.len(), 2);
+        assert_eq!(ready[1].channel, CHANNEL_TLS);
+        assert!(queue.take_ready().is_empty());
+        assert_eq!(queue.take_channel(CHANNEL_PLAIN).len(), 3);
+        assert!(queue.is_empty());
+
+        push(&mut queue, ClientPacket::Ping, 0);
+        push(&mut queue, ClientPacket::Ping, 1);
+        assert_eq!(queue.take_ready().len(), 2);
+
+        for _ in 0..MAX_QUEUED_PER_KIND * 2 {
+            push(&mut queue, ClientPacket::Attack, 2);
+        }
+
+        queue.clear_channel(CHANNEL_PLAIN);
+        assert!(queue.is_empty());
+    }
+}
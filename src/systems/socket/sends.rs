@@ -1,4 +1,7 @@
-use crate::{GlobalKey, Position, data_types::*, data_types::*, socket::*};
+use crate::{
+    APP_MAJOR, APP_MINOR, APP_REV, GlobalKey, Position, data_types::*,
+    socket::*,
+};
 
 use super::bufer_ext::MByteBufferExt;
 
@@ -12,9 +15,9 @@ pub enum Command {
 }
 
 #[derive(
-    Copy, Clone, Debug, PartialEq, Eq, MByteBufferRead, MByteBufferWrite,
+    Copy, Clone, Debug, PartialEq, Eq, Hash, MByteBufferRead, MByteBufferWrite,
 )]
-enum ClientPacket {
+pub enum ClientPacket {
     OnlineCheck,
     Register,
     Login,
@@ -52,6 +55,10 @@ enum ClientPacket {
     Reconnect,
     Disconnect,
     LoginOk,
+    TokenLogin,
+    RequestLoginToken,
+    RevokeLoginToken,
+    RequestContent,
 }
 
 pub fn send_register(
@@ -74,7 +81,7 @@ pub fn send_register(
     buf.write(app_version.2)?;
     buf.finish()?;
 
-    socket.send(buf, true)
+    socket.send(buf, CHANNEL_TLS)
 }
 
 pub fn send_login(
@@ -95,7 +102,49 @@ pub fn send_login(
     buf.write(reconnect_code)?;
     buf.finish()?;
 
-    socket.send(buf, true)
+    socket.send(buf, CHANNEL_TLS)
+}
+
+/// Logs in with the token from ServerPackets::LoginToken instead of a
+/// password.
+pub fn send_token_login(
+    socket: &mut Poller,
+    username: String,
+    token: &str,
+    app_version: (u16, u16, u16),
+    reconnect_code: &str,
+) -> Result<()> {
+    let mut buf = MByteBuffer::new_packet()?;
+
+    buf.write(ClientPacket::TokenLogin)?;
+    buf.write(username)?;
+    buf.write(token)?;
+    buf.write(app_version.0)?;
+    buf.write(app_version.1)?;
+    buf.write(app_version.2)?;
+    buf.write(reconnect_code)?;
+    buf.finish()?;
+
+    socket.send(buf, CHANNEL_TLS)
+}
+
+pub fn send_request_login_token(socket: &mut Poller) -> Result<()> {
+    let mut buf = MByteBuffer::new_packet()?;
+
+    buf.write(ClientPacket::RequestLoginToken)?;
+    buf.finish()?;
+
+    socket.send(buf, CHANNEL_TLS)
+}
+
+pub fn send_revoke_login_token(socket: &mut Poller, token: &str) -> Result<()> {
+    let mut buf = MByteBuffer::new_packet()?;
+
+    buf.write(ClientPacket::RevokeLoginToken)?;
+    buf.write(token)?;
+    buf.finish()?;
+
+    socket.send(buf, CHANNEL_TLS)
 }
 
 pub fn send_handshake(socket: &mut Poller, handshake: String) -> Result<()> {
@@ -105,17 +154,23 @@ pub fn send_handshake(socket: &mut Poller, handshake: String) -> Result<()> {
     buf.write(handshake)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send_direct(buf, CHANNEL_PLAIN)
 }
 
-pub fn send_move(socket: &mut Poller, dir: Option<u8>) -> Result<()> {
+/// `seq` is only sent when the server enabled `Capabilities::MOVE_SEQ`.
+pub fn send_move(socket: &mut Poller, dir: Option<u8>, seq: u32) -> Result<()> {
     let mut buf = MByteBuffer::new_packet()?;
 
     buf.write(ClientPacket::Move)?;
     buf.write(dir)?;
+
+    if socket.capabilities.contains(Capabilities::MOVE_SEQ) {
+        buf.write(seq)?;
+    }
+
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
 
 pub fn send_dir(socket: &mut Poller, dir: u8) -> Result<()> {
@@ -125,7 +180,7 @@ pub fn send_dir(socket: &mut Poller, dir: u8) -> Result<()> {
     buf.write(dir)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
 
 pub fn send_attack(
@@ -140,7 +195,7 @@ pub fn send_attack(
     buf.write(entity)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
 
 pub fn send_useitem(socket: &mut Poller, slot: u16) -> Result<()> {
@@ -150,7 +205,7 @@ pub fn send_useitem(socket: &mut Poller, slot: u16) -> Result<()> {
     buf.write(slot)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
 
 pub fn send_unequip(socket: &mut Poller, slot: u16) -> Result<()> {
@@ -160,7 +215,7 @@ pub fn send_unequip(socket: &mut Poller, slot: u16) -> Result<()> {
     buf.write(slot)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
 
 pub fn send_switchinvslot(
@@ -177,7 +232,7 @@ pub fn send_switchinvslot(
     buf.write(amount)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
 
 pub fn send_pickup(socket: &mut Poller) -> Result<()> {
@@ -186,7 +241,7 @@ pub fn send_pickup(socket: &mut Poller) -> Result<()> {
     buf.write(ClientPacket::PickUp)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
 
 pub fn send_dropitem(
@@ -201,7 +256,7 @@ pub fn send_dropitem(
     buf.write(amount)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
 
 pub fn send_deleteitem(socket: &mut Poller, slot: u16) -> Result<()> {
@@ -211,7 +266,7 @@ pub fn send_deleteitem(socket: &mut Poller, slot: u16) -> Result<()> {
     buf.write(slot)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
 
 pub fn send_switchstorageslot(
@@ -228,7 +283,7 @@ pub fn send_switchstorageslot(
     buf.write(amount)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
 
 pub fn send_deletestorageitem(socket: &mut Poller, slot: u16) -> Result<()> {
@@ -238,7 +293,7 @@ pub fn send_deletestorageitem(socket: &mut Poller, slot: u16) -> Result<()> {
     buf.write(slot)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
 
 pub fn send_deposititem(
@@ -255,7 +310,7 @@ pub fn send_deposititem(
     buf.write(amount)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
 
 pub fn send_withdrawitem(
@@ -272,7 +327,7 @@ pub fn send_withdrawitem(
     buf.write(amount)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
 
 pub fn send_message(
@@ -289,7 +344,7 @@ pub fn send_message(
     buf.write(name)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
 
 pub fn send_command(socket: &mut Poller, command: Command) -> Result<()> {
@@ -299,7 +354,7 @@ pub fn send_command(socket: &mut Poller, command: Command) -> Result<()> {
     buf.write(command)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
 
 pub fn send_settarget(
@@ -312,7 +367,7 @@ pub fn send_settarget(
     buf.write(entity)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
 
 pub fn send_closestorage(socket: &mut Poller) -> Result<()> {
@@ -321,7 +376,7 @@ pub fn send_closestorage(socket: &mut Poller) -> Result<()> {
     buf.write(ClientPacket::CloseStorage)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
 
 pub fn send_closeshop(socket: &mut Poller) -> Result<()> {
@@ -330,7 +385,7 @@ pub fn send_closeshop(socket: &mut Poller) -> Result<()> {
     buf.write(ClientPacket::CloseShop)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
 
 pub fn send_closetrade(socket: &mut Poller) -> Result<()> {
@@ -339,7 +394,7 @@ pub fn send_closetrade(socket: &mut Poller) -> Result<()> {
     buf.write(ClientPacket::CloseTrade)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
 
 pub fn send_buyitem(socket: &mut Poller, slot: u16) -> Result<()> {
@@ -349,7 +404,7 @@ pub fn send_buyitem(socket: &mut Poller, slot: u16) -> Result<()> {
     buf.write(slot)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
 
 pub fn send_sellitem(
@@ -364,7 +419,7 @@ pub fn send_sellitem(
     buf.write(amount)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
 
 pub fn send_addtradeitem(
@@ -379,7 +434,7 @@ pub fn send_addtradeitem(
     buf.write(amount)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
 
 pub fn send_removetradeitem(
@@ -394,7 +449,7 @@ pub fn send_removetradeitem(
     buf.write(amount)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
 
 pub fn send_updatetrademoney(socket: &mut Poller, amount: u64) -> Result<()> {
@@ -404,7 +459,7 @@ pub fn send_updatetrademoney(socket: &mut Poller, amount: u64) -> Result<()> {
     buf.write(amount)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
 
 pub fn send_submittrade(socket: &mut Poller) -> Result<()> {
@@ -413,7 +468,7 @@ pub fn send_submittrade(socket: &mut Poller) -> Result<()> {
     buf.write(ClientPacket::SubmitTrade)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
 
 pub fn send_accepttrade(socket: &mut Poller) -> Result<()> {
@@ -422,7 +477,7 @@ pub fn send_accepttrade(socket: &mut Poller) -> Result<()> {
     buf.write(ClientPacket::AcceptTrade)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
 
 pub fn send_declinetrade(socket: &mut Poller) -> Result<()> {
@@ -431,17 +486,21 @@ pub fn send_declinetrade(socket: &mut Poller) -> Result<()> {
     buf.write(ClientPacket::DeclineTrade)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
 
-pub fn send_ping(socket: &mut Poller) -> Result<()> {
+pub fn online_check_packet() -> Result<MByteBuffer> {
     let mut buf = MByteBuffer::new_packet()?;
 
     buf.write(ClientPacket::OnlineCheck)?;
     buf.write(0u64)?;
     buf.finish()?;
 
-    socket.send(buf, true)
+    Ok(buf)
+}
+
+pub fn send_ping(socket: &mut Poller) -> Result<()> {
+    socket.send(online_check_packet()?, CHANNEL_TLS)
 }
 
 pub fn send_gameping(socket: &mut Poller) -> Result<()> {
@@ -451,7 +510,7 @@ pub fn send_gameping(socket: &mut Poller) -> Result<()> {
     buf.write(0u64)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
 
 pub fn send_tls_reconnect(socket: &mut Poller, code: &str) -> Result<()> {
@@ -461,20 +520,51 @@ pub fn send_tls_reconnect(socket: &mut Poller, code: &str) -> Result<()> {
     buf.write_str(code)?;
     buf.finish()?;
 
-    socket.send(buf, true)
+    socket.send_direct(buf, CHANNEL_TLS)
 }
 
 pub fn send_tls_handshake(
     socket: &mut Poller,
     handshake: String,
+    content_hash: String,
 ) -> Result<()> {
     let mut buf = MByteBuffer::new_packet()?;
 
     buf.write(ClientPacket::TlsHandShake)?;
     buf.write(handshake)?;
+    buf.write(PROTOCOL_VERSION)?;
+    buf.write(APP_MAJOR)?;
+    buf.write(APP_MINOR)?;
+    buf.write(APP_REV)?;
+    buf.write(Capabilities::client())?;
+    // Read by the server because the client offers CONTENT_SYNC.
+    buf.write(content_hash)?;
     buf.finish()?;
 
-    socket.send(buf, true)
+    socket.send_direct(buf, CHANNEL_TLS)
+}
+
+/// Asks for the data files that differ from the server's, at most
+/// CONTENT_REQUEST_BATCH per packet.
+pub fn send_request_content(
+    socket: &mut Poller,
+    paths: &[String],
+) -> Result<()> {
+    for batch in paths.chunks(CONTENT_REQUEST_BATCH) {
+        let mut buf = MByteBuffer::new_packet()?;
+
+        buf.write(ClientPacket::RequestContent)?;
+        buf.write(batch.len() as u16)?;
+
+        for path in batch {
+            buf.write_str(path)?;
+        }
+
+        buf.finish()?;
+        socket.send(buf, CHANNEL_TLS)?;
+    }
+
+    Ok(())
 }
 
 pub fn send_reconnect(socket: &mut Poller, code: &str) -> Result<()> {
@@ -484,7 +574,7 @@ pub fn send_reconnect(socket: &mut Poller, code: &str) -> Result<()> {
     buf.write_str(code)?;
     buf.finish()?;
 
-    socket.send(buf, true)
+    socket.send_direct(buf, CHANNEL_TLS)
 }
 
 pub fn send_disconnect(socket: &mut Poller) -> Result<()> {
@@ -494,7 +584,7 @@ pub fn send_disconnect(socket: &mut Poller) -> Result<()> {
     buf.write(0u32)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
 
 pub fn send_login_ok(socket: &mut Poller, code: &str) -> Result<()> {
@@ -504,5 +594,5 @@ pub fn send_login_ok(socket: &mut Poller, code: &str) -> Result<()> {
     buf.write_str(code)?;
     buf.finish()?;
 
-    socket.send(buf, false)
+    socket.send(buf, CHANNEL_PLAIN)
 }
@@ -0,0 +1,175 @@
+use crate::{ClientError, MyInstant, Result};
+use snafu::Backtrace;
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use super::ChannelId;
+
+/// Identifies a session file and its layout version.
+pub const SESSION_MAGIC: &[u8; 8] = b"ASCSES01";
+
+/// One inbound frame body as it came off a channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedFrame {
+    /// Milliseconds since the recording started.
+    pub time: u64,
+    pub channel: ChannelId,
+    pub data: Vec<u8>,
+}
+
+/// Writes every inbound frame to a session file.
+///
+/// Layout after the magic, per frame: u64 time in ms, u8 channel,
+/// u32 body length, then the body. All numbers are little endian.
+pub struct SessionRecorder {
+    file: BufWriter<File>,
+    start: MyInstant,
+}
+
+impl SessionRecorder {
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+
+        file.write_all(SESSION_MAGIC)?;
+
+        Ok(SessionRecorder {
+            file,
+            start: MyInstant::now(),
+        })
+    }
+
+    pub fn record(&mut self, channel: ChannelId, data: &[u8]) -> Result<()> {
+        let time = self.start.elapsed().as_millis() as u64;
+
+        self.file.write_all(&time.to_le_bytes())?;
+        self.file.write_all(&[channel as u8])?;
+        self.file.write_all(&(data.len() as u32).to_le_bytes())?;
+        self.file.write_all(data)?;
+
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+impl Drop for SessionRecorder {
+    fn drop(&mut self) {
+        if let Err(e) = self.file.flush() {
+            log::error!("Failed to flush session recording: {:?}", e);
+        }
+    }
+}
+
+/// Hands recorded frames back once their original time has passed.
+pub struct SessionReplay {
+    frames: VecDeque<RecordedFrame>,
+    start: MyInstant,
+}
+
+impl SessionReplay {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+        let mut data = Vec::new();
+
+        file.read_to_end(&mut data)?;
+
+        Ok(SessionReplay {
+            frames: parse_session(&data)?,
+            start: MyInstant::now(),
+        })
+    }
+
+    /// Pops the next frame if it is due.
+    pub fn next_due(&mut self) -> Option<RecordedFrame> {
+        let now = self.start.elapsed().as_millis() as u64;
+
+        if self.frames.front()?.time <= now {
+            return self.frames.pop_front();
+        }
+
+        None
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.frames.is_empty()
+    }
+}
+
+fn invalid_session(message: &str) -> ClientError {
+    ClientError::InvalidSession {
+        message: message.to_string(),
+        backtrace: Backtrace::new(),
+    }
+}
+
+pub fn parse_session(data: &[u8]) -> Result<VecDeque<RecordedFrame>> {
+    if !data.starts_with(SESSION_MAGIC) {
+        return Err(invalid_session("missing session header"));
+    }
+
+    let mut frames = VecDeque::new();
+    let mut pos = SESSION_MAGIC.len();
+
+    while pos < data.len() {
+        if data.len() - pos < 13 {
+            return Err(invalid_session("truncated frame header"));
+        }
+
+        let mut time = [0u8; 8];
+        time.copy_from_slice(&data[pos..pos + 8]);
+        let channel = data[pos + 8] as ChannelId;
+        let mut length = [0u8; 4];
+        length.copy_from_slice(&data[pos + 9..pos + 13]);
+        let length = u32::from_le_bytes(length) as usize;
+        pos += 13;
+
+        if data.len() - pos < length {
+            return Err(invalid_session("truncated frame body"));
+        }
+
+        frames.push_back(RecordedFrame {
+            time: u64::from_le_bytes(time),
+            channel,
+            data: data[pos..pos + length].to_vec(),
+        });
+        pos += length;
+    }
+
+    Ok(frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_reads_frames_and_rejects_truncation() {
+        let mut data = SESSION_MAGIC.to_vec();
+
+        data.extend_from_slice(&25u64.to_le_bytes());
+        data.push(1);
+        data.extend_from_slice(&3u32.to_le_bytes());
+        data.extend_from_slice(&[7, 8, 9]);
+
+        let frames = parse_session(&data).unwrap();
+        assert_eq!(
+            frames.front(),
+            Some(&RecordedFrame {
+                time: 25,
+                channel: 1,
+                data: vec![7, 8, 9],
+            })
+        );
+
+        data.pop();
+        assert!(parse_session(&data).is_err());
+        assert!(parse_session(b"nope").is_err());
+    }
+}
@@ -1,6 +1,5 @@
 use crate::{
-    Alert, BufferTask, ClientError, Content, Result, SERVER_ID, SystemHolder,
-    TLS_SERVER_PORT, config::*,
+    Alert, BufferTask, ClientError, Content, Result, SystemHolder, config::*,
 };
 pub use bytey::{ByteBuffer, ByteBufferError, ByteBufferRead, ByteBufferWrite};
 
@@ -25,10 +24,11 @@ use std::{
 };
 
 use super::notls_socket::connect;
+use super::transport::{ChannelId, Transport};
 use super::{
     PacketRouter,
     bufer_ext::ByteBufferExt,
-    handle_data,
+    build_tls_config, describe_cert_error, handle_data,
     states::{ClientState, PollState},
 };
 
@@ -36,36 +36,62 @@ pub struct TlsSocket {
     pub socket: TcpStream,
     pub token: mio::Token,
     pub tls: rustls::ClientConnection,
+    /// Built once for the endpoint, so reconnects do not read the CA
+    /// bundle and client identity again.
+    pub tls_config: Arc<rustls::ClientConfig>,
     pub state: ClientState,
     pub buffer: ByteBuffer,
     pub sends: VecDeque<MByteBuffer>,
     pub poll_state: PollState,
+    /// Why the server certificate was refused, until taken by the Poller.
+    pub trust_error: Option<&'static str>,
 }
 
 ///Creates the Socket and TLS Streams
-fn tls_socket_setup() -> Result<(TcpStream, rustls::ClientConnection)> {
-    let tls_config = build_tls_config()?;
-    let host = SERVER_ID;
-    let socket = connect(host, TLS_SERVER_PORT)?;
-    let server_name = ServerName::try_from(host)?.to_owned();
+fn tls_socket_setup(
+    endpoint: &ServerEndpoint,
+    tls_config: &Arc<rustls::ClientConfig>,
+) -> Result<(TcpStream, rustls::ClientConnection)> {
+    let socket = connect(&endpoint.host, endpoint.tls_port)?;
+    let server_name =
+        ServerName::try_from(endpoint.server_name.as_str())?.to_owned();
 
     Ok((
         socket,
-        rustls::ClientConnection::new(tls_config, server_name)?,
+        rustls::ClientConnection::new(tls_config.clone(), server_name)?,
     ))
 }
 
 impl TlsSocket {
-    pub fn new(poll: &mio::Poll) -> Result<Self> {
-        let (socket, tls) = tls_socket_setup()?;
+    pub fn new(
+        poll: &mio::Poll,
+        endpoint: &ServerEndpoint,
+        channel: ChannelId,
+    ) -> Result<Self> {
+        let tls_config = build_tls_config(&endpoint.trust)?;
+
+        TlsSocket::with_config(poll, endpoint, channel, tls_config)
+    }
+
+    /// Connects with an already built TLS config, for callers that open
+    /// several connections with the same trust settings.
+    pub fn with_config(
+        poll: &mio::Poll,
+        endpoint: &ServerEndpoint,
+        channel: ChannelId,
+        tls_config: Arc<rustls::ClientConfig>,
+    ) -> Result<Self> {
+        let (socket, tls) = tls_socket_setup(endpoint, &tls_config)?;
         let mut socket = TlsSocket {
             socket,
-            token: mio::Token(0),
+            token: mio::Token(channel),
             tls,
+            tls_config,
             state: ClientState::New,
             sends: VecDeque::with_capacity(32),
             buffer: ByteBuffer::new_packet_with(8192)?,
             poll_state: PollState::ReadWrite,
+            trust_error: None,
         };
 
         socket.register(poll)?;
@@ -73,110 +99,11 @@ impl TlsSocket {
         Ok(socket)
     }
 
-    pub fn clear_sends(&mut self) {
-        self.sends.clear();
-    }
-
     pub fn clear_buffer(&mut self) -> Result<()> {
         self.buffer.truncate(0)?;
         Ok(())
     }
 
-    pub fn reconnect(&mut self, poll: &mio::Poll) -> Result<()> {
-        let (socket, tls) = tls_socket_setup()?;
-
-        self.socket = socket;
-        self.tls = tls;
-        self.state = ClientState::New;
-        self.poll_state = PollState::ReadWrite;
-        self.clear_sends();
-        self.clear_buffer()?;
-        self.register(poll)?;
-
-        Ok(())
-    }
-
-    #[inline]
-    #[allow(dead_code)]
-    /// this should properly Close the socket.
-    pub fn set_to_closing(&mut self, poll: &mio::Poll) {
-        self.state = ClientState::Closing;
-        self.reregister(poll).unwrap();
-    }
-
-    pub fn shutdown(&mut self, poll: &mio::Poll) -> Result<()> {
-        poll.registry().deregister(&mut self.socket)?;
-        self.state = ClientState::Closed;
-
-        Ok(())
-    }
-
-    pub fn process(
-        &mut self,
-        event: &mio::event::Event,
-        poll: &mio::Poll,
-    ) -> Result<()> {
-        if self.state == ClientState::New
-            && (event.is_writable() || event.is_readable())
-        {
-            match self.socket.take_error() {
-                Ok(None) => match self.socket.peer_addr() {
-                    Ok(_) => {
-                        self.state = ClientState::Open;
-                        //self.socket.set_nodelay(true)?;
-                    }
-                    Err(err) => {
-                        if let Some(os_err) = err.raw_os_error() {
-                            if os_err == 115 {
-                                self.reregister(poll)?;
-                                return Ok(());
-                            } else {
-                                log::error!(
-                                    "Connection OS Error id: {}",
-                                    os_err
-                                );
-                                return self.shutdown(poll);
-                            }
-                        }
-
-                        if err.kind() == ErrorKind::NotConnected {
-                            self.reregister(poll)?;
-                            return Ok(());
-                        } else {
-                            log::error!(
-                                "Connection Peer Address Error Kind: {:?}",
-                                err.kind()
-                            );
-                            return self.shutdown(poll);
-                        }
-                    }
-                },
-                Ok(Some(err)) | Err(err) => {
-                    log::error!("TLS Connection Error: {:?}", err);
-                    return self.shutdown(poll);
-                }
-            }
-        }
-
-        if event.is_readable() {
-            self.read()?;
-        }
-
-        if event.is_writable() {
-            self.write();
-        }
-
-        match self.state {
-            ClientState::Closing => {
-                poll.registry().deregister(&mut self.socket)?;
-                self.state = ClientState::Closed;
-            }
-            _ => self.reregister(poll)?,
-        }
-
-        Ok(())
-    }
-
     fn read(&mut self) -> Result<()> {
         let pos = self.buffer.cursor();
         self.buffer.move_cursor_to_end();
@@ -206,6 +133,11 @@ impl TlsSocket {
                 Ok(io_state) => io_state,
                 Err(err) => {
                     log::error!("TLS error: {:?}", err);
+
+                    if let rustls::Error::InvalidCertificate(cert_err) = &err {
+                        self.trust_error = Some(describe_cert_error(cert_err));
+                    }
+
                     self.state = ClientState::Closing;
                     return Ok(());
                 }
@@ -313,28 +245,128 @@ impl TlsSocket {
 
         Ok(())
     }
+}
+
+impl Transport for TlsSocket {
+    fn state(&self) -> ClientState {
+        self.state
+    }
+
+    fn process(
+        &mut self,
+        event: &mio::event::Event,
+        poll: &Poll,
+    ) -> Result<()> {
+        if self.state == ClientState::New
+            && (event.is_writable() || event.is_readable())
+        {
+            match self.socket.take_error() {
+                Ok(None) => match self.socket.peer_addr() {
+                    Ok(_) => {
+                        self.state = ClientState::Open;
+                        //self.socket.set_nodelay(true)?;
+                    }
+                    Err(err) => {
+                        if let Some(os_err) = err.raw_os_error() {
+                            if os_err == 115 {
+                                self.reregister(poll)?;
+                                return Ok(());
+                            } else {
+                                log::error!(
+                                    "Connection OS Error id: {}",
+                                    os_err
+                                );
+                                return self.shutdown(poll);
+                            }
+                        }
+
+                        if err.kind() == ErrorKind::NotConnected {
+                            self.reregister(poll)?;
+                            return Ok(());
+                        } else {
+                            log::error!(
+                                "Connection Peer Address Error Kind: {:?}",
+                                err.kind()
+                            );
+                            return self.shutdown(poll);
+                        }
+                    }
+                },
+                Ok(Some(err)) | Err(err) => {
+                    log::error!("TLS Connection Error: {:?}", err);
+                    return self.shutdown(poll);
+                }
+            }
+        }
+
+        if event.is_readable() {
+            self.read()?;
+        }
+
+        if event.is_writable() {
+            self.write();
+        }
 
-    #[inline]
-    #[allow(dead_code)]
-    pub fn send(&mut self, buf: MByteBuffer, poll: &mio::Poll) -> Result<()> {
+        match self.state {
+            ClientState::Closing => {
+                poll.registry().deregister(&mut self.socket)?;
+                self.state = ClientState::Closed;
+            }
+            _ => self.reregister(poll)?,
+        }
+
+        Ok(())
+    }
+
+    fn send(&mut self, buf: MByteBuffer, poll: &Poll) -> Result<()> {
         self.sends.push_back(buf);
         self.poll_state.add(PollState::Write);
         self.reregister(poll)
     }
 
-    pub fn get_length(&mut self, poll: &Poll) -> Option<u64> {
-        if self.buffer.length() - self.buffer.cursor() >= 8 {
-            let length = self.buffer.read::<u64>().ok()?;
+    fn set_to_closing(&mut self, poll: &Poll) {
+        self.state = ClientState::Closing;
+        self.reregister(poll).unwrap();
+    }
 
-            trace!("Length is {}", length);
-            if !(2..=8192).contains(&length) {
-                log::error!("Disconnected on packet get_length");
-                self.set_to_closing(poll);
-            }
+    fn shutdown(&mut self, poll: &Poll) -> Result<()> {
+        poll.registry().deregister(&mut self.socket)?;
+        self.state = ClientState::Closed;
 
-            Some(length)
-        } else {
-            None
-        }
+        Ok(())
+    }
+
+    fn reconnect(
+        &mut self,
+        poll: &Poll,
+        endpoint: &ServerEndpoint,
+    ) -> Result<()> {
+        let (socket, tls) = tls_socket_setup(endpoint, &self.tls_config)?;
+
+        self.socket = socket;
+        self.tls = tls;
+        self.state = ClientState::New;
+        self.poll_state = PollState::ReadWrite;
+        self.clear_sends();
+        self.clear_buffer()?;
+        self.register(poll)?;
+
+        Ok(())
+    }
+
+    fn clear_sends(&mut self) {
+        self.sends.clear();
+    }
+
+    fn take_sends(&mut self) -> VecDeque<MByteBuffer> {
+        std::mem::take(&mut self.sends)
+    }
+
+    fn take_trust_error(&mut self) -> Option<&'static str> {
+        self.trust_error.take()
+    }
+
+    fn buffer(&mut self) -> &mut ByteBuffer {
+        &mut self.buffer
     }
 }
@@ -0,0 +1,51 @@
+use crate::{Result, config::*};
+use mio::Poll;
+use std::collections::VecDeque;
+
+use super::{ByteBuffer, ClientState, MByteBuffer};
+
+/// Index of a channel within the Poller. Also used as its mio Token.
+pub type ChannelId = usize;
+
+/// Encrypted channel used for login, register and reconnect packets.
+pub const CHANNEL_TLS: ChannelId = 0;
+/// Plain channel used for the in game packets.
+pub const CHANNEL_PLAIN: ChannelId = 1;
+
+/// A bidirectional, length prefixed packet stream the Poller can drive.
+/// Implement this to add a new kind of connection to the Poller.
+pub trait Transport {
+    fn state(&self) -> ClientState;
+
+    /// Handles a mio event that was raised for this transport's token.
+    fn process(&mut self, event: &mio::event::Event, poll: &Poll)
+    -> Result<()>;
+
+    /// Queues a finished packet to be written.
+    fn send(&mut self, buf: MByteBuffer, poll: &Poll) -> Result<()>;
+
+    /// this should properly Close the socket.
+    fn set_to_closing(&mut self, poll: &Poll);
+
+    fn shutdown(&mut self, poll: &Poll) -> Result<()>;
+
+    fn reconnect(
+        &mut self,
+        poll: &Poll,
+        endpoint: &ServerEndpoint,
+    ) -> Result<()>;
+
+    fn clear_sends(&mut self);
+
+    /// Removes and returns the packets that were queued but not written.
+    fn take_sends(&mut self) -> VecDeque<MByteBuffer>;
+
+    /// Returns why the server certificate was refused, once. Only the TLS
+    /// transport verifies certificates.
+    fn take_trust_error(&mut self) -> Option<&'static str> {
+        None
+    }
+
+    /// Received bytes that have not been decoded into frames yet.
+    fn buffer(&mut self) -> &mut ByteBuffer;
+}
@@ -0,0 +1,425 @@
+use log::{info, warn};
+use rustls::{
+    CertificateError, ClientConfig, DigitallySignedStruct, Error,
+    RootCertStore, SignatureScheme,
+    client::{
+        WebPkiServerVerifier,
+        danger::{
+            HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+        },
+    },
+    crypto::{CryptoProvider, ring as provider},
+    pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime},
+};
+use snafu::Backtrace;
+use std::{
+    fs,
+    io::{self, BufReader},
+    path::Path,
+    sync::Arc,
+};
+
+use crate::{
+    ClientError, ClientIdentity, DEFAULT_CA_BUNDLE, Result, TrustSettings,
+    restrict_private_file, sha256_hex, write_private_file,
+};
+
+pub fn trust_error(message: String) -> ClientError {
+    ClientError::TlsTrust {
+        message,
+        backtrace: Backtrace::new(),
+    }
+}
+
+/// Returns the DER encoded SubjectPublicKeyInfo of an X.509 certificate.
+pub fn spki_der(cert: &[u8]) -> Option<&[u8]> {
+    let (_, cert) = x509_parser::parse_x509_certificate(cert).ok()?;
+    Some(cert.tbs_certificate.subject_pki.raw)
+}
+
+/// Lower case hex SHA-256 of the certificate's SubjectPublicKeyInfo, the
+/// format `pinned_spki` in settings.toml expects.
+pub fn spki_fingerprint(cert: &[u8]) -> Option<String> {
+    spki_der(cert).map(sha256_hex)
+}
+
+/// Pins are compared without case or `:` separators.
+fn normalize_pin(pin: &str) -> String {
+    pin.chars()
+        .filter(|c| *c != ':' && !c.is_whitespace())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// True when the pin is a hex SHA-256 hash.
+pub fn is_valid_pin(pin: &str) -> bool {
+    let pin = normalize_pin(pin);
+    pin.len() == 64 && pin.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Runs the normal webpki checks and then requires the server key to be one
+/// of the pinned keys.
+#[derive(Debug)]
+pub struct PinnedVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    pins: Vec<String>,
+}
+
+impl PinnedVerifier {
+    pub fn new(inner: Arc<WebPkiServerVerifier>, pins: &[String]) -> Self {
+        PinnedVerifier {
+            inner,
+            pins: pins.iter().map(|pin| normalize_pin(pin)).collect(),
+        }
+    }
+
+    pub fn is_pinned(&self, cert: &[u8]) -> bool {
+        spki_fingerprint(cert)
+            .is_some_and(|fingerprint| self.pins.contains(&fingerprint))
+    }
+}
+
+impl ServerCertVerifier for PinnedVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, Error> {
+        self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        )?;
+
+        if !self.is_pinned(end_entity) {
+            log::error!(
+                "Server key {} is not in pinned_spki",
+                spki_fingerprint(end_entity)
+                    .unwrap_or_else(|| "<unreadable>".into())
+            );
+
+            return Err(Error::InvalidCertificate(
+                CertificateError::ApplicationVerificationFailure,
+            ));
+        }
+
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+/// Creates a self signed device certificate and its key. The server links
+/// the printed fingerprint to an account to allow it.
+pub fn generate_device_cert(cert_path: &str, key_path: &str) -> Result<()> {
+    let key = rcgen::KeyPair::generate()
+        .map_err(|e| trust_error(format!("device key: {e}")))?;
+    let mut params = rcgen::CertificateParams::new(Vec::new())
+        .map_err(|e| trust_error(format!("device certificate: {e}")))?;
+    params.distinguished_name.push(
+        rcgen::DnType::CommonName,
+        format!("ascending-device-{}", uuid::Uuid::now_v7()),
+    );
+    let cert = params
+        .self_signed(&key)
+        .map_err(|e| trust_error(format!("device certificate: {e}")))?;
+
+    for path in [cert_path, key_path] {
+        if let Some(parent) = Path::new(path).parent() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+
+    write_private_file(key_path, key.serialize_pem().as_bytes())?;
+    fs::write(cert_path, cert.pem())?;
+
+    log::info!(
+        "Created device certificate {}, SPKI {}",
+        cert_path,
+        spki_fingerprint(cert.der()).unwrap_or_default()
+    );
+
+    Ok(())
+}
+
+fn load_certs(filename: &str) -> Result<Vec<CertificateDer<'static>>> {
+    let certfile = fs::File::open(filename)?;
+    let mut reader = BufReader::new(certfile);
+
+    Ok(rustls_pemfile::certs(&mut reader).collect::<io::Result<_>>()?)
+}
+
+fn load_private_key(filename: &str) -> Result<PrivateKeyDer<'static>> {
+    let keyfile = fs::File::open(filename)?;
+    let mut reader = BufReader::new(keyfile);
+
+    loop {
+        match rustls_pemfile::read_one(&mut reader)? {
+            Some(rustls_pemfile::Item::Pkcs1Key(key)) => return Ok(key.into()),
+            Some(rustls_pemfile::Item::Pkcs8Key(key)) => return Ok(key.into()),
+            Some(rustls_pemfile::Item::Sec1Key(key)) => return Ok(key.into()),
+            None => break,
+            _ => {}
+        }
+    }
+
+    Err(trust_error(format!(
+        "no keys found in {} (encrypted keys not supported)",
+        filename
+    )))
+}
+
+/// Loads the client certificate chain and key, creating the device
+/// certificate first when it is missing and allowed to be generated.
+pub fn load_client_identity(
+    identity: &ClientIdentity,
+) -> Result<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)> {
+    if identity.generate {
+        if Path::new(&identity.cert).exists()
+            && Path::new(&identity.key).exists()
+        {
+            // Keys from older builds were written with the default mode.
+            if let Err(e) = restrict_private_file(&identity.key) {
+                warn!("Could not restrict {}: {}", identity.key, e);
+            }
+        } else {
+            generate_device_cert(&identity.cert, &identity.key)?;
+        }
+    }
+
+    let certs = load_certs(&identity.cert)?;
+
+    if certs.is_empty() {
+        return Err(trust_error(format!(
+            "no certificates found in {}",
+            identity.cert
+        )));
+    }
+
+    Ok((certs, load_private_key(&identity.key)?))
+}
+
+/// Root store from the CA bundle. Without a `ca_bundle` in settings.toml
+/// the default bundle is used, or the public webpki roots when it is
+/// missing or holds no certificates. A bundle the player set must load,
+/// falling back would trust any public CA for the server name.
+fn load_root_store(trust: &TrustSettings) -> Result<RootCertStore> {
+    let path = trust.ca_bundle.as_deref().unwrap_or(DEFAULT_CA_BUNDLE);
+    let mut root_store = RootCertStore::empty();
+
+    let loaded = load_certs(path).map(|certs| {
+        let (added, ignored) = root_store.add_parsable_certificates(certs);
+
+        if ignored > 0 {
+            warn!("Ignored {} invalid certificates in {}", ignored, path);
+        }
+
+        added
+    });
+
+    match (loaded, &trust.ca_bundle) {
+        (Ok(added), _) if added > 0 => return Ok(root_store),
+        (Ok(_), Some(_)) => {
+            return Err(trust_error(format!("no certificates in {path}")));
+        }
+        (Err(e), Some(_)) => {
+            return Err(trust_error(format!("could not read {path}: {e}")));
+        }
+        (Ok(_), None) => warn!("No certificates in {}", path),
+        (Err(e), None) => warn!("Could not load {}: {}", path, e),
+    }
+
+    info!("Using the public webpki roots for the TLS channel");
+    root_store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    Ok(root_store)
+}
+
+pub fn build_tls_config(
+    trust: &TrustSettings,
+) -> Result<Arc<rustls::ClientConfig>> {
+    let provider = Arc::new(CryptoProvider {
+        cipher_suites: provider::DEFAULT_CIPHER_SUITES.to_vec(),
+        ..provider::default_provider()
+    });
+    let root_store = Arc::new(load_root_store(trust)?);
+
+    let builder = ClientConfig::builder_with_provider(provider.clone())
+        .with_protocol_versions(rustls::DEFAULT_VERSIONS)?;
+
+    let builder = if trust.pinned_spki.is_empty() {
+        builder.with_root_certificates(root_store)
+    } else {
+        let verifier =
+            WebPkiServerVerifier::builder_with_provider(root_store, provider)
+                .build()
+                .map_err(|e| trust_error(e.to_string()))?;
+
+        builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(PinnedVerifier::new(
+                verifier,
+                &trust.pinned_spki,
+            )))
+    };
+
+    let config = match &trust.identity {
+        Some(identity) => {
+            let (certs, key) = load_client_identity(identity)?;
+            builder.with_client_auth_cert(certs, key)?
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    Ok(Arc::new(config))
+}
+
+/// Short reason shown to the player when the server certificate was
+/// refused.
+pub fn describe_cert_error(error: &CertificateError) -> &'static str {
+    match error {
+        CertificateError::Expired => "its certificate has expired",
+        CertificateError::NotValidYet => "its certificate is not valid yet",
+        CertificateError::Revoked => "its certificate was revoked",
+        CertificateError::UnknownIssuer => {
+            "its certificate is signed by an untrusted authority"
+        }
+        CertificateError::NotValidForName => {
+            "its certificate does not match the server name"
+        }
+        CertificateError::ApplicationVerificationFailure => {
+            "its key does not match the pinned key"
+        }
+        _ => "its certificate is invalid",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spki_matches_generated_key() {
+        let key = rcgen::KeyPair::generate().unwrap();
+        let cert = rcgen::CertificateParams::new(vec!["localhost".into()])
+            .unwrap()
+            .self_signed(&key)
+            .unwrap();
+
+        assert_eq!(spki_der(cert.der()), Some(key.public_key_der().as_slice()));
+
+        let fingerprint = spki_fingerprint(cert.der()).unwrap();
+        assert_eq!(fingerprint.len(), 64);
+        assert_eq!(
+            normalize_pin(&fingerprint.to_uppercase()),
+            fingerprint.as_str()
+        );
+    }
+
+    #[test]
+    fn truncated_cert_is_rejected() {
+        let key = rcgen::KeyPair::generate().unwrap();
+        let cert = rcgen::CertificateParams::new(vec!["localhost".into()])
+            .unwrap()
+            .self_signed(&key)
+            .unwrap();
+        let der = cert.der();
+
+        assert_eq!(spki_der(&der[..der.len() / 2]), None);
+        assert_eq!(spki_der(&[0x30, 0x82, 0xff]), None);
+    }
+
+    #[test]
+    fn device_cert_is_created_once() {
+        let dir = std::env::temp_dir()
+            .join(format!("ascending-device-{}", uuid::Uuid::now_v7()));
+        let identity = ClientIdentity {
+            cert: dir.join("crt.pem").to_string_lossy().into_owned(),
+            key: dir.join("key.pem").to_string_lossy().into_owned(),
+            generate: true,
+        };
+
+        let (certs, _) = load_client_identity(&identity).unwrap();
+        let first = spki_fingerprint(&certs[0]).unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&identity.key).unwrap().permissions();
+            assert_eq!(mode.mode() & 0o777, 0o600);
+
+            // A key left readable by others is locked down on load.
+            fs::set_permissions(
+                &identity.key,
+                fs::Permissions::from_mode(0o644),
+            )
+            .unwrap();
+        }
+
+        let (certs, _) = load_client_identity(&identity).unwrap();
+        assert_eq!(spki_fingerprint(&certs[0]).unwrap(), first);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&identity.key).unwrap().permissions();
+            assert_eq!(mode.mode() & 0o777, 0o600);
+        }
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn explicit_ca_bundle_must_load() {
+        let dir = std::env::temp_dir()
+            .join(format!("ascending-ca-{}", uuid::Uuid::now_v7()));
+        fs::create_dir_all(&dir).unwrap();
+        let empty = dir.join("empty.pem");
+        fs::write(&empty, "").unwrap();
+
+        for path in [empty, dir.join("missing.pem")] {
+            let trust = TrustSettings {
+                ca_bundle: Some(path.to_string_lossy().into_owned()),
+                ..Default::default()
+            };
+
+            assert!(matches!(
+                load_root_store(&trust),
+                Err(ClientError::TlsTrust { .. })
+            ));
+        }
+
+        // Without a ca_bundle there is always something to trust.
+        assert!(
+            !load_root_store(&TrustSettings::default())
+                .unwrap()
+                .is_empty()
+        );
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
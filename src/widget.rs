@@ -1,7 +1,10 @@
 pub mod alert;
 pub mod button;
 pub mod checkbox;
+pub mod content_sync_screen;
 pub mod label;
+pub mod net_stats_overlay;
+pub mod reconnect_overlay;
 pub mod scrollbar;
 pub mod textbox;
 pub mod tooltip;
@@ -9,7 +12,10 @@ pub mod tooltip;
 pub use alert::*;
 pub use button::*;
 pub use checkbox::*;
+pub use content_sync_screen::*;
 pub use label::*;
+pub use net_stats_overlay::*;
+pub use reconnect_overlay::*;
 pub use scrollbar::*;
 pub use textbox::*;
 pub use tooltip::*;
@@ -7,7 +7,8 @@ use crate::{
     send_dropitem, send_removetradeitem, send_sellitem, send_switchinvslot,
     send_switchstorageslot, send_withdrawitem, socket,
     systems::{
-        FADE_SWITCH_TO_TITLE, FadeData, FadeType, Poller, send_disconnect,
+        CHANNEL_PLAIN, CHANNEL_TLS, FADE_SWITCH_TO_TITLE, FadeData, FadeType,
+        Poller, send_disconnect,
     },
     widget::*,
 };
@@ -36,6 +37,7 @@ pub enum AlertIndex {
     Offline,
     ExitGame,
     Disconnect,
+    UpdateRequired,
 }
 
 pub struct AlertTextbox {
@@ -599,7 +601,7 @@ impl Alert {
                     AlertIndex::Offline => {
                         //elwt.exit();
                     }
-                    AlertIndex::Disconnect => {
+                    AlertIndex::Disconnect | AlertIndex::UpdateRequired => {
                         systems.fade.init_fade(
                             &mut systems.gfx,
                             FadeType::In,
@@ -621,8 +623,8 @@ impl Alert {
                         }
                         AlertIndex::ExitGame => {
                             if content.content_type == ContentType::Game {
-                                socket.socket.clear_sends();
-                                socket.tls_socket.clear_sends();
+                                socket.clear_sends(CHANNEL_PLAIN);
+                                socket.clear_sends(CHANNEL_TLS);
                                 send_disconnect(socket)?;
                                 systems.fade.init_fade(
                                     &mut systems.gfx,
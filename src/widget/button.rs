@@ -48,20 +48,23 @@ pub struct ButtonContentText {
     pub click_change: ButtonChangeType,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub enum ButtonType {
+    #[default]
     None,
     Rect(ButtonRect),
     Image(ButtonImage),
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub enum ButtonContentType {
+    #[default]
     None,
     Image(ButtonContentImg),
     Text(ButtonContentText),
 }
 
+#[derive(Default)]
 pub struct Button {
     visible: bool,
     index: Option<GfxType>,
@@ -249,6 +252,10 @@ impl Button {
         }
     }
 
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
     pub fn set_z_order(&mut self, systems: &mut SystemHolder, z_order: f32) {
         self.z_order = z_order;
 
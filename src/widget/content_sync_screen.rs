@@ -0,0 +1,145 @@
+use graphics::*;
+
+use crate::{SyncProgress, SystemHolder, create_label, data_types::*};
+
+/// Covers the menu while game data is downloaded from the server, so the
+/// player can not log in before it is done.
+pub struct ContentSyncScreen {
+    shade: GfxType,
+    window: GfxType,
+    bar: GfxType,
+    fill: GfxType,
+    text: GfxType,
+    bar_width: f32,
+    shown: Option<SyncProgress>,
+}
+
+impl ContentSyncScreen {
+    pub fn new(systems: &mut SystemHolder) -> Self {
+        let scale = systems.scale as f32;
+
+        let mut shade_rect = Rect::new(&mut systems.renderer, 0);
+        shade_rect
+            .set_position(Vec3::new(0.0, 0.0, ORDER_SYNC_SHADE))
+            .set_size(Vec2::new(systems.size.width, systems.size.height))
+            .set_color(Color::rgba(0, 0, 0, 160));
+        let shade = systems.gfx.add_rect(shade_rect, 4, "Sync Shade", false);
+
+        let size = (Vec2::new(320.0, 72.0) * scale).floor();
+        let pos = Vec2::new(
+            ((systems.size.width - size.x) * 0.5).floor(),
+            ((systems.size.height - size.y) * 0.5).floor(),
+        );
+
+        let mut window_rect = Rect::new(&mut systems.renderer, 0);
+        window_rect
+            .set_position(Vec3::new(pos.x, pos.y, ORDER_SYNC_WINDOW))
+            .set_size(size)
+            .set_color(Color::rgba(40, 40, 40, 240))
+            .set_border_width(1.0)
+            .set_border_color(Color::rgba(10, 10, 10, 255));
+        let window = systems.gfx.add_rect(window_rect, 4, "Sync Window", false);
+
+        let bar_pos = pos + (Vec2::new(16.0, 14.0) * scale).floor();
+        let bar_size = Vec2::new(size.x - (32.0 * scale).floor(), 14.0 * scale);
+
+        let mut bar_rect = Rect::new(&mut systems.renderer, 0);
+        bar_rect
+            .set_position(Vec3::new(bar_pos.x, bar_pos.y, ORDER_SYNC_BAR))
+            .set_size(bar_size)
+            .set_color(Color::rgba(20, 20, 20, 255));
+        let bar = systems.gfx.add_rect(bar_rect, 4, "Sync Bar", false);
+
+        let mut fill_rect = Rect::new(&mut systems.renderer, 0);
+        fill_rect
+            .set_position(Vec3::new(bar_pos.x, bar_pos.y, ORDER_SYNC_FILL))
+            .set_size(Vec2::new(0.0, bar_size.y))
+            .set_color(Color::rgba(90, 160, 90, 255));
+        let fill = systems.gfx.add_rect(fill_rect, 4, "Sync Bar Fill", false);
+
+        let text_pos =
+            Vec2::new(pos.x, pos.y + size.y - (32.0 * scale).floor());
+        let text_label = create_label(
+            systems,
+            Vec3::new(text_pos.x, text_pos.y, ORDER_SYNC_TEXT),
+            Vec2::new(size.x, (20.0 * scale).floor()),
+            Bounds::new(
+                text_pos.x,
+                text_pos.y,
+                text_pos.x + size.x,
+                text_pos.y + (20.0 * scale).floor(),
+            ),
+            Color::rgba(240, 240, 240, 255),
+        );
+        let text = systems.gfx.add_text(text_label, 5, "Sync Text", false);
+
+        ContentSyncScreen {
+            shade,
+            window,
+            bar,
+            fill,
+            text,
+            bar_width: bar_size.x,
+            shown: None,
+        }
+    }
+
+    /// Shows the progress, or hides the screen once there is none.
+    pub fn update(
+        &mut self,
+        systems: &mut SystemHolder,
+        progress: Option<SyncProgress>,
+    ) {
+        if self.shown == progress {
+            return;
+        }
+
+        let Some(progress) = progress else {
+            for gfx in [self.shade, self.window, self.bar, self.fill, self.text]
+            {
+                systems.gfx.set_visible(&gfx, false);
+            }
+
+            self.shown = None;
+            return;
+        };
+
+        let msg = if progress.files_total == 0 {
+            "Checking game data\u{2026}".to_string()
+        } else {
+            format!(
+                "Updating game data {} / {} ({} / {} KB)",
+                progress.files_done,
+                progress.files_total,
+                progress.bytes_done / 1024,
+                progress.bytes_total.div_ceil(1024)
+            )
+        };
+        let done = if progress.bytes_total == 0 {
+            0.0
+        } else {
+            progress.bytes_done as f32 / progress.bytes_total as f32
+        };
+
+        systems
+            .gfx
+            .set_text(&mut systems.renderer, &self.text, &msg);
+        systems.gfx.center_text(&self.text);
+        systems.gfx.set_size(
+            &self.fill,
+            Vec2::new(
+                (self.bar_width * done).floor(),
+                14.0 * systems.scale as f32,
+            ),
+        );
+
+        if self.shown.is_none() {
+            for gfx in [self.shade, self.window, self.bar, self.fill, self.text]
+            {
+                systems.gfx.set_visible(&gfx, true);
+            }
+        }
+
+        self.shown = Some(progress);
+    }
+}
@@ -0,0 +1,143 @@
+use graphics::*;
+use std::fmt::Write;
+
+use crate::{
+    MovePrediction, Poller, SystemHolder, create_label, data_types::*,
+};
+
+/// Packet types listed per direction.
+const NET_STATS_ROWS: usize = 8;
+
+/// Debug panel with traffic per packet type, toggled with
+/// `Action::ToggleNetStats`, F5 by default.
+#[derive(Default)]
+pub struct NetStatsOverlay {
+    window: GfxType,
+    text: GfxType,
+    pub visible: bool,
+}
+
+impl NetStatsOverlay {
+    pub fn new(systems: &mut SystemHolder) -> Self {
+        let size = (Vec2::new(300.0, 330.0) * systems.scale as f32).floor();
+        let pos = Vec2::new(
+            (10.0 * systems.scale as f32).floor(),
+            systems.size.height
+                - size.y
+                - (40.0 * systems.scale as f32).floor(),
+        );
+
+        let mut window_rect = Rect::new(&mut systems.renderer, 0);
+        window_rect
+            .set_position(Vec3::new(pos.x, pos.y, ORDER_NET_STATS_BG))
+            .set_size(size)
+            .set_color(Color::rgba(20, 20, 20, 200))
+            .set_border_width(1.0)
+            .set_border_color(Color::rgba(10, 10, 10, 255));
+        let window =
+            systems
+                .gfx
+                .add_rect(window_rect, 4, "Net Stats Window", false);
+
+        let text_pos = pos + (Vec2::new(6.0, 4.0) * systems.scale as f32);
+        let text_size = size - (Vec2::new(12.0, 8.0) * systems.scale as f32);
+        let mut text_label = create_label(
+            systems,
+            Vec3::new(text_pos.x, text_pos.y, ORDER_NET_STATS_TEXT),
+            text_size,
+            Bounds::new(
+                text_pos.x,
+                text_pos.y,
+                text_pos.x + text_size.x,
+                text_pos.y + text_size.y,
+            ),
+            Color::rgba(220, 220, 220, 255),
+        );
+        text_label.set_buffer_size(
+            &mut systems.renderer,
+            Some(text_size.x),
+            Some(text_size.y),
+        );
+        let text = systems.gfx.add_text(text_label, 5, "Net Stats Text", false);
+
+        NetStatsOverlay {
+            window,
+            text,
+            visible: false,
+        }
+    }
+
+    pub fn toggle(
+        &mut self,
+        systems: &mut SystemHolder,
+        socket: &Poller,
+        prediction: &MovePrediction,
+    ) {
+        self.visible = !self.visible;
+        systems.gfx.set_visible(&self.window, self.visible);
+        systems.gfx.set_visible(&self.text, self.visible);
+        self.update(systems, socket, prediction);
+    }
+
+    pub fn update(
+        &mut self,
+        systems: &mut SystemHolder,
+        socket: &Poller,
+        prediction: &MovePrediction,
+    ) {
+        if !self.visible {
+            return;
+        }
+
+        let metrics = &socket.metrics;
+        let (rate_in, rate_out) = metrics.rates();
+        let total_in = metrics.total_in();
+        let total_out = metrics.total_out();
+        let mut msg = String::with_capacity(1024);
+
+        let _ = writeln!(
+            msg,
+            "In: {} frames, {} B ({:.0} B/s)",
+            total_in.frames, total_in.bytes, rate_in
+        );
+        let _ = writeln!(
+            msg,
+            "Out: {} frames, {} B ({:.0} B/s)",
+            total_out.frames, total_out.bytes, rate_out
+        );
+        let _ = writeln!(
+            msg,
+            "Compression saved: {} B",
+            socket.compression.total()
+        );
+        let _ = writeln!(
+            msg,
+            "Predicted moves: {}, {} wrong ({:.1}%)",
+            prediction.predicted,
+            prediction.mispredicted,
+            prediction.error_rate() * 100.0
+        );
+        let _ = writeln!(msg, "\nServer packets (frames, bytes, handler ms)");
+
+        for (id, stat) in metrics.top_inbound().iter().take(NET_STATS_ROWS) {
+            let _ = writeln!(
+                msg,
+                "{:?}: {}, {}, {:.2}",
+                id,
+                stat.frames,
+                stat.bytes,
+                stat.handler_time.as_secs_f64() * 1000.0
+            );
+        }
+
+        let _ = writeln!(msg, "\nClient packets (frames, bytes)");
+
+        for (id, stat) in metrics.top_outbound().iter().take(NET_STATS_ROWS) {
+            let _ = writeln!(msg, "{:?}: {}, {}", id, stat.frames, stat.bytes);
+        }
+
+        systems
+            .gfx
+            .set_text(&mut systems.renderer, &self.text, &msg);
+    }
+}
@@ -0,0 +1,89 @@
+use graphics::*;
+
+use crate::{SystemHolder, create_label, data_types::*};
+
+/// Banner shown at the top of the screen while the connection is being
+/// restored.
+pub struct ReconnectOverlay {
+    window: GfxType,
+    text: GfxType,
+    attempt: Option<u32>,
+}
+
+impl ReconnectOverlay {
+    pub fn new(systems: &mut SystemHolder) -> Self {
+        let size = (Vec2::new(260.0, 28.0) * systems.scale as f32).floor();
+        let pos = Vec2::new(
+            ((systems.size.width - size.x) * 0.5).floor(),
+            systems.size.height
+                - size.y
+                - (20.0 * systems.scale as f32).floor(),
+        );
+
+        let mut window_rect = Rect::new(&mut systems.renderer, 0);
+        window_rect
+            .set_position(Vec3::new(pos.x, pos.y, ORDER_RECONNECT_BG))
+            .set_size(size)
+            .set_color(Color::rgba(40, 40, 40, 220))
+            .set_border_width(1.0)
+            .set_border_color(Color::rgba(10, 10, 10, 255));
+        let window =
+            systems
+                .gfx
+                .add_rect(window_rect, 4, "Reconnect Overlay", false);
+
+        let text_pos =
+            Vec2::new(pos.x, pos.y + (4.0 * systems.scale as f32).floor());
+        let text_label = create_label(
+            systems,
+            Vec3::new(text_pos.x, text_pos.y, ORDER_RECONNECT_TEXT),
+            Vec2::new(size.x, (20.0 * systems.scale as f32).floor()),
+            Bounds::new(
+                text_pos.x,
+                text_pos.y,
+                text_pos.x + size.x,
+                text_pos.y + (20.0 * systems.scale as f32).floor(),
+            ),
+            Color::rgba(240, 240, 240, 255),
+        );
+        let text = systems.gfx.add_text(
+            text_label,
+            5,
+            "Reconnect Overlay Text",
+            false,
+        );
+
+        ReconnectOverlay {
+            window,
+            text,
+            attempt: None,
+        }
+    }
+
+    pub fn show(&mut self, systems: &mut SystemHolder, attempt: u32) {
+        if self.attempt == Some(attempt) {
+            return;
+        }
+
+        systems.gfx.set_text(
+            &mut systems.renderer,
+            &self.text,
+            &format!("Reconnecting (attempt {attempt})\u{2026}"),
+        );
+        systems.gfx.center_text(&self.text);
+
+        if self.attempt.is_none() {
+            systems.gfx.set_visible(&self.window, true);
+            systems.gfx.set_visible(&self.text, true);
+        }
+
+        self.attempt = Some(attempt);
+    }
+
+    pub fn hide(&mut self, systems: &mut SystemHolder) {
+        if self.attempt.take().is_some() {
+            systems.gfx.set_visible(&self.window, false);
+            systems.gfx.set_visible(&self.text, false);
+        }
+    }
+}
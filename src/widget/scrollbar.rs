@@ -1,4 +1,5 @@
 use crate::{is_within_area, logic::*, GfxType, SystemHolder};
+use educe::Educe;
 use graphics::*;
 
 pub struct ScrollbarBackground {
@@ -19,6 +20,8 @@ pub struct ScrollbarRect {
     pub radius: f32,
 }
 
+#[derive(Educe)]
+#[educe(Default)]
 pub struct Scrollbar {
     pub visible: bool,
     is_vertical: bool,
@@ -40,8 +43,11 @@ pub struct Scrollbar {
     end_pos: usize,
     length: usize,
     min_bar_size: f32,
+    #[educe(Default = Color::rgba(0, 0, 0, 0))]
     default_color: Color,
+    #[educe(Default = Color::rgba(0, 0, 0, 0))]
     hover_color: Color,
+    #[educe(Default = Color::rgba(0, 0, 0, 0))]
     hold_color: Color,
     in_hover: bool,
     pub in_hold: bool,
@@ -18,6 +18,7 @@ pub enum TextDisable {
     Paste,
 }
 
+#[derive(Default)]
 pub struct Textbox {
     visible: bool,
     pub text: String,